@@ -95,6 +95,554 @@ test.serial('should not be same entry as others for file', async (t) => {
   t.false(await fileHandle2.isSameEntry(rootHandle));
 })
 
+test.serial('should not be same entry for files with same name in different directories', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const dirHandle2 = await rootHandle.getDirectoryHandle('quatre');
+  const fileHandle = await dirHandle.getFileHandle('points', {create: true});
+  const fileHandle2 = await dirHandle2.getFileHandle('points');
+  t.false(await fileHandle.isSameEntry(fileHandle2));
+  t.false(await fileHandle2.isSameEntry(fileHandle));
+  t.true(await fileHandle.isSameEntry(await dirHandle.getFileHandle('points')));
+  await dirHandle.removeEntry('points');
+})
+
+test.serial('should return same unique id for same entry', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar');
+  const fileHandle2 = await rootHandle.getFileHandle('annar');
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const id = await fileHandle.getUniqueId();
+  t.is(id, await fileHandle2.getUniqueId());
+  t.not(id, await dirHandle.getUniqueId());
+  t.not(id, await rootHandle.getUniqueId());
+})
+
+test.serial('should reopen serialized directory handle', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const reopened = NfsDirectoryHandle.deserialize(dirHandle.serialize());
+  t.is(reopened.kind, 'directory');
+  t.is(reopened.name, 'first');
+  t.is(await reopened.getUniqueId(), await dirHandle.getUniqueId());
+  t.true(await reopened.isSameEntry(dirHandle));
+  const keys = [];
+  for await (const key of (reopened as NfsDirectoryHandle).keys()) {
+    keys.push(key);
+  }
+  t.deepEqual(keys, ['comment']);
+})
+
+test.serial('should reopen serialized file handle', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar');
+  const reopened = NfsDirectoryHandle.deserialize(fileHandle.serialize(), {url: nfsURL});
+  t.is(reopened.kind, 'file');
+  t.is(reopened.name, 'annar');
+  t.true(await reopened.isSameEntry(fileHandle));
+  const file = await (reopened as NfsFileHandle).getFile();
+  t.is(file.size, 123);
+})
+
+test.serial('should refresh path of serialized handle and check ids when reopening by path', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const fileHandle = await dirHandle.getFileHandle('comment');
+  const serialized = JSON.parse(fileHandle.serialize());
+  if (serialized.fh !== undefined) {
+    const renamed = NfsDirectoryHandle.deserialize(JSON.stringify({...serialized, path: '/first/old-comment', name: 'old-comment'}));
+    t.is(renamed.name, 'comment');
+    t.is(JSON.parse(renamed.serialize()).path, '/first/comment');
+  }
+  delete serialized.fh;
+  t.throws(() => NfsDirectoryHandle.deserialize(JSON.stringify({...serialized, fileid: '1'})));
+})
+
+test.serial('should return error when deserializing invalid handle', async (t) => {
+  t.throws(() => NfsDirectoryHandle.deserialize('not a handle'));
+  t.throws(() => NfsDirectoryHandle.deserialize(JSON.stringify({kind: 'file', name: 'annar'})));
+})
+
+test.serial('should only see entries below root when connecting with root', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {root: 'first'});
+  const keys = [];
+  for await (const key of rootHandle.keys()) {
+    keys.push(key);
+  }
+  t.deepEqual(keys, ['comment']);
+  const fileHandle = await rootHandle.getFileHandle('comment');
+  t.deepEqual(await rootHandle.resolve(fileHandle), ['comment']);
+  const err = await t.throwsAsync(rootHandle.getFileHandle('annar'));
+  t.is(err?.name, 'NotFoundError');
+})
+
+test.serial('should keep root when reopening serialized handle', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {root: 'first'});
+  const fileHandle = await rootHandle.getFileHandle('comment');
+  const reopened = NfsDirectoryHandle.deserialize(fileHandle.serialize());
+  t.true(await reopened.isSameEntry(fileHandle));
+  t.true(JSON.parse(reopened.serialize()).path.indexOf('first') == -1);
+})
+
+test.serial('should return error when modifying using read-only connection', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {readOnly: true});
+  const err = await t.throwsAsync(rootHandle.getFileHandle('read-only', {create: true}));
+  t.is(err?.name, 'NoModificationAllowedError');
+  const err2 = await t.throwsAsync(rootHandle.getDirectoryHandle('read-only', {create: true}));
+  t.is(err2?.name, 'NoModificationAllowedError');
+  const err3 = await t.throwsAsync(rootHandle.removeEntry('annar'));
+  t.is(err3?.name, 'NoModificationAllowedError');
+  const fileHandle = await rootHandle.getFileHandle('annar');
+  const err4 = await t.throwsAsync(fileHandle.createWritable());
+  t.is(err4?.name, 'NoModificationAllowedError');
+  const file = await fileHandle.getFile();
+  t.is(file.size, 123);
+})
+
+test.serial('should be denied readwrite permission when querying using read-only connection', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {readOnly: true});
+  const fileHandle = await rootHandle.getFileHandle('annar');
+  t.is(await fileHandle.queryPermission({mode: 'readwrite'}), 'denied');
+  t.is(await fileHandle.queryPermission({mode: 'read'}), 'granted');
+  t.is(await rootHandle.requestPermission({mode: 'readwrite'}), 'denied');
+})
+
+test.serial('should send connect option credentials', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {uid: 1000, gid: 1000});
+  const fileHandle = await rootHandle.getFileHandle('credentials-file', {create: true});
+  const stat = await fileHandle.stat();
+  t.is(stat.uid, 1000);
+  t.is(stat.gid, 1000);
+  t.is(JSON.parse(fileHandle.serialize()).uid, 1000);
+  await rootHandle.removeEntry('credentials-file');
+})
+
+test.serial('should send other credentials using derived handle sharing connection', async (t) => {
+  const rootHandle = getRootHandle();
+  const otherHandle = rootHandle.withCredentials({uid: 1001, gid: 1002});
+  t.is(otherHandle.kind, 'directory');
+  t.is(otherHandle.name, rootHandle.name);
+  t.true(await otherHandle.isSameEntry(rootHandle));
+  const fileHandle = await otherHandle.getFileHandle('credentials-file', {create: true});
+  t.is((await fileHandle.stat()).uid, 1001);
+  t.is((await fileHandle.stat()).gid, 1002);
+  const otherFileHandle = fileHandle.withCredentials({uid: 1003, gid: 1002});
+  t.is(otherFileHandle.kind, 'file');
+  t.true(await otherFileHandle.isSameEntry(fileHandle));
+  t.is((await otherFileHandle.stat()).uid, 1001);
+  t.is(await otherFileHandle.queryPermission({mode: 'readwrite'}), 'granted');
+  const strangerFileHandle = (await rootHandle.getFileHandle('annar')).withCredentials({uid: 1004, gid: 1004});
+  t.is((await strangerFileHandle.stat()).uid, 0);
+  t.is(await strangerFileHandle.queryPermission({mode: 'read'}), 'granted');
+  t.is(await strangerFileHandle.queryPermission({mode: 'readwrite'}), 'denied');
+  t.is(await strangerFileHandle.withCredentials({uid: 1004, gid: 1004, auxGids: [0]}).queryPermission({mode: 'readwrite'}), 'granted');
+  const writable = await otherFileHandle.createWritable();
+  await writable.write('written with other credentials');
+  await writable.close();
+  await otherHandle.removeEntry('credentials-file');
+})
+
+test.serial('should refuse to connect when transport security is requested', (t) => {
+  t.throws(() => new NfsDirectoryHandle(nfsURL, {xprtsec: 'tls'}), {message: /xprtsec "tls" not supported/});
+  t.throws(() => new NfsDirectoryHandle(nfsURL + (nfsURL.includes('?') ? '&' : '?') + 'xprtsec=mtls'), {message: /xprtsec "mtls" not supported/});
+  t.throws(() => new NfsDirectoryHandle(nfsURL, {xprtsec: 'bogus' as any}), {message: /invalid xprtsec/});
+})
+
+test.serial('should keep retry options when reopening serialized handle', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {retries: 5, timeoutMs: 30000, backoffMs: 10});
+  const fileHandle = await rootHandle.getFileHandle('annar');
+  const reopened = NfsDirectoryHandle.deserialize(fileHandle.serialize());
+  const serialized = JSON.parse(reopened.serialize());
+  t.is(serialized.retries, 5);
+  t.is(serialized.timeoutMs, 30000);
+  t.is(serialized.backoffMs, 10);
+  t.is((await (reopened as NfsFileHandle).getFile()).size, 123);
+})
+
+test.serial('should read file using multiple connections', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {nconnect: 4});
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  const file = await fileHandle.getFile();
+  t.is((await file.arrayBuffer()).byteLength, 123);
+  t.is(JSON.parse(fileHandle.serialize()).nconnect, 4);
+  const bigHandle = await rootHandle.getFileHandle('nconnect-file', {create: true}) as NfsFileHandle;
+  const data = new Uint8Array(9 * 1024 * 1024).map((_, i) => i % 251);
+  const writable = await bigHandle.createWritable();
+  await writable.write(data);
+  await writable.close();
+  t.deepEqual(new Uint8Array(await (await bigHandle.getFile()).arrayBuffer()), data);
+  await rootHandle.removeEntry('nconnect-file');
+})
+
+test.serial('should keep cached attributes in line with local modifications', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {actimeo: 10, acdirmax: 20});
+  const serialized = JSON.parse(rootHandle.serialize());
+  t.deepEqual([serialized.acregmin, serialized.acregmax, serialized.acdirmin, serialized.acdirmax], [10, 10, 10, 20]);
+  const uncached = JSON.parse(new NfsDirectoryHandle(nfsURL).serialize());
+  t.deepEqual([uncached.acregmin, uncached.acregmax, uncached.acdirmin, uncached.acdirmax], [0, 0, 0, 0]);
+  for await (const [name, handle] of rootHandle.entries()) {
+    if (name === 'annar') {
+      t.is((await (handle as NfsFileHandle).getFile()).size, 123);
+    }
+  }
+  const fileHandle = await rootHandle.getFileHandle('cached-attributes-file', {create: true}) as NfsFileHandle;
+  t.is((await fileHandle.getFile()).size, 0);
+  const writable = await fileHandle.createWritable();
+  await writable.write('cached');
+  await writable.close();
+  t.is((await fileHandle.getFile()).size, 6);
+  t.is((await fileHandle.stat()).size, 6);
+  await rootHandle.removeEntry('cached-attributes-file');
+  await t.throwsAsync(fileHandle.getFile());
+})
+
+test.serial('should count name cache hits and misses', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  await (await dirHandle.getFileHandle('comment')).stat();
+  const before = await rootHandle.cacheStats();
+  t.true(before.nameCacheMisses > 0);
+  await (await dirHandle.getFileHandle('comment')).stat();
+  const after = await dirHandle.cacheStats();
+  t.is(after.nameCacheMisses, before.nameCacheMisses);
+  t.true(after.nameCacheHits > before.nameCacheHits);
+  t.true(after.nameCacheEntries > 0);
+  const uncachedHandle = new NfsDirectoryHandle(nfsURL, {lookupcache: 'none'});
+  await (await (await uncachedHandle.getDirectoryHandle('first')).getFileHandle('comment')).stat();
+  t.is((await uncachedHandle.cacheStats()).nameCacheHits, 0);
+  t.is(JSON.parse(uncachedHandle.serialize()).lookupcache, 'none');
+  t.throws(() => new NfsDirectoryHandle(nfsURL, {lookupcache: 'some' as 'all'}), {message: /lookupcache/});
+})
+
+test.serial('should serve repeated reads from data cache until file is modified', async (t) => {
+  const rootHandle = new NfsDirectoryHandle(nfsURL, {dataCacheSize: 1048576});
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  const text = await (await fileHandle.getFile()).text();
+  t.is(await (await fileHandle.getFile()).text(), text);
+  t.is(await (await fileHandle.getFile()).slice(3, 8).text(), text.slice(3, 8));
+  const stats = await fileHandle.cacheStats();
+  t.is(stats.dataCacheMisses, 1);
+  t.is(stats.dataCacheHits, 2);
+  t.is(stats.dataCacheBytes, 123);
+  const writable = await fileHandle.createWritable({keepExistingData: true});
+  await writable.write({type: 'write', position: 0, data: 'On'});
+  await writable.close();
+  t.is(await (await fileHandle.getFile()).text(), 'On' + text.slice(2));
+  t.is(JSON.parse(fileHandle.serialize()).dataCacheSize, 1048576);
+})
+
+test.serial('should take and release byte-range locks', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  if (!process.env.TEST_USING_MOCKS) { // XXX: neither libnfs nor nfs-rs bindings support locks (yet)
+    const err = await t.throwsAsync(fileHandle.lock());
+    t.is(err?.name, 'NotSupportedError');
+    return;
+  }
+  const shared = await fileHandle.lock({offset: 0, length: 10, mode: 'shared'});
+  t.is(shared.mode, 'shared');
+  const other = await fileHandle.lock({offset: 5, length: 10, mode: 'shared'});
+  await t.throwsAsync(fileHandle.lock({wait: false}));
+  const err = await t.throwsAsync(fileHandle.lock({timeoutMs: 50}));
+  t.is(err?.name, 'TimeoutError');
+  await shared.release();
+  await shared.release();
+  setTimeout(() => other.release(), 50);
+  const writable = await fileHandle.createWritable({lock: 'exclusive', keepExistingData: true});
+  await t.throwsAsync(fileHandle.lock({mode: 'shared', wait: false}));
+  await writable.close();
+  const exclusive = await fileHandle.lock({wait: false});
+  t.is(exclusive.mode, 'exclusive');
+  await exclusive.release();
+})
+
+test.serial('should fail exclusive create when file already exists', async (t) => {
+  const rootHandle = getRootHandle();
+  const err = await t.throwsAsync(rootHandle.getFileHandle('annar', {create: true, exclusive: true}));
+  t.is(err?.name, 'InvalidModificationError');
+  if (process.env.TEST_USING_PURE_RUST) {
+    // XXX: nfs-rs bindings can send neither GUARDED nor EXCLUSIVE create (yet), while libnfs ones send GUARDED
+    const err2 = await t.throwsAsync(rootHandle.getFileHandle('exclusive-create', {create: true, exclusive: true}));
+    return t.is(err2?.name, 'NotSupportedError');
+  }
+  const fileHandle = await rootHandle.getFileHandle('exclusive-create', {create: true, exclusive: true});
+  t.is(fileHandle.name, 'exclusive-create');
+  const err2 = await t.throwsAsync(rootHandle.getFileHandle('exclusive-create', {create: true, exclusive: true}));
+  t.is(err2?.name, 'InvalidModificationError');
+  t.true(await (await rootHandle.getFileHandle('exclusive-create', {create: true})).isSameEntry(fileHandle));
+  await rootHandle.removeEntry('exclusive-create');
+})
+
+test.serial('should create entries with requested mode', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('private', {create: true, mode: 0o700}) as NfsDirectoryHandle;
+  t.is((await dirHandle.stat()).mode & 0o7777, 0o700);
+  const fileHandle = await dirHandle.getFileHandle('secret', {create: true, mode: 0o600}) as NfsFileHandle;
+  t.is((await fileHandle.stat()).mode & 0o7777, 0o600);
+  const err = await t.throwsAsync(rootHandle.getDirectoryHandle('bad-mode', {create: true, mode: 0o70000}));
+  t.is(err?.name, 'TypeError');
+  await rootHandle.removeEntry('private', {recursive: true});
+})
+
+test.serial('should create missing parents with mkdirp', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.mkdirp('mkdirp/b//c/');
+  t.is(dirHandle.name, 'c');
+  t.deepEqual(await rootHandle.resolve(dirHandle), ['mkdirp', 'b', 'c']);
+  t.true(await (await rootHandle.getDirectoryHandle('mkdirp/b/c', {recursive: true})).isSameEntry(dirHandle));
+  const err = await t.throwsAsync(rootHandle.getDirectoryHandle('mkdirp/x/c', {recursive: true}));
+  t.is(err?.name, 'NotFoundError');
+  const err2 = await t.throwsAsync(rootHandle.getDirectoryHandle('mkdirp/b'));
+  t.is(err2?.name, 'TypeError');
+  const err3 = await t.throwsAsync(rootHandle.mkdirp('mkdirp/../b'));
+  t.is(err3?.name, 'TypeError');
+  await rootHandle.removeEntry('mkdirp', {recursive: true});
+})
+
+test.serial('should keep holes when copying sparse files', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('sparse', {create: true});
+  const writable = await fileHandle.createWritable();
+  await writable.write('head');
+  await writable.write({type: 'write', position: 100000, data: 'tail'});
+  await writable.punchHole(1, 2);
+  await writable.allocate(200000, 10);
+  await writable.close();
+  const text = await (await fileHandle.getFile()).text();
+  t.is(text.length, 200010);
+  t.is(text.slice(0, 4), 'h\0\0d');
+  t.is(text.slice(100000, 100004), 'tail');
+  const copyHandle = await rootHandle.getFileHandle('sparse-copy', {create: true});
+  const copyWritable = await copyHandle.createWritable();
+  const reader = (await fileHandle.getFile()).stream().getReader();
+  for (let chunk = await reader.read(); !chunk.done; chunk = await reader.read()) {
+    await copyWritable.write(chunk.value);
+  }
+  await copyWritable.close();
+  t.is(await (await copyHandle.getFile()).text(), text);
+  if (process.env.TEST_USING_MOCKS) { // XXX: neither libnfs nor nfs-rs bindings support SEEK/ALLOCATE/DEALLOCATE (yet)
+    const stat = await fileHandle.stat();
+    t.true(stat.used < stat.size);
+    const copyStat = await copyHandle.stat();
+    t.is(copyStat.size, stat.size);
+    t.true(copyStat.used < stat.size);
+  }
+  await rootHandle.removeEntry('sparse');
+  await rootHandle.removeEntry('sparse-copy');
+})
+
+test.serial('should set, get, list and remove extended attributes', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  if (!process.env.TEST_USING_MOCKS) { // XXX: neither libnfs nor nfs-rs bindings support xattrs (yet)
+    const err = await t.throwsAsync(fileHandle.listXattrs());
+    t.is(err?.name, 'NotSupportedError');
+    return;
+  }
+  t.deepEqual(await fileHandle.listXattrs(), []);
+  await fileHandle.setXattr('provenance', 'pipeline');
+  await fileHandle.setXattr('checksum', new Uint8Array([0, 1, 2]));
+  t.is(Buffer.from(await fileHandle.getXattr('provenance')).toString(), 'pipeline');
+  t.deepEqual([...await fileHandle.getXattr('checksum')], [0, 1, 2]);
+  t.deepEqual(await fileHandle.listXattrs(), ['checksum', 'provenance']);
+  await fileHandle.removeXattr('checksum');
+  t.deepEqual(await fileHandle.listXattrs(), ['provenance']);
+  const err = await t.throwsAsync(fileHandle.getXattr('checksum'));
+  t.is(err?.name, 'NotFoundError');
+  const err2 = await t.throwsAsync(fileHandle.setXattr('', 'empty'));
+  t.is(err2?.name, 'TypeError');
+  await fileHandle.removeXattr('provenance');
+})
+
+test.serial('should get and set access control lists', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('acl', {create: true}) as NfsFileHandle;
+  if (!process.env.TEST_USING_MOCKS) { // XXX: neither libnfs nor nfs-rs bindings support NFSv4 ACLs (yet)
+    const err = await t.throwsAsync(fileHandle.getAcl());
+    t.is(err?.name, 'NotSupportedError');
+    await rootHandle.removeEntry('acl');
+    return;
+  }
+  const acl = await fileHandle.getAcl();
+  t.deepEqual(acl.map((ace) => ace.who), ['OWNER@', 'OWNER@', 'GROUP@', 'GROUP@', 'EVERYONE@']);
+  t.is(await fileHandle.queryPermission({mode: 'readwrite'}), 'granted');
+  await fileHandle.setAcl([
+    {type: 'deny', flags: 0, mask: 0x2, who: 'EVERYONE@'},
+    {type: 'allow', flags: 0, mask: 0x1, who: 'EVERYONE@'},
+  ]);
+  t.deepEqual((await fileHandle.getAcl()).map((ace) => ace.type), ['deny', 'allow']);
+  t.is(await fileHandle.queryPermission({mode: 'readwrite'}), 'denied');
+  t.is(await fileHandle.queryPermission({mode: 'read'}), 'granted');
+  const err = await t.throwsAsync(fileHandle.setAcl([{type: 'permit' as any, flags: 0, mask: 0x1, who: 'EVERYONE@'}]));
+  t.is(err?.name, 'TypeError');
+  await rootHandle.removeEntry('acl');
+})
+
+test.serial('should only escalate permission as allowed by permission policy', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('policy', {create: true, mode: 0o500});
+  t.is(await dirHandle.queryPermission({mode: 'readwrite'}), 'denied');
+  t.is(await dirHandle.requestPermission({mode: 'readwrite'}), 'denied');
+  await rootHandle.removeEntry('policy');
+  let answer = false;
+  const promptRootHandle = new NfsDirectoryHandle(nfsURL, {permissionPolicy: () => answer});
+  const promptDirHandle = await promptRootHandle.getDirectoryHandle('policy', {create: true, mode: 0o500});
+  t.is(await promptDirHandle.queryPermission({mode: 'readwrite'}), 'prompt');
+  t.is(await promptDirHandle.requestPermission({mode: 'readwrite'}), 'denied');
+  answer = true;
+  t.is(await promptDirHandle.requestPermission({mode: 'readwrite'}), 'granted');
+  t.is(await promptDirHandle.queryPermission({mode: 'readwrite'}), 'granted');
+  await promptRootHandle.removeEntry('policy');
+  t.throws(() => new NfsDirectoryHandle(nfsURL, {permissionPolicy: 'chmdo' as any}), {message: /Invalid permissionPolicy "chmdo"/});
+})
+
+test.serial('should list special files according to special files option', async (t) => {
+  t.throws(() => new NfsDirectoryHandle(nfsURL, {specialFiles: 'expsoe' as any}), {message: /Invalid specialFiles "expsoe"/});
+  for (const specialFiles of ['file', 'skip', 'expose'] as const) {
+    const rootHandle = new NfsDirectoryHandle(nfsURL, {specialFiles});
+    const dirHandle = await rootHandle.getDirectoryHandle('special', {create: true}) as NfsDirectoryHandle;
+    try {
+      await dirHandle.mkfifo('fifo', {mode: 0o600});
+    } catch (reason: any) {
+      t.is(reason.name, 'NotSupportedError'); // XXX: nfs-rs bindings do not support MKNOD
+      await rootHandle.removeEntry('special');
+      return;
+    }
+    const handles = [];
+    for await (const handle of dirHandle.values()) {
+      handles.push(handle as NfsFileHandle);
+    }
+    if (specialFiles == 'skip') {
+      t.is(handles.length, 0);
+    } else {
+      t.is(handles.length, 1);
+      t.is(handles[0].kind, 'file');
+      t.is(handles[0].special, specialFiles == 'expose' ? 'fifo' : undefined);
+      const err2 = await t.throwsAsync(handles[0].getFile());
+      t.true(err2?.message.endsWith('is not a regular file'));
+    }
+    await rootHandle.removeEntry('special', {recursive: true});
+  }
+})
+
+test.serial('should decode invalid UTF-8 lossily and expose raw names', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('latin1', {create: true}) as NfsFileHandle;
+  const writable = await fileHandle.createWritable();
+  await writable.write(new Uint8Array([0x63, 0x61, 0x66, 0xe9]));
+  await writable.close();
+  t.is(await (await fileHandle.getFile()).text(), 'caf\ufffd');
+  t.deepEqual(Buffer.from(fileHandle.nameBytes), Buffer.from('latin1'));
+  await rootHandle.removeEntry('latin1');
+})
+
+test.serial('should decode text in given encoding and iterate lines', async (t) => {
+  const rootHandle = getRootHandle();
+  let fileHandle = await rootHandle.getFileHandle('encoded', {create: true}) as NfsFileHandle;
+  let writable = await fileHandle.createWritable();
+  await writable.write(new Uint8Array([0xff, 0xfe, 0x63, 0x00, 0xe9, 0x00, 0x0d, 0x00, 0x0a, 0x00, 0x78, 0x00]));
+  await writable.close();
+  let file = await fileHandle.getFile();
+  t.is(await file.text({encoding: 'utf-16le'}), 'c\u00e9\r\nx');
+  t.is(await file.text({encoding: 'utf-16le', ignoreBOM: true}), '\ufeffc\u00e9\r\nx');
+  const lines: string[] = [];
+  for await (const line of file.lines({encoding: 'utf-16le'})) {
+    lines.push(line);
+  }
+  t.deepEqual(lines, ['c\u00e9', 'x']);
+  await t.throwsAsync(file.text({fatal: true}), {message: 'The encoded data was not valid for encoding utf-8'});
+  t.throws(() => file.lines({encoding: 'shift_jis'}), {message: 'Encoding "shift_jis" is not supported'});
+  await rootHandle.removeEntry('encoded');
+  fileHandle = await rootHandle.getFileHandle('encoded', {create: true}) as NfsFileHandle;
+  writable = await fileHandle.createWritable();
+  await writable.write(new Uint8Array([0x63, 0x61, 0x66, 0xe9, 0x80]));
+  await writable.close();
+  file = await fileHandle.getFile();
+  t.is(await file.text({encoding: 'latin1'}), 'caf\u00e9\u20ac');
+  await rootHandle.removeEntry('encoded');
+})
+
+test.serial('should decode lines spanning chunks of more than one read', async (t) => {
+  // XXX: odd read size, so that a chunk ends in the middle of a UTF-16 code unit (where server or bindings allow it)
+  const url = /[?&]rsize=\d+/.test(nfsURL) ? nfsURL.replace(/rsize=\d+/, 'rsize=8193') : nfsURL + (nfsURL.includes('?') ? '&' : '?') + 'rsize=8193';
+  const rootHandle = new NfsDirectoryHandle(url);
+  const expected = [...Array(40).fill('x'.repeat(99)), 'y'.repeat(54) + '\u{1f600}z', ...Array(100).fill('w'.repeat(99))];
+  const text = expected.join('\r\n') + '\r\n';
+  t.is(text.indexOf('\u{1f600}'), 4094); // XXX: after BOM, high surrogate ends and low surrogate starts at byte 8192
+  const fileHandle = await rootHandle.getFileHandle('chunked', {create: true}) as NfsFileHandle;
+  const writable = await fileHandle.createWritable();
+  await writable.write(new Uint8Array([0xff, 0xfe, ...Buffer.from(text, 'utf16le')]));
+  await writable.close();
+  const file = await fileHandle.getFile();
+  t.true(file.size > 3 * 8193);
+  t.is(await file.text({encoding: 'utf-16le'}), text);
+  const lines: string[] = [];
+  for await (const line of file.lines({encoding: 'utf-16le', fatal: true})) {
+    lines.push(line);
+  }
+  t.deepEqual(lines, expected);
+  await rootHandle.removeEntry('chunked');
+})
+
+test.serial('should return abort error when signal is already aborted', async (t) => {
+  const rootHandle = getRootHandle();
+  const controller = new AbortController();
+  controller.abort();
+  const err = await t.throwsAsync(rootHandle.getDirectoryHandle('first', {signal: controller.signal}));
+  t.is(err?.name, 'AbortError');
+  const err2 = await t.throwsAsync(rootHandle.removeEntry('first', {recursive: true, signal: controller.signal}));
+  t.is(err2?.name, 'AbortError');
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  const err3 = await t.throwsAsync(fileHandle.createWritable({keepExistingData: true, signal: controller.signal}));
+  t.is(err3?.name, 'AbortError');
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  t.is(dirHandle.name, 'first');
+})
+
+test.serial('should return timeout error when operation takes longer than timeout', async (t) => {
+  const rootHandle = getRootHandle();
+  const err = await t.throwsAsync(rootHandle.getFileHandle('annar', {timeoutMs: 0}));
+  t.is(err?.name, 'TimeoutError');
+  const fileHandle = await rootHandle.getFileHandle('annar', {timeoutMs: 30000}) as NfsFileHandle;
+  const file = await fileHandle.getFile({timeoutMs: 30000, signal: new AbortController().signal});
+  t.is((await file.arrayBuffer()).byteLength, 123);
+})
+
+test.serial('should start timeout of file reads when they start and name their cancellation errors', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  const file = await fileHandle.getFile({timeoutMs: 50});
+  await new Promise((resolve) => setTimeout(resolve, 100));
+  t.is((await file.arrayBuffer()).byteLength, 123);
+  t.is((await file.text()).length, 123);
+  const lines = [];
+  for await (const line of file.lines()) {
+    lines.push(line);
+  }
+  t.is(lines.join('').length, 123);
+  const controller = new AbortController();
+  controller.abort();
+  const err = await t.throwsAsync(file.arrayBuffer({signal: controller.signal}));
+  t.is(err?.name, 'AbortError');
+  const err2 = await t.throwsAsync(file.text({timeoutMs: 0}));
+  t.is(err2?.name, 'TimeoutError');
+  const err3 = await t.throwsAsync(file.stream({signal: controller.signal}).getReader().read());
+  t.is(err3?.name, 'AbortError');
+  const err4 = await t.throwsAsync(file.lines({signal: controller.signal}).next());
+  t.is(err4?.name, 'AbortError');
+})
+
+test.serial('should return error when getting handle with name that is not allowed', async (t) => {
+  const rootHandle = getRootHandle();
+  for (const name of ['', '.', '..', 'first/comment', '../annar']) {
+    const err = await t.throwsAsync(rootHandle.getFileHandle(name));
+    t.is(err?.name, 'TypeError');
+    const err2 = await t.throwsAsync(rootHandle.getDirectoryHandle(name, {create: true}));
+    t.is(err2?.name, 'TypeError');
+  }
+  await t.throwsAsync(rootHandle.removeEntry('..', {recursive: true}));
+})
+
 test.serial('should be granted read permission when querying on directory', async (t) => {
   const rootHandle = getRootHandle();
   const dirHandle = await rootHandle.getDirectoryHandle('first') as NfsDirectoryHandle;
@@ -575,10 +1123,10 @@ test.serial('should return null when resolving unknown file', async (t) => {
   t.deepEqual(resolved, null);
 })
 
-test.serial('should return non-null when resolving known directory', async (t) => {
+test.serial('should return null when resolving known directory without identity', async (t) => {
   const rootHandle = getRootHandle();
   const resolved = await rootHandle.resolve({kind: 'directory', name: 'first'} as any);
-  t.deepEqual(resolved, ['first']);
+  t.deepEqual(resolved, null);
 })
 
 test.serial('should return non-null when resolving known directory using handle', async (t) => {
@@ -588,15 +1136,11 @@ test.serial('should return non-null when resolving known directory using handle'
   t.deepEqual(resolved, ['first']);
 })
 
-test.serial('should return non-null when resolving known file', async (t) => {
+test.serial('should return null when resolving known file without identity', async (t) => {
   const rootHandle = getRootHandle();
-  for (const name of ['annar', '3']) {
-    const resolved = await rootHandle.resolve({kind: 'file', name} as any);
-    t.deepEqual(resolved, [name]);
-  }
-  for (const {dir, name} of [{dir: 'first', name: 'comment'}, {dir: 'quatre', name: 'points'}]) {
+  for (const name of ['annar', '3', 'comment', 'points']) {
     const resolved = await rootHandle.resolve({kind: 'file', name} as any);
-    t.deepEqual(resolved, [dir, name]);
+    t.deepEqual(resolved, null);
   }
 })
 
@@ -624,14 +1168,39 @@ test.serial('should return null when resolving file belonging to different direc
   const resolved = await dirHandle2.resolve(fileHandle);
   t.deepEqual(resolved, null);
   const resolved2 = await dirHandle2.resolve(fileHandle2);
-  t.deepEqual(resolved2, ['quatre', 'points']);
+  t.deepEqual(resolved2, ['points']);
   const resolved3 = await dirHandle.resolve(fileHandle2);
   t.deepEqual(resolved3, null);
   const resolved4 = await dirHandle2.resolve({kind: fileHandle.kind, name: fileHandle.name} as any);
-  t.deepEqual(resolved4, ['quatre', 'points']);
+  t.deepEqual(resolved4, null);
   await dirHandle.removeEntry('points');
 })
 
+test.serial('should return non-null when resolving self', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  t.deepEqual(await rootHandle.resolve(rootHandle), []);
+  t.deepEqual(await dirHandle.resolve(dirHandle), []);
+})
+
+test.serial('should return null when resolving sibling sharing name prefix', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const siblingHandle = await rootHandle.getDirectoryHandle('firstborn', {create: true});
+  t.deepEqual(await dirHandle.resolve(siblingHandle), null);
+  t.deepEqual(await rootHandle.resolve(siblingHandle), ['firstborn']);
+  await rootHandle.removeEntry('firstborn');
+})
+
+test.serial('should return null when resolving removed file using handle', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first');
+  const fileHandle = await dirHandle.getFileHandle('points', {create: true});
+  t.deepEqual(await rootHandle.resolve(fileHandle), ['first', 'points']);
+  await dirHandle.removeEntry('points');
+  t.deepEqual(await rootHandle.resolve(fileHandle), null);
+})
+
 test.serial('should return file for file handle', async (t) => {
   const rootHandle = getRootHandle();
   const fileHandle = await rootHandle.getFileHandle('annar');
@@ -642,6 +1211,25 @@ test.serial('should return file for file handle', async (t) => {
   t.true(file.lastModified >= 1658159058723);
 })
 
+test.serial('should return stat for file handle', async (t) => {
+  const rootHandle = getRootHandle();
+  const fileHandle = await rootHandle.getFileHandle('annar') as NfsFileHandle;
+  const stat = await fileHandle.stat();
+  t.is(stat.size, 123);
+  t.true(stat.mtime >= 1658159058);
+  t.true(stat.mtimeNsec >= 0 && stat.mtimeNsec < 1000000000);
+  const file = await fileHandle.getFile();
+  t.is(file.lastModified, stat.mtime * 1000 + Math.floor(stat.mtimeNsec / 1000000));
+})
+
+test.serial('should return stat for directory handle', async (t) => {
+  const rootHandle = getRootHandle();
+  const dirHandle = await rootHandle.getDirectoryHandle('first') as NfsDirectoryHandle;
+  const stat = await dirHandle.stat();
+  t.true(stat.mtime >= 1658159058);
+  t.true(stat.ctimeNsec >= 0 && stat.ctimeNsec < 1000000000);
+})
+
 test.serial('should return file with correct MIME type for files with extension in name', async (t) => {
   const rootHandle = getRootHandle();
   const files = [
@@ -1378,10 +1966,12 @@ test.serial('should handle resolving concurrently', async (t) => {
   // @ts-ignore
   const count = process.env.TEST_USING_MOCKS ? 1000 : 10;
   const rootHandle = getRootHandle();
+  const firstHandle = await rootHandle.getDirectoryHandle('first');
+  const quatreHandle = await rootHandle.getDirectoryHandle('quatre');
   for (let i = 0; i < count; i++) {
     const [first, quatre] = await Promise.all([
-      rootHandle.resolve({kind: 'directory', name: 'first'} as any),
-      rootHandle.resolve({kind: 'directory', name: 'quatre'} as any),
+      rootHandle.resolve(firstHandle),
+      rootHandle.resolve(quatreHandle),
     ]);
     t.deepEqual(first, ['first']);
     t.deepEqual(quatre, ['quatre']);
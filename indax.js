@@ -20,13 +20,144 @@ var _a;
 Object.defineProperty(exports, "__esModule", { value: true });
 exports.NfsWritableFileStream = exports.NfsFileHandle = exports.NfsDirectoryHandle = exports.NfsHandle = void 0;
 const index_1 = require("./index");
+const NO_MODIFICATION_ALLOWED = 'modification not allowed on read-only connection';
+function nameReadOnlyError(reason) {
+    if ((reason === null || reason === void 0 ? void 0 : reason.message) !== undefined && reason.message.indexOf(NO_MODIFICATION_ALLOWED) != -1) {
+        reason.name = 'NoModificationAllowedError';
+    }
+    return reason;
+}
+function nameMetadataError(reason) {
+    let errMsg = reason === null || reason === void 0 ? void 0 : reason.message;
+    if (errMsg !== undefined) {
+        if (errMsg.indexOf('not found') != -1) {
+            reason.name = 'NotFoundError';
+        }
+        else if (errMsg.indexOf('not supported') != -1) {
+            reason.name = 'NotSupportedError';
+        }
+        else if (errMsg.endsWith('is not allowed')) {
+            reason.name = 'TypeError';
+        }
+        else if (errMsg.endsWith('already exists')) {
+            reason.name = 'InvalidModificationError';
+        }
+    }
+    return nameReadOnlyError(reason);
+}
+function toJsConnectOptions(options) {
+    const prompt = options === null || options === void 0 ? void 0 : options.permissionPolicy;
+    if (typeof prompt !== 'function') {
+        return options;
+    }
+    return Object.assign(Object.assign({}, options), { permissionPolicy: async (jsh, perm) => !!(await prompt(new NfsHandle(jsh), perm)) });
+}
+const OPERATION_ABORTED = 'operation was aborted';
+const OPERATION_TIMED_OUT = 'operation timed out';
+function nameCancellationError(reason) {
+    if ((reason === null || reason === void 0 ? void 0 : reason.message) !== undefined) {
+        if (reason.message.indexOf(OPERATION_ABORTED) != -1) {
+            reason.name = 'AbortError';
+        }
+        else if (reason.message.indexOf(OPERATION_TIMED_OUT) != -1) {
+            reason.name = 'TimeoutError';
+        }
+    }
+    return reason;
+}
+/**
+ * Runs native operation with a flag it checks in between RPCs, and settles as soon as signal is aborted or timeout
+ * expires, even when operation is still waiting on a server that does not respond.
+ */
+function withCancellation(options, operation) {
+    if ((options === null || options === void 0 ? void 0 : options.signal) === undefined && (options === null || options === void 0 ? void 0 : options.timeoutMs) === undefined) {
+        return operation(options);
+    }
+    const { signal, ...rest } = options;
+    const abortFlag = new Int32Array(new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT));
+    return new Promise((resolve, reject) => {
+        let timer;
+        const settle = () => {
+            clearTimeout(timer);
+            signal === null || signal === void 0 ? void 0 : signal.removeEventListener('abort', onAbort);
+        };
+        const cancel = (reason) => {
+            Atomics.store(abortFlag, 0, 1);
+            settle();
+            reject(reason);
+        };
+        const onAbort = () => { var _a; return cancel((_a = signal === null || signal === void 0 ? void 0 : signal.reason) !== null && _a !== void 0 ? _a : nameCancellationError(new Error(OPERATION_ABORTED))); };
+        if (signal === null || signal === void 0 ? void 0 : signal.aborted) {
+            return onAbort();
+        }
+        signal === null || signal === void 0 ? void 0 : signal.addEventListener('abort', onAbort, { once: true });
+        if (rest.timeoutMs !== undefined) {
+            timer = setTimeout(() => cancel(nameCancellationError(new Error(OPERATION_TIMED_OUT))), rest.timeoutMs);
+        }
+        operation({ ...rest, abortFlag })
+            .then(resolve, (reason) => reject(nameCancellationError(reason)))
+            .finally(settle);
+    });
+}
+/**
+ * Turns signal into a flag that native streams and iterators check before each read, since they outlive any single
+ * promise withCancellation could settle early. Timeout is left to native side, which restarts it for every read.
+ */
+function withAbortFlag(options) {
+    if ((options === null || options === void 0 ? void 0 : options.signal) === undefined) {
+        return options;
+    }
+    const { signal, ...rest } = options;
+    const abortFlag = new Int32Array(new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT));
+    const onAbort = () => Atomics.store(abortFlag, 0, 1);
+    if (signal.aborted) {
+        onAbort();
+    }
+    else {
+        signal.addEventListener('abort', onAbort, { once: true });
+    }
+    return { ...rest, abortFlag };
+}
+function wrapFile(_js) {
+    return {
+        size: _js.size,
+        type: _js.type,
+        lastModified: _js.lastModified,
+        name: _js.name,
+        webkitRelativePath: '',
+        arrayBuffer: (options) => withCancellation(options, (options) => _js.arrayBuffer(options)),
+        bytes: async (options) => new Uint8Array(await withCancellation(options, (options) => _js.arrayBuffer(options))),
+        slice: (start, end, contentType) => _js.slice(start, end, contentType),
+        stream: (options) => {
+            const reader = _js.stream(withAbortFlag(options)).getReader();
+            return new ReadableStream({
+                pull: (controller) => reader.read().then(({ done, value }) => done ? controller.close() : controller.enqueue(value), (reason) => { throw nameCancellationError(reason); }),
+                cancel: (reason) => reader.cancel(reason),
+            });
+        },
+        text: (options) => withCancellation(options, (options) => _js.text(options)),
+        lines: (options) => {
+            const lines = _js.lines(withAbortFlag(options));
+            return (async function* () {
+                try {
+                    yield* lines;
+                }
+                catch (reason) {
+                    throw nameCancellationError(reason);
+                }
+            })();
+        },
+    };
+}
 class NfsHandle {
     constructor(_jsh) {
         this._jsh = _jsh;
-        this.kind = _jsh.kind;
+        this.kind = _jsh.kind == 'directory' ? 'directory' : 'file';
         this.name = _jsh.name;
-        this.isFile = _jsh.kind == 'file';
-        this.isDirectory = _jsh.kind == 'directory';
+        this.nameBytes = _jsh.nameBytes;
+        this.special = _jsh.kind == 'directory' || _jsh.kind == 'file' ? undefined : _jsh.kind;
+        this.isFile = this.kind == 'file';
+        this.isDirectory = this.kind == 'directory';
     }
     isSameEntry(other) {
         return new Promise(async (resolve, reject) => {
@@ -38,18 +169,49 @@ class NfsHandle {
             }
         });
     }
+    async getUniqueId() {
+        return this._jsh.getUniqueId();
+    }
+    serialize() {
+        return this._jsh.serialize();
+    }
     async queryPermission(perm) {
         return this._jsh.queryPermission(perm);
     }
     async requestPermission(perm) {
         return this._jsh.requestPermission(perm);
     }
+    async stat(options) {
+        return withCancellation(options, (options) => this._jsh.stat(options));
+    }
+    async cacheStats() {
+        return this._jsh.cacheStats();
+    }
+    async getXattr(name) {
+        return this._jsh.getXattr(name).catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async setXattr(name, value) {
+        const buffer = typeof value === 'string' ? Buffer.from(value) : ArrayBuffer.isView(value) ? Buffer.from(value.buffer, value.byteOffset, value.byteLength) : Buffer.from(value);
+        return this._jsh.setXattr(name, buffer).catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async listXattrs() {
+        return this._jsh.listXattrs().catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async removeXattr(name) {
+        return this._jsh.removeXattr(name).catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async getAcl() {
+        return this._jsh.getAcl().catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async setAcl(aces) {
+        return this._jsh.setAcl(aces).catch((reason) => { throw nameMetadataError(reason); });
+    }
 }
 exports.NfsHandle = NfsHandle;
 class NfsDirectoryHandle extends NfsHandle {
-    constructor(param) {
+    constructor(param, options) {
         const [url, toWrap] = typeof param === 'string' ? [param] : ['', param];
-        const _js = toWrap || new index_1.JsNfsDirectoryHandle(url);
+        const _js = toWrap || new index_1.JsNfsDirectoryHandle(url, toJsConnectOptions(options));
         super(_js.toHandle());
         this[_a] = this.entries;
         this[Symbol.asyncIterator] = this.entries;
@@ -61,6 +223,13 @@ class NfsDirectoryHandle extends NfsHandle {
         this.getDirectory = this.getDirectoryHandle;
         this.getEntries = this.values;
     }
+    static deserialize(serialized, options) {
+        const handle = index_1.JsNfsDirectoryHandle.deserialize(serialized, options);
+        return handle instanceof index_1.JsNfsDirectoryHandle ? new NfsDirectoryHandle(handle) : new NfsFileHandle(handle);
+    }
+    withCredentials(credentials) {
+        return new NfsDirectoryHandle(this._js.withCredentials(credentials));
+    }
     async *entries() {
         for await (const [key, value] of this._js.entries()) {
             yield [key, value instanceof index_1.JsNfsDirectoryHandle ? new NfsDirectoryHandle(value) : new NfsFileHandle(value)];
@@ -78,7 +247,7 @@ class NfsDirectoryHandle extends NfsHandle {
     }
     async getDirectoryHandle(name, options) {
         return new Promise(async (resolve, reject) => {
-            await this._js.getDirectoryHandle(name, options)
+            await withCancellation(options, (options) => this._js.getDirectoryHandle(name, options))
                 .then((handle) => resolve(new NfsDirectoryHandle(handle)))
                 .catch((reason) => {
                 let errMsg = reason.message;
@@ -89,14 +258,23 @@ class NfsDirectoryHandle extends NfsHandle {
                     else if (errMsg.indexOf('not found') != -1) {
                         reason.name = 'NotFoundError';
                     }
+                    else if (errMsg.endsWith('is not allowed')) {
+                        reason.name = 'TypeError';
+                    }
                 }
-                reject(reason);
+                reject(nameReadOnlyError(reason));
             });
         });
     }
+    /**
+     * Creates directory at path relative to this one, along with whatever parents of it are missing, like `mkdir -p`.
+     */
+    async mkdirp(path, options) {
+        return this.getDirectoryHandle(path, { ...options, create: true, recursive: true });
+    }
     async getFileHandle(name, options) {
         return new Promise(async (resolve, reject) => {
-            await this._js.getFileHandle(name, options)
+            await withCancellation(options, (options) => this._js.getFileHandle(name, options))
                 .then((handle) => resolve(new NfsFileHandle(handle)))
                 .catch((reason) => {
                 let errMsg = reason.message;
@@ -107,16 +285,31 @@ class NfsDirectoryHandle extends NfsHandle {
                     else if (errMsg.indexOf('not found') != -1) {
                         reason.name = 'NotFoundError';
                     }
+                    else if (errMsg.indexOf('not supported') != -1) {
+                        reason.name = 'NotSupportedError';
+                    }
+                    else if (errMsg.endsWith('is not allowed')) {
+                        reason.name = 'TypeError';
+                    }
+                    else if (errMsg.endsWith('already exists')) {
+                        reason.name = 'InvalidModificationError';
+                    }
                 }
-                reject(reason);
+                reject(nameReadOnlyError(reason));
             });
         });
     }
+    async mknod(name, kind, options) {
+        return withCancellation(options, (options) => this._js.mknod(name, kind, options)).catch((reason) => { throw nameMetadataError(reason); });
+    }
+    async mkfifo(name, options) {
+        return withCancellation(options, (options) => this._js.mkfifo(name, options)).catch((reason) => { throw nameMetadataError(reason); });
+    }
     async removeEntry(name, options) {
-        return this._js.removeEntry(name, options);
+        return withCancellation(options, (options) => this._js.removeEntry(name, options)).catch((reason) => { throw nameReadOnlyError(reason); });
     }
-    async resolve(possibleDescendant) {
-        return this._js.resolve(possibleDescendant._jsh || possibleDescendant);
+    async resolve(possibleDescendant, options) {
+        return withCancellation(options, (options) => this._js.resolve(possibleDescendant._jsh || possibleDescendant, options));
     }
 }
 exports.NfsDirectoryHandle = NfsDirectoryHandle;
@@ -129,19 +322,25 @@ class NfsFileHandle extends NfsHandle {
         this.isFile = true;
         this.isDirectory = false;
     }
-    async getFile() {
-        return this._js.getFile();
+    withCredentials(credentials) {
+        return new NfsFileHandle(this._js.withCredentials(credentials));
+    }
+    async getFile(options) {
+        return withCancellation(options, (options) => this._js.getFile(options)).then((file) => wrapFile(file));
     }
     async createSyncAccessHandle() {
         throw new Error("createSyncAccessHandle not implemented");
     }
     async createWritable(options) {
         return new Promise(async (resolve, reject) => {
-            await this._js.createWritable(options)
+            await withCancellation(options, (options) => this._js.createWritable(options))
                 .then((stream) => resolve(new NfsWritableFileStream(stream)))
-                .catch((reason) => reject(reason));
+                .catch((reason) => reject(nameMetadataError(reason)));
         });
     }
+    async lock(options) {
+        return withCancellation(options, (options) => this._js.lock(options)).catch((reason) => { throw nameMetadataError(reason); });
+    }
 }
 exports.NfsFileHandle = NfsFileHandle;
 class NfsWritableFileStream {
@@ -163,10 +362,10 @@ class NfsWritableFileStream {
             try {
                 await this._js.write(data)
                     .then(() => resolve())
-                    .catch((reason) => reject(reason));
+                    .catch((reason) => reject(nameReadOnlyError(reason)));
             }
             catch (reason) {
-                reject(reason);
+                reject(nameReadOnlyError(reason));
             }
         });
     }
@@ -174,10 +373,16 @@ class NfsWritableFileStream {
         return this._js.seek(position);
     }
     async truncate(size) {
-        return this._js.truncate(size);
+        return this._js.truncate(size).catch((reason) => { throw nameReadOnlyError(reason); });
+    }
+    async allocate(offset, length) {
+        return this._js.allocate(offset, length).catch((reason) => { throw nameReadOnlyError(reason); });
+    }
+    async punchHole(offset, length) {
+        return this._js.punchHole(offset, length).catch((reason) => { throw nameMetadataError(reason); });
     }
     async close() {
-        return this._js.close();
+        return this._js.close().catch((reason) => { throw nameReadOnlyError(reason); });
     }
     async abort(reason) {
         return new Promise(async (resolve, reject) => {
@@ -18,26 +18,213 @@
 
 import {
   JsNfsHandlePermissionDescriptor,
+  JsNfsAce,
+  JsNfsConnectOptions,
+  JsNfsCredentials,
+  JsNfsDeserializeOptions,
+  JsNfsStat,
+  JsNfsCacheStats,
   JsNfsHandle,
   JsNfsDirectoryHandle,
   JsNfsFileHandle,
+  JsNfsFile,
   JsNfsWritableFileStream,
+  JsNfsLockOptions,
+  JsNfsMknodOptions,
+  JsNfsTextOptions,
+  JsNfsFileLock,
 } from './index';
 
 type NfsHandlePermissionDescriptor = JsNfsHandlePermissionDescriptor;
+/**
+ * Asks whether permission that is not granted may be granted by changing mode of entry handle is for, like a browser
+ * would ask user.
+ */
+export type NfsPermissionPrompt = (handle: NfsHandle, perm: NfsHandlePermissionDescriptor) => boolean | Promise<boolean>;
+export type NfsStat = JsNfsStat;
+export type NfsAce = JsNfsAce;
+export type NfsCacheStats = JsNfsCacheStats;
+export type NfsConnectOptions = Omit<JsNfsConnectOptions, 'permissionPolicy'> & { permissionPolicy?: 'chmod' | 'never' | NfsPermissionPrompt };
+export type NfsCredentials = JsNfsCredentials;
+export type NfsSpecialKind = 'blockDevice' | 'characterDevice' | 'fifo' | 'socket';
+export type NfsMknodOptions = Omit<JsNfsMknodOptions, 'abortFlag'> & NfsOperationOptions;
+export type NfsDeserializeOptions = JsNfsDeserializeOptions;
+export type NfsOperationOptions = { signal?: AbortSignal, timeoutMs?: number };
+// @ts-ignore
+type NfsGetDirectoryOptions = FileSystemGetDirectoryOptions & { recursive?: boolean, mode?: number } & NfsOperationOptions;
 // @ts-ignore
-type NfsCreateWritableOptions = FileSystemCreateWritableOptions;
+type NfsGetFileOptions = FileSystemGetFileOptions & { exclusive?: boolean, mode?: number } & NfsOperationOptions;
+// @ts-ignore
+type NfsRemoveOptions = FileSystemRemoveOptions & NfsOperationOptions;
+// @ts-ignore
+type NfsCreateWritableOptions = FileSystemCreateWritableOptions & { lock?: 'exclusive' } & NfsOperationOptions;
+export type NfsLockOptions = Omit<JsNfsLockOptions, 'abortFlag'> & NfsOperationOptions;
+export type NfsTextOptions = Omit<JsNfsTextOptions, 'abortFlag'> & NfsOperationOptions;
+/**
+ * File that can also decode its contents from encodings other than UTF-8, whole or a line at a time, and whose reads
+ * each take a signal and timeout of their own, where timeout defaults to the one given to getFile.
+ */
+export type NfsFile = File & {
+  arrayBuffer(options?: NfsOperationOptions): Promise<ArrayBuffer>;
+  stream(options?: NfsOperationOptions): ReadableStream<Uint8Array>;
+  text(options?: NfsTextOptions): Promise<string>;
+  lines(options?: NfsTextOptions): AsyncIterableIterator<string>;
+};
+export type NfsFileLock = JsNfsFileLock;
 // @ts-ignore
 type FileSystemWritableFileStream = FileSystemWritableFileStream;
 // @ts-ignore
 type FileSystemSyncAccessHandle = FileSystemSyncAccessHandle;
 
+const NO_MODIFICATION_ALLOWED = 'modification not allowed on read-only connection';
+
+function nameReadOnlyError(reason: any): any {
+  if (reason?.message !== undefined && reason.message.indexOf(NO_MODIFICATION_ALLOWED) != -1) {
+    reason.name = 'NoModificationAllowedError';
+  }
+  return reason;
+}
+
+function nameMetadataError(reason: any): any {
+  let errMsg: string = reason?.message;
+  if (errMsg !== undefined) {
+    if (errMsg.indexOf('not found') != -1) {
+      reason.name = 'NotFoundError';
+    } else if (errMsg.indexOf('not supported') != -1) {
+      reason.name = 'NotSupportedError';
+    } else if (errMsg.endsWith('is not allowed')) {
+      reason.name = 'TypeError';
+    } else if (errMsg.endsWith('already exists')) {
+      reason.name = 'InvalidModificationError';
+    }
+  }
+  return nameReadOnlyError(reason);
+}
+
+function toJsConnectOptions(options?: NfsConnectOptions): JsNfsConnectOptions | undefined {
+  const prompt = options?.permissionPolicy;
+  if (typeof prompt !== 'function') {
+    return options as JsNfsConnectOptions | undefined;
+  }
+  return { ...options, permissionPolicy: async (jsh, perm) => !!(await prompt(new NfsHandle(jsh), perm)) };
+}
+
+const OPERATION_ABORTED = 'operation was aborted';
+const OPERATION_TIMED_OUT = 'operation timed out';
+
+function nameCancellationError(reason: any): any {
+  if (reason?.message !== undefined) {
+    if (reason.message.indexOf(OPERATION_ABORTED) != -1) {
+      reason.name = 'AbortError';
+    } else if (reason.message.indexOf(OPERATION_TIMED_OUT) != -1) {
+      reason.name = 'TimeoutError';
+    }
+  }
+  return reason;
+}
+
+/**
+ * Runs native operation with a flag it checks in between RPCs, and settles as soon as signal is aborted or timeout
+ * expires, even when operation is still waiting on a server that does not respond.
+ */
+function withCancellation<O extends NfsOperationOptions, T>(options: O | undefined, operation: (options?: Omit<O, 'signal'> & { abortFlag?: Int32Array }) => Promise<T>): Promise<T> {
+  if (options?.signal === undefined && options?.timeoutMs === undefined) {
+    return operation(options);
+  }
+  const { signal, ...rest } = options;
+  const abortFlag = new Int32Array(new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT));
+  return new Promise((resolve, reject) => {
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const settle = () => {
+      clearTimeout(timer);
+      signal?.removeEventListener('abort', onAbort);
+    };
+    const cancel = (reason: any) => {
+      Atomics.store(abortFlag, 0, 1);
+      settle();
+      reject(reason);
+    };
+    const onAbort = () => cancel(signal?.reason ?? nameCancellationError(new Error(OPERATION_ABORTED)));
+    if (signal?.aborted) {
+      return onAbort();
+    }
+    signal?.addEventListener('abort', onAbort, { once: true });
+    if (rest.timeoutMs !== undefined) {
+      timer = setTimeout(() => cancel(nameCancellationError(new Error(OPERATION_TIMED_OUT))), rest.timeoutMs);
+    }
+    operation({ ...rest, abortFlag })
+      .then(resolve, (reason) => reject(nameCancellationError(reason)))
+      .finally(settle);
+  });
+}
+
+/**
+ * Turns signal into a flag that native streams and iterators check before each read, since they outlive any single
+ * promise withCancellation could settle early. Timeout is left to native side, which restarts it for every read.
+ */
+function withAbortFlag<O extends NfsOperationOptions>(options: O | undefined): (Omit<O, 'signal'> & { abortFlag?: Int32Array }) | undefined {
+  if (options?.signal === undefined) {
+    return options;
+  }
+  const { signal, ...rest } = options;
+  const abortFlag = new Int32Array(new SharedArrayBuffer(Int32Array.BYTES_PER_ELEMENT));
+  const onAbort = () => Atomics.store(abortFlag, 0, 1);
+  if (signal.aborted) {
+    onAbort();
+  } else {
+    signal.addEventListener('abort', onAbort, { once: true });
+  }
+  return { ...rest, abortFlag };
+}
+
+function wrapFile(_js: JsNfsFile): NfsFile {
+  return {
+    size: _js.size,
+    type: _js.type,
+    lastModified: _js.lastModified,
+    name: _js.name,
+    webkitRelativePath: '',
+    arrayBuffer: (options?: NfsOperationOptions) => withCancellation(options, (options) => _js.arrayBuffer(options)),
+    bytes: async (options?: NfsOperationOptions) => new Uint8Array(await withCancellation(options, (options) => _js.arrayBuffer(options))),
+    slice: (start?: number, end?: number, contentType?: string) => _js.slice(start, end, contentType),
+    stream: (options?: NfsOperationOptions) => {
+      const reader = _js.stream(withAbortFlag(options)).getReader();
+      return new ReadableStream<Uint8Array>({
+        pull: (controller) => reader.read().then(
+          ({ done, value }) => done ? controller.close() : controller.enqueue(value),
+          (reason) => { throw nameCancellationError(reason); }),
+        cancel: (reason) => reader.cancel(reason),
+      });
+    },
+    text: (options?: NfsTextOptions) => withCancellation(options, (options) => _js.text(options)),
+    lines: (options?: NfsTextOptions) => {
+      const lines = _js.lines(withAbortFlag(options));
+      return (async function* () {
+        try {
+          yield* lines;
+        } catch (reason) {
+          throw nameCancellationError(reason);
+        }
+      })();
+    },
+  } as NfsFile;
+}
+
 type TypedArray = Int8Array | Uint8Array | Uint8ClampedArray | Int16Array | Uint16Array | Int32Array | Uint32Array | Float32Array | Float64Array | BigInt64Array | BigUint64Array;
 
 export class NfsHandle implements FileSystemHandle {
   private _jsh: JsNfsHandle
   readonly kind: FileSystemHandleKind
   readonly name: string
+  /**
+   * Name as raw bytes, which differ from `name` if they are not valid UTF-8, since `name` is then decoded lossily, and
+   * such an entry can only be listed, since no path reaches it, with anything else done with it failing.
+   */
+  readonly nameBytes: Uint8Array
+  /**
+   * Kind of special file entry is, if connected with `specialFiles: 'expose'`, which `kind` still reports as file.
+   */
+  readonly special?: NfsSpecialKind
   /**
    * @deprecated Old property just for Chromium <=85. Use `kind` property in the new API.
    */
@@ -48,10 +235,12 @@ export class NfsHandle implements FileSystemHandle {
   readonly isDirectory: boolean
   constructor(_jsh: JsNfsHandle) {
     this._jsh = _jsh;
-    this.kind = _jsh.kind;
+    this.kind = _jsh.kind == 'directory' ? 'directory' : 'file';
     this.name = _jsh.name;
-    this.isFile = _jsh.kind == 'file';
-    this.isDirectory = _jsh.kind == 'directory';
+    this.nameBytes = _jsh.nameBytes;
+    this.special = _jsh.kind == 'directory' || _jsh.kind == 'file' ? undefined : _jsh.kind;
+    this.isFile = this.kind == 'file';
+    this.isDirectory = this.kind == 'directory';
   }
   isSameEntry(other: FileSystemHandle): Promise<boolean> {
     return new Promise(async (resolve, reject) => {
@@ -62,12 +251,43 @@ export class NfsHandle implements FileSystemHandle {
       }
     });
   }
+  async getUniqueId(): Promise<string> {
+    return this._jsh.getUniqueId();
+  }
+  serialize(): string {
+    return this._jsh.serialize();
+  }
   async queryPermission(perm: NfsHandlePermissionDescriptor): Promise<PermissionState> {
     return this._jsh.queryPermission(perm) as Promise<PermissionState>;
   }
   async requestPermission(perm: NfsHandlePermissionDescriptor): Promise<PermissionState> {
     return this._jsh.requestPermission(perm) as Promise<PermissionState>;
   }
+  async stat(options?: NfsOperationOptions): Promise<NfsStat> {
+    return withCancellation(options, (options) => this._jsh.stat(options));
+  }
+  async cacheStats(): Promise<NfsCacheStats> {
+    return this._jsh.cacheStats();
+  }
+  async getXattr(name: string): Promise<Uint8Array> {
+    return this._jsh.getXattr(name).catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async setXattr(name: string, value: ArrayBuffer | ArrayBufferView | string): Promise<void> {
+    const buffer = typeof value === 'string' ? Buffer.from(value) : ArrayBuffer.isView(value) ? Buffer.from(value.buffer, value.byteOffset, value.byteLength) : Buffer.from(value);
+    return this._jsh.setXattr(name, buffer).catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async listXattrs(): Promise<Array<string>> {
+    return this._jsh.listXattrs().catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async removeXattr(name: string): Promise<void> {
+    return this._jsh.removeXattr(name).catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async getAcl(): Promise<Array<NfsAce>> {
+    return this._jsh.getAcl().catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async setAcl(aces: Array<NfsAce>): Promise<void> {
+    return this._jsh.setAcl(aces).catch((reason) => { throw nameMetadataError(reason); });
+  }
 }
 
 export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectoryHandle {
@@ -82,11 +302,11 @@ export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectory
    */
   readonly isDirectory: true
   private _js: JsNfsDirectoryHandle
-  constructor(url: string);
+  constructor(url: string, options?: NfsConnectOptions);
   constructor(toWrap: JsNfsDirectoryHandle);
-  constructor(param: string | JsNfsDirectoryHandle) {
+  constructor(param: string | JsNfsDirectoryHandle, options?: NfsConnectOptions) {
     const [url, toWrap] = typeof param === 'string' ? [param] : ['', param];
-    const _js = toWrap || new JsNfsDirectoryHandle(url);
+    const _js = toWrap || new JsNfsDirectoryHandle(url, toJsConnectOptions(options));
     super(_js.toHandle());
     this[Symbol.asyncIterator] = this.entries;
     this._js = _js;
@@ -97,6 +317,13 @@ export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectory
     this.getDirectory = this.getDirectoryHandle;
     this.getEntries = this.values;
   }
+  static deserialize(serialized: string, options?: NfsDeserializeOptions): NfsDirectoryHandle | NfsFileHandle {
+    const handle = JsNfsDirectoryHandle.deserialize(serialized, options);
+    return handle instanceof JsNfsDirectoryHandle ? new NfsDirectoryHandle(handle) : new NfsFileHandle(handle);
+  }
+  withCredentials(credentials: NfsCredentials): NfsDirectoryHandle {
+    return new NfsDirectoryHandle(this._js.withCredentials(credentials));
+  }
   async *entries(): AsyncIterableIterator<[string, FileSystemDirectoryHandle | FileSystemFileHandle]> {
     for await (const [key, value] of this._js.entries()) {
       yield [key, value instanceof JsNfsDirectoryHandle ? new NfsDirectoryHandle(value) as FileSystemDirectoryHandle : new NfsFileHandle(value) as FileSystemFileHandle];
@@ -112,9 +339,9 @@ export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectory
       yield value instanceof JsNfsDirectoryHandle ? new NfsDirectoryHandle(value) as FileSystemDirectoryHandle : new NfsFileHandle(value) as FileSystemFileHandle;
     }
   }
-  async getDirectoryHandle(name: string, options?: FileSystemGetDirectoryOptions): Promise<FileSystemDirectoryHandle> {
+  async getDirectoryHandle(name: string, options?: NfsGetDirectoryOptions): Promise<FileSystemDirectoryHandle> {
     return new Promise(async (resolve, reject) => {
-      await this._js.getDirectoryHandle(name, options)
+      await withCancellation(options, (options) => this._js.getDirectoryHandle(name, options))
         .then((handle) => resolve(new NfsDirectoryHandle(handle) as FileSystemDirectoryHandle))
         .catch((reason) => {
           let errMsg: string = reason.message;
@@ -123,15 +350,23 @@ export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectory
               reason.name = 'TypeMismatchError';
             } else if (errMsg.indexOf('not found') != -1) {
               reason.name = 'NotFoundError';
+            } else if (errMsg.endsWith('is not allowed')) {
+              reason.name = 'TypeError';
             }
           }
-          reject(reason);
+          reject(nameReadOnlyError(reason));
         });
     });
   }
-  async getFileHandle(name: string, options?: FileSystemGetFileOptions): Promise<FileSystemFileHandle> {
+  /**
+   * Creates directory at path relative to this one, along with whatever parents of it are missing, like `mkdir -p`.
+   */
+  async mkdirp(path: string, options?: Omit<NfsGetDirectoryOptions, 'create' | 'recursive'>): Promise<FileSystemDirectoryHandle> {
+    return this.getDirectoryHandle(path, { ...options, create: true, recursive: true });
+  }
+  async getFileHandle(name: string, options?: NfsGetFileOptions): Promise<FileSystemFileHandle> {
     return new Promise(async (resolve, reject) => {
-      await this._js.getFileHandle(name, options)
+      await withCancellation(options, (options) => this._js.getFileHandle(name, options))
         .then((handle) => resolve(new NfsFileHandle(handle) as FileSystemFileHandle))
         .catch((reason) => {
           let errMsg: string = reason.message;
@@ -140,17 +375,29 @@ export class NfsDirectoryHandle extends NfsHandle implements FileSystemDirectory
               reason.name = 'TypeMismatchError';
             } else if (errMsg.indexOf('not found') != -1) {
               reason.name = 'NotFoundError';
+            } else if (errMsg.indexOf('not supported') != -1) {
+              reason.name = 'NotSupportedError';
+            } else if (errMsg.endsWith('is not allowed')) {
+              reason.name = 'TypeError';
+            } else if (errMsg.endsWith('already exists')) {
+              reason.name = 'InvalidModificationError';
             }
           }
-          reject(reason);
+          reject(nameReadOnlyError(reason));
         });
     });
   }
-  async removeEntry(name: string, options?: FileSystemRemoveOptions): Promise<void> {
-    return this._js.removeEntry(name, options);
+  async mknod(name: string, kind: NfsSpecialKind, options?: NfsMknodOptions): Promise<void> {
+    return withCancellation(options, (options) => this._js.mknod(name, kind, options)).catch((reason) => { throw nameMetadataError(reason); });
   }
-  async resolve(possibleDescendant: FileSystemHandle): Promise<Array<string> | null> {
-    return this._js.resolve((possibleDescendant as any)._jsh || possibleDescendant);
+  async mkfifo(name: string, options?: NfsMknodOptions): Promise<void> {
+    return withCancellation(options, (options) => this._js.mkfifo(name, options)).catch((reason) => { throw nameMetadataError(reason); });
+  }
+  async removeEntry(name: string, options?: NfsRemoveOptions): Promise<void> {
+    return withCancellation(options, (options) => this._js.removeEntry(name, options)).catch((reason) => { throw nameReadOnlyError(reason); });
+  }
+  async resolve(possibleDescendant: FileSystemHandle, options?: NfsOperationOptions): Promise<Array<string> | null> {
+    return withCancellation(options, (options) => this._js.resolve((possibleDescendant as any)._jsh || possibleDescendant, options));
   }
 
   /**
@@ -185,19 +432,25 @@ export class NfsFileHandle extends NfsHandle implements FileSystemFileHandle {
     this.isFile = true;
     this.isDirectory = false;
   }
-  async getFile(): Promise<File> {
-    return this._js.getFile();
+  withCredentials(credentials: NfsCredentials): NfsFileHandle {
+    return new NfsFileHandle(this._js.withCredentials(credentials));
+  }
+  async getFile(options?: NfsOperationOptions): Promise<NfsFile> {
+    return withCancellation(options, (options) => this._js.getFile(options)).then((file) => wrapFile(file as unknown as JsNfsFile));
   }
   async createSyncAccessHandle(): Promise<FileSystemSyncAccessHandle> {
     throw new Error("createSyncAccessHandle not implemented");
   }
   async createWritable(options?: NfsCreateWritableOptions): Promise<FileSystemWritableFileStream> {
     return new Promise(async (resolve, reject) => {
-      await this._js.createWritable(options)
+      await withCancellation(options, (options) => this._js.createWritable(options))
         .then((stream) => resolve(new NfsWritableFileStream(stream) as FileSystemWritableFileStream))
-        .catch((reason) => reject(reason));
+        .catch((reason) => reject(nameMetadataError(reason)));
     });
   }
+  async lock(options?: NfsLockOptions): Promise<NfsFileLock> {
+    return withCancellation(options, (options) => this._js.lock(options)).catch((reason) => { throw nameMetadataError(reason); });
+  }
 }
 
 interface NfsWritableFileStreamLock { locked: boolean }
@@ -222,9 +475,9 @@ export class NfsWritableFileStream implements NfsWritableFileStreamLock {
       try {
         await this._js.write(data)
           .then(() => resolve())
-          .catch((reason) => reject(reason));
+          .catch((reason) => reject(nameReadOnlyError(reason)));
       } catch(reason) {
-        reject(reason);
+        reject(nameReadOnlyError(reason));
       }
     });
   }
@@ -232,10 +485,16 @@ export class NfsWritableFileStream implements NfsWritableFileStreamLock {
     return this._js.seek(position);
   }
   async truncate(size: number): Promise<void> {
-    return this._js.truncate(size);
+    return this._js.truncate(size).catch((reason) => { throw nameReadOnlyError(reason); });
+  }
+  async allocate(offset: number, length: number): Promise<void> {
+    return this._js.allocate(offset, length).catch((reason) => { throw nameReadOnlyError(reason); });
+  }
+  async punchHole(offset: number, length: number): Promise<void> {
+    return this._js.punchHole(offset, length).catch((reason) => { throw nameMetadataError(reason); });
   }
   async close(): Promise<void> {
-    return this._js.close();
+    return this._js.close().catch((reason) => { throw nameReadOnlyError(reason); });
   }
   async abort(reason: string): Promise<void> {
     return new Promise(async (resolve, reject) => {
@@ -6,17 +6,126 @@
 export interface JsNfsHandlePermissionDescriptor {
   mode: 'read' | 'readwrite'
 }
+export interface JsNfsAce {
+  type: 'allow' | 'deny' | 'audit' | 'alarm'
+  flags: number
+  mask: number
+  who: string
+}
+export interface JsNfsStat {
+  dev: number
+  ino: number
+  mode: number
+  nlink: number
+  uid: number
+  gid: number
+  rdev: number
+  rdevMajor: number
+  rdevMinor: number
+  size: number
+  used: number
+  blksize: number
+  blocks: number
+  atime: number
+  mtime: number
+  ctime: number
+  atimeNsec: number
+  mtimeNsec: number
+  ctimeNsec: number
+}
+export interface JsNfsCacheStats {
+  nameCacheHits: number
+  nameCacheMisses: number
+  nameCacheEntries: number
+  dataCacheHits: number
+  dataCacheMisses: number
+  dataCacheBytes: number
+}
 export interface JsNfsGetDirectoryOptions {
   create?: boolean
+  recursive?: boolean
+  mode?: number
+  timeoutMs?: number
+  abortFlag?: Int32Array
 }
 export interface JsNfsGetFileOptions {
   create?: boolean
+  exclusive?: boolean
+  mode?: number
+  timeoutMs?: number
+  abortFlag?: Int32Array
+}
+export interface JsNfsMknodOptions {
+  mode?: number
+  major?: number
+  minor?: number
+  timeoutMs?: number
+  abortFlag?: Int32Array
+}
+export interface JsNfsTextOptions {
+  encoding?: string
+  fatal?: boolean
+  ignoreBOM?: boolean
+  timeoutMs?: number
+  abortFlag?: Int32Array
+}
+export interface JsNfsTextLinesResult {
+  value?: string
+  done: boolean
+}
+export interface JsNfsOperationOptions {
+  timeoutMs?: number
+  abortFlag?: Int32Array
+}
+export interface JsNfsConnectOptions {
+  root?: string
+  readOnly?: boolean
+  permissionPolicy?: 'chmod' | 'never' | ((handle: JsNfsHandle, perm: JsNfsHandlePermissionDescriptor) => Promise<boolean>)
+  specialFiles?: 'expose' | 'file' | 'skip'
+  uid?: number
+  gid?: number
+  auxGids?: Array<number>
+  xprtsec?: 'none' | 'tls' | 'mtls'
+  retries?: number
+  timeoutMs?: number
+  backoffMs?: number
+  nconnect?: number
+  actimeo?: number
+  acregmin?: number
+  acregmax?: number
+  acdirmin?: number
+  acdirmax?: number
+  lookupcache?: 'all' | 'none' | 'positive'
+  dataCacheSize?: number
+  dataCacheDir?: string
+  dataCacheDirSize?: number
+}
+export interface JsNfsCredentials {
+  uid?: number
+  gid?: number
+  auxGids?: Array<number>
+}
+export interface JsNfsDeserializeOptions {
+  url?: string
 }
 export interface JsNfsRemoveOptions {
   recursive?: boolean
+  timeoutMs?: number
+  abortFlag?: Int32Array
 }
 export interface JsNfsCreateWritableOptions {
   keepExistingData?: boolean
+  lock?: 'exclusive'
+  timeoutMs?: number
+  abortFlag?: Int32Array
+}
+export interface JsNfsLockOptions {
+  offset?: number
+  length?: number
+  mode?: 'exclusive' | 'shared'
+  wait?: boolean
+  timeoutMs?: number
+  abortFlag?: Int32Array
 }
 export declare class JsNfsDirectoryHandleEntries {
   [Symbol.asyncIterator]: AsyncIterableIterator<[string, JsNfsDirectoryHandle | JsNfsFileHandle]>
@@ -28,48 +137,96 @@ export declare class JsNfsDirectoryHandleValues {
   [Symbol.asyncIterator]: AsyncIterableIterator<JsNfsDirectoryHandle | JsNfsFileHandle>
 }
 export declare class JsNfsHandle {
-  readonly kind: 'directory' | 'file'
+  readonly kind: 'directory' | 'file' | 'blockDevice' | 'characterDevice' | 'fifo' | 'socket'
   readonly name: string
+  get nameBytes(): Buffer
   isSameEntry(other: JsNfsHandle): boolean
+  getUniqueId(): string
+  serialize(): string
+  withCredentials(credentials: JsNfsCredentials): JsNfsHandle
   queryPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
+  stat(options?: JsNfsOperationOptions): Promise<JsNfsStat>
+  cacheStats(): Promise<JsNfsCacheStats>
+  getXattr(name: string): Promise<Buffer>
+  setXattr(name: string, value: Buffer): Promise<void>
+  listXattrs(): Promise<Array<string>>
+  removeXattr(name: string): Promise<void>
+  getAcl(): Promise<Array<JsNfsAce>>
+  setAcl(aces: Array<JsNfsAce>): Promise<void>
   requestPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
 }
 export declare class JsNfsDirectoryHandle {
   [Symbol.asyncIterator]: JsNfsDirectoryHandle['entries']
   readonly kind: 'directory'
   readonly name: string
-  constructor(url: string)
+  constructor(url: string, options?: JsNfsConnectOptions)
+  static deserialize(serialized: string, options?: JsNfsDeserializeOptions): JsNfsDirectoryHandle | JsNfsFileHandle
   toHandle(): JsNfsHandle
   isSameEntry(other: JsNfsHandle): boolean
+  getUniqueId(): string
+  serialize(): string
+  withCredentials(credentials: JsNfsCredentials): JsNfsDirectoryHandle
   queryPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
   requestPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
+  stat(options?: JsNfsOperationOptions): Promise<JsNfsStat>
+  cacheStats(): Promise<JsNfsCacheStats>
+  getXattr(name: string): Promise<Buffer>
+  setXattr(name: string, value: Buffer): Promise<void>
+  listXattrs(): Promise<Array<string>>
+  removeXattr(name: string): Promise<void>
+  getAcl(): Promise<Array<JsNfsAce>>
+  setAcl(aces: Array<JsNfsAce>): Promise<void>
   entries(): AsyncIterableIterator<[string, JsNfsDirectoryHandle | JsNfsFileHandle]>
   keys(): AsyncIterableIterator<string>
   values(): AsyncIterableIterator<JsNfsDirectoryHandle | JsNfsFileHandle>
   getDirectoryHandle(name: string, options?: JsNfsGetDirectoryOptions): Promise<JsNfsDirectoryHandle>
   getFileHandle(name: string, options?: JsNfsGetFileOptions): Promise<JsNfsFileHandle>
+  mknod(name: string, kind: 'blockDevice' | 'characterDevice' | 'fifo' | 'socket', options?: JsNfsMknodOptions): Promise<void>
+  mkfifo(name: string, options?: JsNfsMknodOptions): Promise<void>
   removeEntry(name: string, options?: JsNfsRemoveOptions): Promise<void>
-  resolve(possibleDescendant: JsNfsHandle): Promise<Array<string> | null>
+  resolve(possibleDescendant: JsNfsHandle, options?: JsNfsOperationOptions): Promise<Array<string> | null>
 }
 export declare class JsNfsFileHandle {
   readonly kind: 'file'
   readonly name: string
   toHandle(): JsNfsHandle
   isSameEntry(other: JsNfsHandle): boolean
+  getUniqueId(): string
+  serialize(): string
+  withCredentials(credentials: JsNfsCredentials): JsNfsFileHandle
   queryPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
   requestPermission(perm: JsNfsHandlePermissionDescriptor): Promise<string>
-  getFile(): Promise<File>
+  stat(options?: JsNfsOperationOptions): Promise<JsNfsStat>
+  cacheStats(): Promise<JsNfsCacheStats>
+  getXattr(name: string): Promise<Buffer>
+  setXattr(name: string, value: Buffer): Promise<void>
+  listXattrs(): Promise<Array<string>>
+  removeXattr(name: string): Promise<void>
+  getAcl(): Promise<Array<JsNfsAce>>
+  setAcl(aces: Array<JsNfsAce>): Promise<void>
+  getFile(options?: JsNfsOperationOptions): Promise<File>
   createWritable(options?: JsNfsCreateWritableOptions): Promise<JsNfsWritableFileStream>
+  lock(options?: JsNfsLockOptions): Promise<JsNfsFileLock>
+}
+export declare class JsNfsFileLock {
+  readonly offset: number
+  readonly length: number
+  readonly mode: 'exclusive' | 'shared'
+  release(): Promise<void>
 }
 export declare class JsNfsFile {
   readonly size: number
   readonly type: string
   readonly lastModified: number
   readonly name: string
-  arrayBuffer(): Promise<ArrayBuffer>
+  arrayBuffer(options?: JsNfsOperationOptions): Promise<ArrayBuffer>
   slice(start?: number, end?: number, contentType?: string): Blob
-  stream(): ReadableStream<Uint8Array>
-  text(): Promise<string>
+  stream(options?: JsNfsOperationOptions): ReadableStream<Uint8Array>
+  text(options?: JsNfsTextOptions): Promise<string>
+  lines(options?: JsNfsTextOptions): AsyncIterableIterator<string>
+}
+export declare class JsNfsTextLines {
+  next(): Promise<JsNfsTextLinesResult>
 }
 export declare class JsNfsReadableStreamSource {
   readonly type: 'bytes'
@@ -94,6 +251,8 @@ export declare class JsNfsWritableFileStream {
   ): Promise<void>
   seek(position: number): Promise<void>
   truncate(size: number): Promise<void>
+  allocate(offset: number, length: number): Promise<void>
+  punchHole(offset: number, length: number): Promise<void>
   close(): Promise<void>
   abort(reason: string): Promise<string>
   releaseLock(): void
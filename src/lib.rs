@@ -16,15 +16,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 
-use napi::{JsArrayBuffer, JsDataView, JsString, JsTypedArray, NapiRaw, bindgen_prelude::*};
+use napi::{JsArrayBuffer, JsDataView, JsString, JsSymbol, JsTypedArray, JsUnknown, NapiRaw, bindgen_prelude::*};
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction};
 use napi_derive::napi;
 use nix::fcntl::OFlag;
-use nix::sys::stat::Mode;
+use nix::sys::stat::{Mode, SFlag};
 use send_wrapper::SendWrapper;
-use std::{path::Path, sync::{Arc, RwLock, RwLockWriteGuard}};
+use std::{collections::VecDeque, path::Path, sync::{Arc, Mutex, RwLock, RwLockWriteGuard, atomic::{AtomicI32, Ordering}}, time::{Duration, Instant}};
 
 mod nfs;
-use nfs::{NFS, NFSEntryType};
+use nfs::{ACCESS_DELETE, ACCESS_EXTEND, ACCESS_LOOKUP, ACCESS_MODIFY, ACCESS_READ, ACE_TYPE_ALARM, ACE_TYPE_ALLOW, ACE_TYPE_AUDIT, ACE_TYPE_DENY};
+use nfs::{Ace, CreateMode, NFS, NFSDirEntry, NFSDirectory, NFSEntryType, NFSFile, NFSStat64};
 
 /*
 
@@ -126,6 +128,30 @@ interface Blob {
 const FIELD_KIND: &str = "kind";
 const FIELD_NAME: &str = "name";
 const FIELD_PATH: &str = "path";
+const FIELD_URL: &str = "url";
+const FIELD_ROOT: &str = "root";
+const FIELD_READ_ONLY: &str = "readOnly";
+const FIELD_PERMISSION_POLICY: &str = "permissionPolicy";
+const FIELD_SPECIAL_FILES: &str = "specialFiles";
+const FIELD_UID: &str = "uid";
+const FIELD_GID: &str = "gid";
+const FIELD_AUX_GIDS: &str = "auxGids";
+const FIELD_XPRTSEC: &str = "xprtsec";
+const FIELD_RETRIES: &str = "retries";
+const FIELD_TIMEOUT_MS: &str = "timeoutMs";
+const FIELD_BACKOFF_MS: &str = "backoffMs";
+const FIELD_NCONNECT: &str = "nconnect";
+const FIELD_ACREGMIN: &str = "acregmin";
+const FIELD_ACREGMAX: &str = "acregmax";
+const FIELD_ACDIRMIN: &str = "acdirmin";
+const FIELD_ACDIRMAX: &str = "acdirmax";
+const FIELD_LOOKUPCACHE: &str = "lookupcache";
+const FIELD_DATA_CACHE_SIZE: &str = "dataCacheSize";
+const FIELD_DATA_CACHE_DIR: &str = "dataCacheDir";
+const FIELD_DATA_CACHE_DIR_SIZE: &str = "dataCacheDirSize";
+const FIELD_FSID: &str = "fsid";
+const FIELD_FILEID: &str = "fileid";
+const FIELD_FH: &str = "fh";
 const FIELD_DATA: &str = "data";
 const FIELD_TYPE: &str = "type";
 const FIELD_SIZE: &str = "size";
@@ -136,16 +162,38 @@ const FIELD_ENQUEUE: &str = "enqueue";
 const FIELD_POSITION: &str = "position";
 const FIELD_SUBSTRING: &str = "substring";
 const FIELD_BYTE_LENGTH: &str = "byteLength";
+const FIELD_ASYNC_ITERATOR: &str = "asyncIterator";
 
 const KIND_FILE: &str = "file";
 const KIND_DIRECTORY: &str = "directory";
+const KIND_BLOCK_DEVICE: &str = "blockDevice";
+const KIND_CHARACTER_DEVICE: &str = "characterDevice";
+const KIND_FIFO: &str = "fifo";
+const KIND_SOCKET: &str = "socket";
 
 const PERM_READ: &str = "read";
 const PERM_READWRITE: &str = "readwrite";
 
+const ACE_TYPE_NAME_ALLOW: &str = "allow";
+const ACE_TYPE_NAME_DENY: &str = "deny";
+const ACE_TYPE_NAME_AUDIT: &str = "audit";
+const ACE_TYPE_NAME_ALARM: &str = "alarm";
+
 const PERM_STATE_GRANTED: &str = "granted";
 const PERM_STATE_DENIED: &str = "denied";
-const _PERM_STATE_PROMPT: &str = "prompt";
+const PERM_STATE_PROMPT: &str = "prompt";
+
+const PERM_POLICY_NEVER: &str = "never";
+const PERM_POLICY_CHMOD: &str = "chmod";
+const PERM_POLICY_PROMPT: &str = "prompt";
+
+const SPECIAL_FILES_FILE: &str = "file";
+const SPECIAL_FILES_SKIP: &str = "skip";
+const SPECIAL_FILES_EXPOSE: &str = "expose";
+
+const LOOKUP_CACHE_ALL: &str = "all";
+const LOOKUP_CACHE_POSITIVE: &str = "positive";
+const LOOKUP_CACHE_NONE: &str = "none";
 
 const WRITE_TYPE_WRITE: &str = "write";
 const WRITE_TYPE_SEEK: &str = "seek";
@@ -155,15 +203,31 @@ const DIR_ROOT: &str = "/";
 const DIR_CURRENT: &str = ".";
 const DIR_PARENT: &str = "..";
 
+const MAX_SYMLINKS: usize = 40; // XXX: same limit as Linux
+
+const DEFAULT_DIR_MODE: u32 = 0o775;
+const DEFAULT_FILE_MODE: u32 = 0o666; // XXX: change mode value to 0o664?
+
 const MIME_TYPE_UNKNOWN: &str = "unknown";
 
+const OPERATION_ABORTED: &str = "operation was aborted";
+const OPERATION_TIMED_OUT: &str = "operation timed out";
+
+const LOCK_MODE_EXCLUSIVE: &str = "exclusive";
+const LOCK_MODE_SHARED: &str = "shared";
+const LOCK_POLL_MIN_MS: u64 = 10;
+const LOCK_POLL_MAX_MS: u64 = 1000;
+
 const JS_TYPE_BLOB: &str = "Blob";
+const JS_TYPE_SYMBOL: &str = "Symbol";
 const JS_TYPE_READABLE_STREAM: &str = "ReadableStream";
 const JS_TYPE_WRITABLE_STREAM: &str = "WritableStream";
 const JS_TYPE_WRITABLE_STREAM_DEFAULT_WRITER: &str = "WritableStreamDefaultWriter";
 
 const READABLE_STREAM_SOURCE_TYPE_BYTES: &str = "bytes";
 
+const TEXT_ENCODING_DEFAULT: &str = "utf-8";
+
 #[napi(iterator)]
 pub struct JsNfsDirectoryHandleEntries {
   #[napi(js_name="[Symbol.asyncIterator]", ts_type="AsyncIterableIterator<[string, JsNfsDirectoryHandle | JsNfsFileHandle]>")]
@@ -254,6 +318,10 @@ impl Generator for JsNfsDirectoryHandleValues {
   }
 }
 
+// XXX: called with handle and descriptor whenever permissionPolicy is prompt and permission is not yet granted, and
+// must resolve to whether user confirmed, which only native code gets to see so that callers cannot skip the prompt
+type JsNfsPermissionPrompt = ThreadsafeFunction<(JsNfsHandle, JsNfsHandlePermissionDescriptor), ErrorStrategy::Fatal>;
+
 #[napi(object)]
 pub struct JsNfsHandlePermissionDescriptor {
   #[napi(ts_type="'read' | 'readwrite'")]
@@ -271,56 +339,455 @@ impl JsNfsHandlePermissionDescriptor {
     }
   }
 
-  fn to_u64(&self, kind: &str) -> u64 {
-    self.to_mode(kind).bits().into()
+  // Returns ACCESS bits that must all be granted for permission: listing and looking up entries for reading directory,
+  // plus adding, changing and removing entries for writing it, and reading data for file, plus changing and appending
+  // to it for writing it.
+  fn to_access(&self, kind: &str) -> u32 {
+    match (kind, self.mode.as_str()) {
+      (KIND_DIRECTORY, PERM_READWRITE) => ACCESS_READ | ACCESS_LOOKUP | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE,
+      (KIND_DIRECTORY, _) => ACCESS_READ | ACCESS_LOOKUP,
+      (_, PERM_READWRITE) => ACCESS_READ | ACCESS_MODIFY | ACCESS_EXTEND,
+      _ => ACCESS_READ
+    }
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsAce {
+  #[napi(js_name="type", ts_type="'allow' | 'deny' | 'audit' | 'alarm'")]
+  pub type_: String,
+  pub flags: u32,
+  pub mask: u32,
+  pub who: String
+}
+
+impl From<Ace> for JsNfsAce {
+
+  fn from(ace: Ace) -> Self {
+    let type_ = match ace.type_ {
+      ACE_TYPE_ALLOW => ACE_TYPE_NAME_ALLOW,
+      ACE_TYPE_DENY => ACE_TYPE_NAME_DENY,
+      ACE_TYPE_AUDIT => ACE_TYPE_NAME_AUDIT,
+      _ => ACE_TYPE_NAME_ALARM
+    };
+    Self{type_: type_.into(), flags: ace.flags, mask: ace.mask, who: ace.who}
+  }
+}
+
+impl TryFrom<JsNfsAce> for Ace {
+  type Error = Error;
+
+  fn try_from(ace: JsNfsAce) -> Result<Self> {
+    let type_ = match ace.type_.as_str() {
+      ACE_TYPE_NAME_ALLOW => ACE_TYPE_ALLOW,
+      ACE_TYPE_NAME_DENY => ACE_TYPE_DENY,
+      ACE_TYPE_NAME_AUDIT => ACE_TYPE_AUDIT,
+      ACE_TYPE_NAME_ALARM => ACE_TYPE_ALARM,
+      _ => return Err(Error::new(Status::InvalidArg, format!("ACE type {:?} is not allowed", ace.type_)))
+    };
+    if ace.who.is_empty() {
+      return Err(Error::new(Status::InvalidArg, format!("ACE who {:?} is not allowed", ace.who)));
+    }
+    Ok(Self{type_, flags: ace.flags, mask: ace.mask, who: ace.who})
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsStat {
+  pub dev: i64,
+  pub ino: i64,
+  pub mode: i64,
+  pub nlink: i64,
+  pub uid: i64,
+  pub gid: i64,
+  pub rdev: i64,
+  pub rdev_major: u32,
+  pub rdev_minor: u32,
+  pub size: i64,
+  pub used: i64,
+  pub blksize: i64,
+  pub blocks: i64,
+  pub atime: i64,
+  pub mtime: i64,
+  pub ctime: i64,
+  pub atime_nsec: i64,
+  pub mtime_nsec: i64,
+  pub ctime_nsec: i64
+}
+
+impl From<NFSStat64> for JsNfsStat {
+
+  fn from(stat: NFSStat64) -> Self {
+    Self{
+      dev: stat.dev as i64,
+      ino: stat.ino as i64,
+      mode: stat.mode as i64,
+      nlink: stat.nlink as i64,
+      uid: stat.uid as i64,
+      gid: stat.gid as i64,
+      rdev: stat.rdev as i64,
+      rdev_major: nfs::rdev_major(stat.rdev),
+      rdev_minor: nfs::rdev_minor(stat.rdev),
+      size: stat.size as i64,
+      used: stat.used as i64,
+      blksize: stat.blksize as i64,
+      blocks: stat.blocks as i64,
+      atime: stat.atime as i64,
+      mtime: stat.mtime as i64,
+      ctime: stat.ctime as i64,
+      atime_nsec: stat.atime_nsec as i64,
+      mtime_nsec: stat.mtime_nsec as i64,
+      ctime_nsec: stat.ctime_nsec as i64,
+    }
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsCacheStats {
+  pub name_cache_hits: i64,
+  pub name_cache_misses: i64,
+  pub name_cache_entries: i64,
+  pub data_cache_hits: i64,
+  pub data_cache_misses: i64,
+  pub data_cache_bytes: i64
+}
+
+impl From<nfs::CacheStats> for JsNfsCacheStats {
+
+  fn from(stats: nfs::CacheStats) -> Self {
+    Self{
+      name_cache_hits: stats.name_hits as i64,
+      name_cache_misses: stats.name_misses as i64,
+      name_cache_entries: stats.name_entries as i64,
+      data_cache_hits: stats.data_hits as i64,
+      data_cache_misses: stats.data_misses as i64,
+      data_cache_bytes: stats.data_bytes as i64,
+    }
   }
 }
 
 #[napi(object)]
 pub struct JsNfsGetDirectoryOptions {
-  pub create: Option<bool>
+  pub create: Option<bool>,
+  pub recursive: Option<bool>,
+  pub mode: Option<u32>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
 }
 
 impl Default for JsNfsGetDirectoryOptions {
 
   fn default() -> Self {
-    Self{create: Default::default()}
+    Self{create: Default::default(), recursive: Default::default(), mode: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
   }
 }
 
 #[napi(object)]
 pub struct JsNfsGetFileOptions {
-  pub create: Option<bool>
+  pub create: Option<bool>,
+  pub exclusive: Option<bool>,
+  pub mode: Option<u32>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
 }
 
 impl Default for JsNfsGetFileOptions {
 
   fn default() -> Self {
-    Self{create: Default::default()}
+    Self{create: Default::default(), exclusive: Default::default(), mode: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsMknodOptions {
+  pub mode: Option<u32>,
+  pub major: Option<u32>,
+  pub minor: Option<u32>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
+}
+
+impl Default for JsNfsMknodOptions {
+
+  fn default() -> Self {
+    Self{mode: Default::default(), major: Default::default(), minor: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsTextOptions {
+  pub encoding: Option<String>,
+  pub fatal: Option<bool>,
+  #[napi(js_name="ignoreBOM")]
+  pub ignore_bom: Option<bool>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
+}
+
+impl Default for JsNfsTextOptions {
+
+  fn default() -> Self {
+    Self{encoding: Default::default(), fatal: Default::default(), ignore_bom: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsTextLinesResult {
+  pub value: Option<String>,
+  pub done: bool
+}
+
+#[napi(object)]
+pub struct JsNfsOperationOptions {
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
+}
+
+impl Default for JsNfsOperationOptions {
+
+  fn default() -> Self {
+    Self{timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsConnectOptions {
+  pub root: Option<String>,
+  pub read_only: Option<bool>,
+  #[napi(ts_type="'chmod' | 'never' | ((handle: JsNfsHandle, perm: JsNfsHandlePermissionDescriptor) => Promise<boolean>)")]
+  pub permission_policy: Option<Either<String, JsFunction>>,
+  #[napi(ts_type="'expose' | 'file' | 'skip'")]
+  pub special_files: Option<String>,
+  pub uid: Option<u32>,
+  pub gid: Option<u32>,
+  pub aux_gids: Option<Vec<u32>>,
+  #[napi(ts_type="'none' | 'tls' | 'mtls'")]
+  pub xprtsec: Option<String>,
+  pub retries: Option<u32>,
+  pub timeout_ms: Option<u32>,
+  pub backoff_ms: Option<u32>,
+  pub nconnect: Option<u32>,
+  pub actimeo: Option<u32>,
+  pub acregmin: Option<u32>,
+  pub acregmax: Option<u32>,
+  pub acdirmin: Option<u32>,
+  pub acdirmax: Option<u32>,
+  #[napi(ts_type="'all' | 'none' | 'positive'")]
+  pub lookupcache: Option<String>,
+  pub data_cache_size: Option<i64>,
+  pub data_cache_dir: Option<String>,
+  pub data_cache_dir_size: Option<i64>
+}
+
+impl Default for JsNfsConnectOptions {
+
+  fn default() -> Self {
+    Self{
+      root: Default::default(),
+      read_only: Default::default(),
+      permission_policy: Default::default(),
+      special_files: Default::default(),
+      uid: Default::default(),
+      gid: Default::default(),
+      aux_gids: Default::default(),
+      xprtsec: Default::default(),
+      retries: Default::default(),
+      timeout_ms: Default::default(),
+      backoff_ms: Default::default(),
+      nconnect: Default::default(),
+      actimeo: Default::default(),
+      acregmin: Default::default(),
+      acregmax: Default::default(),
+      acdirmin: Default::default(),
+      acdirmax: Default::default(),
+      lookupcache: Default::default(),
+      data_cache_size: Default::default(),
+      data_cache_dir: Default::default(),
+      data_cache_dir_size: Default::default()
+    }
+  }
+}
+
+impl TryFrom<JsNfsConnectOptions> for nfs::ConnectOptions {
+  type Error = Error;
+
+  fn try_from(options: JsNfsConnectOptions) -> Result<Self> {
+    let credentials = nfs::Credentials{uid: options.uid, gid: options.gid, aux_gids: options.aux_gids};
+    let tls = nfs::TlsOptions{xprtsec: options.xprtsec};
+    let default_retry = nfs::RetryOptions::default();
+    let retry = nfs::RetryOptions{
+      retries: options.retries.unwrap_or(default_retry.retries),
+      timeout_ms: options.timeout_ms.or(default_retry.timeout_ms),
+      backoff_ms: options.backoff_ms.unwrap_or(default_retry.backoff_ms)
+    };
+    // XXX: actimeo sets all four timeouts at once, as the mount option does, while any of them given as well wins, and
+    //      caching stays off unless at least one of them is given
+    let asked_for = [options.acregmin, options.acregmax, options.acdirmin, options.acdirmax].iter().any(Option::is_some);
+    let default_attr_cache = match options.actimeo {
+      Some(actimeo) => nfs::AttrCacheOptions{acregmin: actimeo, acregmax: actimeo, acdirmin: actimeo, acdirmax: actimeo},
+      None if asked_for => nfs::AttrCacheOptions::kernel_defaults(),
+      None => nfs::AttrCacheOptions::default()
+    };
+    let attr_cache = nfs::AttrCacheOptions{
+      acregmin: options.acregmin.unwrap_or(default_attr_cache.acregmin),
+      acregmax: options.acregmax.unwrap_or(default_attr_cache.acregmax),
+      acdirmin: options.acdirmin.unwrap_or(default_attr_cache.acdirmin),
+      acdirmax: options.acdirmax.unwrap_or(default_attr_cache.acdirmax)
+    };
+    let default_data_cache = nfs::DataCacheOptions::default();
+    let data_cache = nfs::DataCacheOptions{
+      max_bytes: options.data_cache_size.map(|size| size.max(0) as u64).unwrap_or(default_data_cache.max_bytes),
+      spill_dir: options.data_cache_dir,
+      spill_max_bytes: options.data_cache_dir_size.map(|size| size.max(0) as u64).unwrap_or(default_data_cache.spill_max_bytes)
+    };
+    let lookup_cache = get_lookup_cache(options.lookupcache.as_deref())?;
+    let permission_policy = match &options.permission_policy {
+      // XXX: prompt itself is taken by handle opened with these options, which is the only one that gets to call it
+      Some(Either::B(_)) => nfs::PermissionPolicy::Prompt,
+      Some(Either::A(policy)) => get_permission_policy(Some(policy.as_str()))?,
+      None => get_permission_policy(None)?
+    };
+    let special_files = get_special_files(options.special_files.as_deref())?;
+    Ok(Self{root: options.root, read_only: options.read_only.unwrap_or_default(), permission_policy, special_files, credentials, tls, retry, nconnect: options.nconnect, attr_cache, lookup_cache, data_cache})
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsCredentials {
+  pub uid: Option<u32>,
+  pub gid: Option<u32>,
+  pub aux_gids: Option<Vec<u32>>
+}
+
+impl Default for JsNfsCredentials {
+
+  fn default() -> Self {
+    Self{uid: Default::default(), gid: Default::default(), aux_gids: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsDeserializeOptions {
+  pub url: Option<String>
+}
+
+impl Default for JsNfsDeserializeOptions {
+
+  fn default() -> Self {
+    Self{url: Default::default()}
   }
 }
 
 #[napi(object)]
 pub struct JsNfsRemoveOptions {
-  pub recursive: Option<bool>
+  pub recursive: Option<bool>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
 }
 
 impl Default for JsNfsRemoveOptions {
 
   fn default() -> Self {
-    Self{recursive: Default::default()}
+    Self{recursive: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
   }
 }
 
 #[napi(object)]
 pub struct JsNfsCreateWritableOptions {
-  pub keep_existing_data: Option<bool>
+  pub keep_existing_data: Option<bool>,
+  #[napi(ts_type="'exclusive'")]
+  pub lock: Option<String>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
 }
 
 impl Default for JsNfsCreateWritableOptions {
 
   fn default() -> Self {
-    Self{keep_existing_data: Default::default()}
+    Self{keep_existing_data: Default::default(), lock: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+#[napi(object)]
+pub struct JsNfsLockOptions {
+  pub offset: Option<i64>,
+  pub length: Option<i64>,
+  #[napi(ts_type="'exclusive' | 'shared'")]
+  pub mode: Option<String>,
+  pub wait: Option<bool>,
+  pub timeout_ms: Option<u32>,
+  pub abort_flag: Option<Int32Array>
+}
+
+impl Default for JsNfsLockOptions {
+
+  fn default() -> Self {
+    Self{offset: Default::default(), length: Default::default(), mode: Default::default(), wait: Default::default(), timeout_ms: Default::default(), abort_flag: Default::default()}
+  }
+}
+
+struct JsNfsAbortFlag(Int32Array);
+
+// XXX: flag is only ever stored to by javascript (with Atomics.store) and only ever loaded from here, both atomically
+unsafe impl Sync for JsNfsAbortFlag {}
+
+impl JsNfsAbortFlag {
+
+  fn is_set(&self) -> bool {
+    self.0.first().is_some_and(|flag| unsafe { &*(flag as *const i32 as *const AtomicI32) }.load(Ordering::SeqCst) != 0)
+  }
+}
+
+// Cancellation is checked in between the RPCs an operation is made of, so that it can be aborted through a flag
+// shared with javascript or given up on once its deadline has passed.
+#[derive(Clone, Default)]
+struct JsNfsCancellation {
+  abort_flag: Option<Arc<JsNfsAbortFlag>>,
+  timeout_ms: Option<u32>,
+  deadline: Option<Instant>
+}
+
+impl JsNfsCancellation {
+
+  fn new(timeout_ms: Option<u32>, abort_flag: Option<Int32Array>) -> Self {
+    Self{abort_flag: abort_flag.map(|abort_flag| Arc::new(JsNfsAbortFlag(abort_flag))), timeout_ms, deadline: None}.restart()
+  }
+
+  // Returns cancellation with same abort flag and a deadline counting from now, for streams and iterators that are
+  // made of several operations, each of which gets the full timeout.
+  fn restart(&self) -> Self {
+    let deadline = self.timeout_ms.map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms.into()));
+    Self{abort_flag: self.abort_flag.clone(), timeout_ms: self.timeout_ms, deadline}
+  }
+
+  fn check(&self) -> Result<()> {
+    if self.abort_flag.as_ref().is_some_and(|abort_flag| abort_flag.is_set()) {
+      return Err(Error::new(Status::Cancelled, OPERATION_ABORTED.to_string()));
+    }
+    if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+      return Err(Error::new(Status::GenericFailure, OPERATION_TIMED_OUT.to_string()));
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct JsNfsHandleId {
+  fsid: u64,
+  fileid: u64
+}
+
+impl From<&NFSStat64> for JsNfsHandleId {
+
+  fn from(stat: &NFSStat64) -> Self {
+    Self{fsid: stat.dev, fileid: stat.ino}
+  }
+}
+
+impl From<&NFSDirEntry> for JsNfsHandleId {
+
+  fn from(entry: &NFSDirEntry) -> Self {
+    Self{fsid: entry.dev, fileid: entry.inode}
   }
 }
 
@@ -328,23 +795,211 @@ impl Default for JsNfsCreateWritableOptions {
 #[napi]
 pub struct JsNfsHandle {
   nfs: Option<Arc<RwLock<Box<dyn NFS>>>>,
+  url: String,
+  options: nfs::ConnectOptions,
   path: String,
-  #[napi(readonly, ts_type="'directory' | 'file'")]
+  link: Option<String>,
+  id: Option<JsNfsHandleId>,
+  fh: Arc<RwLock<Option<Vec<u8>>>>,
+  parent_fh: Option<Vec<u8>>,
+  #[napi(readonly, ts_type="'directory' | 'file' | 'blockDevice' | 'characterDevice' | 'fifo' | 'socket'")]
   pub kind: String,
   #[napi(readonly)]
-  pub name: String
+  pub name: String,
+  raw_name: Option<Vec<u8>>, // XXX: only set if name is not valid UTF-8, in which case name is lossy version of it
+  prompt: Option<JsNfsPermissionPrompt>
 }
 
 #[napi]
 impl JsNfsHandle {
 
-  pub fn open(url: String) -> Self {
-    let my_nfs = nfs::connect(url).unwrap_or_else(|e| panic!("error opening connection to NFS server: {:?}", e));
-    Self{nfs: Some(Arc::new(RwLock::new(my_nfs))), path: DIR_ROOT.into(), kind: KIND_DIRECTORY.into(), name: DIR_ROOT.into()}
+  pub fn open(url: String, options: nfs::ConnectOptions) -> Result<Self> {
+    Self::connect(url, options).map_err(|e| Error::new(e.status, format!("error opening connection to NFS server: {}", e.reason)))
+  }
+
+  fn connect(url: String, options: nfs::ConnectOptions) -> Result<Self> {
+    let my_nfs = nfs::connect(url.clone(), &options)?;
+    let id = my_nfs.stat64(DIR_ROOT).ok().map(|nfs_stat| (&nfs_stat).into());
+    Ok(Self{nfs: Some(Arc::new(RwLock::new(my_nfs))), url, options, path: DIR_ROOT.into(), link: None, id, fh: Default::default(), parent_fh: None, kind: KIND_DIRECTORY.into(), name: DIR_ROOT.into(), raw_name: None, prompt: None})
+  }
+
+  fn new_child(&self, path: String, id: Option<JsNfsHandleId>, kind: String, name: String) -> Self {
+    Self{nfs: self.nfs.clone(), url: self.url.clone(), options: self.options.clone(), path, link: None, id, fh: Default::default(), parent_fh: self.fh.read().unwrap().clone(), kind, name, raw_name: None, prompt: self.prompt.clone()}
+  }
+
+  fn new_link_child(&self, link: String, target: String, id: Option<JsNfsHandleId>, kind: String, name: String) -> Self {
+    // XXX: file handle lookups by name in parent directory would yield the symlink itself, so target is only ever reached by path
+    Self{nfs: self.nfs.clone(), url: self.url.clone(), options: self.options.clone(), path: target, link: Some(link), id, fh: Default::default(), parent_fh: None, kind, name, raw_name: None, prompt: self.prompt.clone()}
+  }
+
+  // XXX: entry whose name is not valid UTF-8 is listed so that nameBytes tells what it is, but no path reaches it, since
+  // paths are only ever strings, and its lossy name may well be that of another entry
+  fn check_reachable(&self) -> Result<()> {
+    if self.raw_name.is_some() {
+      return Err(Error::new(Status::GenericFailure, format!("Entry {:?} has name that is not valid UTF-8, which is not supported", self.name)));
+    }
+    Ok(())
+  }
+
+  fn has_name(&self, name: &str) -> bool {
+    self.raw_name.is_none() && self.name == name
+  }
+
+  fn nfs_guard(&self) -> Result<RwLockWriteGuard<'_, Box<dyn NFS>>> {
+    self.check_reachable()?;
+    let mut my_nfs = self.nfs.as_ref().unwrap().write().unwrap();
+    // XXX: connection is shared with handles that may send other credentials, so switch to ours while holding the lock
+    my_nfs.set_credentials(&self.options.credentials)?;
+    Ok(my_nfs)
+  }
+
+  fn nfs_fh(&self, my_nfs: &RwLockWriteGuard<Box<dyn NFS>>) -> Option<Vec<u8>> {
+    if let Some(fh) = self.fh.read().unwrap().as_ref() {
+      return Some(fh.clone());
+    }
+    // XXX: a single lookup in the parent directory is cheaper than resolving every component of the path
+    let fh = self.parent_fh.as_ref()
+      .and_then(|parent_fh| my_nfs.lookup_in(parent_fh, self.name.as_str()).ok())
+      .or_else(|| my_nfs.lookup_fh(self.path.as_str()).ok());
+    fh.inspect(|fh| { let _ = self.fh.write().unwrap().replace(fh.clone()); })
+  }
+
+  fn forget_fh(&self) {
+    let _ = self.fh.write().unwrap().take();
+  }
+
+  fn nfs_stat(&self, my_nfs: &RwLockWriteGuard<Box<dyn NFS>>) -> Result<NFSStat64> {
+    if let Some(fh) = self.nfs_fh(my_nfs) {
+      match my_nfs.getattr_fh(&fh) {
+        Ok(nfs_stat) => return Ok(nfs_stat),
+        Err(_) => self.forget_fh(), // XXX: file handle may be stale, so fall back to path
+      }
+    }
+    Ok(my_nfs.stat64(self.path.as_str())?)
+  }
+
+  fn nfs_opendir(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>) -> Result<Box<dyn NFSDirectory>> {
+    if let Some(fh) = self.nfs_fh(my_nfs) {
+      match my_nfs.readdir_fh(&fh) {
+        Ok(dir) => return Ok(dir),
+        Err(_) => self.forget_fh(), // XXX: file handle may be stale, so fall back to path
+      }
+    }
+    Ok(my_nfs.opendir(self.path.as_str())?)
+  }
+
+  fn nfs_open(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, flags: u32) -> Result<Box<dyn NFSFile>> {
+    if is_special_kind(&self.kind) {
+      return Err(not_regular_file(&self.name));
+    }
+    if let Some(fh) = self.nfs_fh(my_nfs) {
+      match my_nfs.open_fh(&fh, flags) {
+        Ok(file) => return Ok(file),
+        Err(_) => self.forget_fh(), // XXX: file handle may be stale, so fall back to path
+      }
+    }
+    Ok(my_nfs.open(self.path.as_str(), flags)?)
+  }
+
+  fn deserialize(serialized: &str, url: Option<String>) -> Result<Self> {
+    let value: serde_json::Value = serde_json::from_str(serialized)
+      .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid serialized handle: {}", e)))?;
+    let get_str = |field: &str| value.get(field).and_then(|v| v.as_str()).map(str::to_string);
+    let url = url.or_else(|| get_str(FIELD_URL))
+      .ok_or(Error::new(Status::InvalidArg, format!("Property {} is required to deserialize handle", FIELD_URL)))?;
+    let kind = get_str(FIELD_KIND).filter(|kind| kind == KIND_DIRECTORY || kind == KIND_FILE || is_special_kind(kind))
+      .ok_or(Error::new(Status::InvalidArg, format!("Property {} must be either {:?}, {:?} or kind of special file", FIELD_KIND, KIND_DIRECTORY, KIND_FILE)))?;
+    let path = get_str(FIELD_PATH).filter(|path| path.starts_with(DIR_ROOT))
+      .ok_or(Error::new(Status::InvalidArg, format!("Property {} is required to deserialize handle", FIELD_PATH)))?;
+    let name = get_str(FIELD_NAME).unwrap_or_default();
+    // XXX: ids are serialized as strings, since JSON.parse() rounds numbers beyond 2^53, which 64-bit ids often are
+    let get_id = |field: &str| value.get(field).and_then(|v| v.as_str().and_then(|v| v.parse().ok()).or_else(|| v.as_u64()));
+    let id = match (get_id(FIELD_FSID), get_id(FIELD_FILEID)) {
+      (Some(fsid), Some(fileid)) => Some(JsNfsHandleId{fsid, fileid}),
+      _ => None
+    };
+    let read_only = value.get(FIELD_READ_ONLY).and_then(|v| v.as_bool()).unwrap_or_default();
+    let permission_policy = get_permission_policy(value.get(FIELD_PERMISSION_POLICY).and_then(|v| v.as_str()))?;
+    let special_files = get_special_files(value.get(FIELD_SPECIAL_FILES).and_then(|v| v.as_str()))?;
+    let get_u32 = |field: &str| value.get(field).and_then(|v| v.as_u64()).and_then(|v| u32::try_from(v).ok());
+    let aux_gids = value.get(FIELD_AUX_GIDS).and_then(|v| v.as_array())
+      .map(|aux_gids| aux_gids.iter().filter_map(|v| v.as_u64()).filter_map(|v| u32::try_from(v).ok()).collect());
+    let credentials = nfs::Credentials{uid: get_u32(FIELD_UID), gid: get_u32(FIELD_GID), aux_gids};
+    let tls = nfs::TlsOptions{xprtsec: get_str(FIELD_XPRTSEC)};
+    let default_retry = nfs::RetryOptions::default();
+    let retry = nfs::RetryOptions{
+      retries: get_u32(FIELD_RETRIES).unwrap_or(default_retry.retries),
+      timeout_ms: get_u32(FIELD_TIMEOUT_MS).or(default_retry.timeout_ms),
+      backoff_ms: get_u32(FIELD_BACKOFF_MS).unwrap_or(default_retry.backoff_ms)
+    };
+    let default_attr_cache = nfs::AttrCacheOptions::default();
+    let attr_cache = nfs::AttrCacheOptions{
+      acregmin: get_u32(FIELD_ACREGMIN).unwrap_or(default_attr_cache.acregmin),
+      acregmax: get_u32(FIELD_ACREGMAX).unwrap_or(default_attr_cache.acregmax),
+      acdirmin: get_u32(FIELD_ACDIRMIN).unwrap_or(default_attr_cache.acdirmin),
+      acdirmax: get_u32(FIELD_ACDIRMAX).unwrap_or(default_attr_cache.acdirmax)
+    };
+    let get_u64 = |field: &str| value.get(field).and_then(|v| v.as_u64());
+    let default_data_cache = nfs::DataCacheOptions::default();
+    let data_cache = nfs::DataCacheOptions{
+      max_bytes: get_u64(FIELD_DATA_CACHE_SIZE).unwrap_or(default_data_cache.max_bytes),
+      spill_dir: get_str(FIELD_DATA_CACHE_DIR),
+      spill_max_bytes: get_u64(FIELD_DATA_CACHE_DIR_SIZE).unwrap_or(default_data_cache.spill_max_bytes)
+    };
+    let lookup_cache = get_lookup_cache(get_str(FIELD_LOOKUPCACHE).as_deref())?;
+    let options = nfs::ConnectOptions{root: get_str(FIELD_ROOT), read_only, permission_policy, special_files, credentials, tls, retry, nconnect: get_u32(FIELD_NCONNECT), attr_cache, lookup_cache, data_cache};
+
+    let root = Self::connect(url, options)?;
+    let (nfs_stat, fh, path, name) = {
+      let mut my_nfs = root.nfs_guard()?;
+      // XXX: prefer reopening by file handle, since it survives renames, and fall back to path if server does not allow it
+      let by_fh = get_str(FIELD_FH)
+        .and_then(|fh| decode_hex(&fh))
+        .and_then(|fh| my_nfs.getattr_fh(&fh).ok().map(|nfs_stat| (nfs_stat, fh)));
+      match by_fh {
+        Some((nfs_stat, fh)) => {
+          let (path, name) = nfs_find_path(&mut my_nfs, path, name, &nfs_stat)?;
+          (nfs_stat, Some(fh), path, name)
+        },
+        None => {
+          let nfs_stat = my_nfs.stat64(path.as_str())?;
+          // XXX: serialized entry may have been removed and another one created under its name since
+          if id.is_some_and(|id| id != (&nfs_stat).into()) {
+            return Err(Error::new(Status::GenericFailure, format!("Entry {:?} not found", name)));
+          }
+          (nfs_stat, None, path, name)
+        },
+      }
+    };
+    Ok(Self{nfs: root.nfs, url: root.url, options: root.options, path, link: None, id: Some((&nfs_stat).into()), fh: Arc::new(RwLock::new(fh)), parent_fh: None, kind, name, raw_name: None, prompt: None})
+  }
+
+  #[napi(getter)]
+  pub fn name_bytes(&self) -> Buffer {
+    self.raw_name.clone().unwrap_or_else(|| self.name.as_bytes().to_vec()).into()
+  }
+
+  #[napi]
+  pub fn with_credentials(&self, credentials: JsNfsCredentials) -> Result<JsNfsHandle> {
+    if self.nfs.is_none() {
+      return Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)));
+    }
+    let mut handle = self.clone();
+    let current = &self.options.credentials;
+    handle.options.credentials = nfs::Credentials{
+      uid: credentials.uid.or(current.uid),
+      gid: credentials.gid.or(current.gid),
+      aux_gids: credentials.aux_gids.or_else(|| current.aux_gids.clone()),
+    };
+    Ok(handle)
   }
 
   fn is_same(&self, other: &JsNfsHandle) -> bool {
-    other.kind == self.kind && other.name == self.name && (other.path.is_empty() || self.path.is_empty() || other.path == self.path)
+    match (self.id, other.id) {
+      (Some(id), Some(other_id)) => other.kind == self.kind && other_id == id,
+      // XXX: handles passed in as plain objects carry no identity, so fall back to comparing kind, name and (non-empty) path
+      _ => other.kind == self.kind && other.name == self.name && other.raw_name == self.raw_name && !other.path.is_empty() && other.path == self.path
+    }
   }
 
   #[napi]
@@ -352,35 +1007,221 @@ impl JsNfsHandle {
     Ok(self.is_same(other))
   }
 
+  #[napi]
+  pub fn get_unique_id(&self) -> Result<String> {
+    match self.id {
+      Some(id) => Ok(format!("{}#{:x}:{:x}", get_export_from_url(&self.url), id.fsid, id.fileid)),
+      None => Err(Error::new(Status::GenericFailure, format!("Entry {:?} has no unique id", self.name)))
+    }
+  }
+
+  #[napi]
+  pub fn serialize(&self) -> Result<String> {
+    if self.nfs.is_none() {
+      return Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)));
+    }
+    let fh = self.nfs_fh(&self.nfs_guard()?);
+    let mut serialized = serde_json::Map::new();
+    serialized.insert(FIELD_URL.into(), self.url.clone().into());
+    if let Some(root) = &self.options.root {
+      serialized.insert(FIELD_ROOT.into(), root.clone().into());
+    }
+    if self.options.read_only {
+      serialized.insert(FIELD_READ_ONLY.into(), true.into());
+    }
+    match self.options.permission_policy {
+      nfs::PermissionPolicy::Never => (),
+      nfs::PermissionPolicy::Chmod => { serialized.insert(FIELD_PERMISSION_POLICY.into(), PERM_POLICY_CHMOD.into()); },
+      nfs::PermissionPolicy::Prompt => { serialized.insert(FIELD_PERMISSION_POLICY.into(), PERM_POLICY_PROMPT.into()); },
+    }
+    match self.options.special_files {
+      nfs::SpecialFiles::File => (),
+      nfs::SpecialFiles::Skip => { serialized.insert(FIELD_SPECIAL_FILES.into(), SPECIAL_FILES_SKIP.into()); },
+      nfs::SpecialFiles::Expose => { serialized.insert(FIELD_SPECIAL_FILES.into(), SPECIAL_FILES_EXPOSE.into()); },
+    }
+    if let Some(uid) = self.options.credentials.uid {
+      serialized.insert(FIELD_UID.into(), uid.into());
+    }
+    if let Some(gid) = self.options.credentials.gid {
+      serialized.insert(FIELD_GID.into(), gid.into());
+    }
+    if let Some(aux_gids) = &self.options.credentials.aux_gids {
+      serialized.insert(FIELD_AUX_GIDS.into(), aux_gids.clone().into());
+    }
+    if let Some(xprtsec) = &self.options.tls.xprtsec {
+      serialized.insert(FIELD_XPRTSEC.into(), xprtsec.clone().into());
+    }
+    let retry = &self.options.retry;
+    serialized.insert(FIELD_RETRIES.into(), retry.retries.into());
+    if let Some(timeout_ms) = retry.timeout_ms {
+      serialized.insert(FIELD_TIMEOUT_MS.into(), timeout_ms.into());
+    }
+    serialized.insert(FIELD_BACKOFF_MS.into(), retry.backoff_ms.into());
+    if let Some(nconnect) = self.options.nconnect {
+      serialized.insert(FIELD_NCONNECT.into(), nconnect.into());
+    }
+    let attr_cache = &self.options.attr_cache;
+    for (field, value) in [(FIELD_ACREGMIN, attr_cache.acregmin), (FIELD_ACREGMAX, attr_cache.acregmax), (FIELD_ACDIRMIN, attr_cache.acdirmin), (FIELD_ACDIRMAX, attr_cache.acdirmax)] {
+      serialized.insert(field.into(), value.into());
+    }
+    match self.options.lookup_cache {
+      nfs::LookupCache::All => (),
+      nfs::LookupCache::Positive => { serialized.insert(FIELD_LOOKUPCACHE.into(), LOOKUP_CACHE_POSITIVE.into()); },
+      nfs::LookupCache::None => { serialized.insert(FIELD_LOOKUPCACHE.into(), LOOKUP_CACHE_NONE.into()); },
+    }
+    let data_cache = &self.options.data_cache;
+    if data_cache.max_bytes > 0 {
+      serialized.insert(FIELD_DATA_CACHE_SIZE.into(), data_cache.max_bytes.into());
+      if let Some(spill_dir) = &data_cache.spill_dir {
+        serialized.insert(FIELD_DATA_CACHE_DIR.into(), spill_dir.clone().into());
+        serialized.insert(FIELD_DATA_CACHE_DIR_SIZE.into(), data_cache.spill_max_bytes.into());
+      }
+    }
+    serialized.insert(FIELD_KIND.into(), self.kind.clone().into());
+    serialized.insert(FIELD_NAME.into(), self.name.clone().into());
+    serialized.insert(FIELD_PATH.into(), self.path.clone().into());
+    if let Some(id) = self.id {
+      serialized.insert(FIELD_FSID.into(), id.fsid.to_string().into());
+      serialized.insert(FIELD_FILEID.into(), id.fileid.to_string().into());
+    }
+    if let Some(fh) = fh {
+      serialized.insert(FIELD_FH.into(), encode_hex(&fh).into());
+    }
+    Ok(serde_json::Value::Object(serialized).to_string())
+  }
+
   #[napi]
   pub async fn query_permission(&self, perm: JsNfsHandlePermissionDescriptor) -> Result<String> {
-    if let Some(nfs) = &self.nfs {
-      let my_nfs = nfs.write().unwrap();
-      let nfs_stat = my_nfs.stat64(self.path.as_str())?;
-      let perm_u64 = perm.to_u64(self.kind.as_str());
-      if nfs_stat.mode & perm_u64 == perm_u64 {
+    if self.options.read_only && perm.mode == PERM_READWRITE {
+      return Ok(PERM_STATE_DENIED.into());
+    }
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      if self.nfs_is_granted(&my_nfs, &perm)? {
         return Ok(PERM_STATE_GRANTED.into());
       }
+      // XXX: prompt tells that requesting permission may grant it, which it can only do when allowed to chmod
+      return Ok(if self.options.permission_policy == nfs::PermissionPolicy::Never { PERM_STATE_DENIED } else { PERM_STATE_PROMPT }.into());
     }
-    if self.nfs.is_none() && ((self.name != "3" && self.name != "quatre") || perm.mode != PERM_READWRITE) {
-      return Ok(PERM_STATE_GRANTED.into());
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn stat(&self, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> Result<JsNfsStat> {
+    let options = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(options.timeout_ms, options.abort_flag);
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      cancellation.check()?;
+      let nfs_stat = self.nfs_stat(&my_nfs)?;
+      return Ok(nfs_stat.into());
     }
-    Ok(PERM_STATE_DENIED.into())
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
   }
 
   #[napi]
-  pub async fn request_permission(&self, perm: JsNfsHandlePermissionDescriptor) -> Result<String> {
+  pub async fn cache_stats(&self) -> Result<JsNfsCacheStats> {
     if let Some(nfs) = &self.nfs {
-      let my_nfs = nfs.write().unwrap();
-      let nfs_stat = my_nfs.stat64(self.path.as_str())?;
-      let perm_u64 = perm.to_u64(self.kind.as_str());
-      if nfs_stat.mode & perm_u64 == perm_u64 {
+      return Ok(nfs.read().unwrap().cache_stats().into());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn get_xattr(&self, name: String) -> Result<Buffer> {
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      let value = my_nfs.getxattr(self.path.as_str(), check_xattr_name(&name)?)?;
+      return Ok(value.into());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn set_xattr(&self, name: String, value: Buffer) -> Result<Undefined> {
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      my_nfs.setxattr(self.path.as_str(), check_xattr_name(&name)?, value.as_ref())?;
+      return Ok(());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn list_xattrs(&self) -> Result<Vec<String>> {
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      return Ok(my_nfs.listxattrs(self.path.as_str())?);
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn remove_xattr(&self, name: String) -> Result<Undefined> {
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      my_nfs.removexattr(self.path.as_str(), check_xattr_name(&name)?)?;
+      return Ok(());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn get_acl(&self) -> Result<Vec<JsNfsAce>> {
+    if self.nfs.is_some() {
+      let my_nfs = self.nfs_guard()?;
+      let acl = my_nfs.getacl(self.path.as_str())?;
+      return Ok(acl.into_iter().map(JsNfsAce::from).collect());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  #[napi]
+  pub async fn set_acl(&self, aces: Vec<JsNfsAce>) -> Result<Undefined> {
+    if self.nfs.is_some() {
+      let acl = aces.into_iter().map(Ace::try_from).collect::<Result<Vec<Ace>>>()?;
+      let my_nfs = self.nfs_guard()?;
+      my_nfs.setacl(self.path.as_str(), &acl)?;
+      return Ok(());
+    }
+    Err(Error::new(Status::GenericFailure, format!("Entry {:?} is not connected", self.name)))
+  }
+
+  fn nfs_is_granted(&self, my_nfs: &RwLockWriteGuard<Box<dyn NFS>>, perm: &JsNfsHandlePermissionDescriptor) -> Result<bool> {
+    // XXX: ask server, since only it knows about ownership, groups, ACLs, root squashing and read-only exports
+    let access = perm.to_access(self.kind.as_str());
+    let granted = my_nfs.access(self.path.as_str(), access)?;
+    Ok(granted & access == access)
+  }
+
+  #[napi]
+  pub async fn request_permission(&self, perm: JsNfsHandlePermissionDescriptor) -> Result<String> {
+    if self.options.read_only && perm.mode == PERM_READWRITE {
+      return Ok(PERM_STATE_DENIED.into());
+    }
+    if self.nfs.is_some() {
+      if self.nfs_is_granted(&self.nfs_guard()?, &perm)? {
         return Ok(PERM_STATE_GRANTED.into());
       }
+      match (&self.options.permission_policy, &self.prompt) {
+        (nfs::PermissionPolicy::Never, _) => return Ok(PERM_STATE_DENIED.into()),
+        (nfs::PermissionPolicy::Prompt, None) => return Ok(PERM_STATE_PROMPT.into()),
+        (nfs::PermissionPolicy::Prompt, Some(prompt)) => {
+          // XXX: connection is not locked while waiting for user, who may take as long as they like to answer
+          let confirmed = prompt.call_async::<Promise<bool>>((self.clone(), JsNfsHandlePermissionDescriptor{mode: perm.mode.clone()})).await?.await?;
+          if !confirmed {
+            return Ok(PERM_STATE_DENIED.into());
+          }
+        },
+        (nfs::PermissionPolicy::Chmod, _) => ()
+      }
+      let my_nfs = self.nfs_guard()?;
+      let nfs_stat = self.nfs_stat(&my_nfs)?;
       let mode = perm.to_mode(self.kind.as_str()).union(Mode::from_bits_truncate((nfs_stat.mode as u16).into()));
-      if !my_nfs.lchmod(self.name.as_str(), mode.bits() as u32).is_ok() {
+      if my_nfs.lchmod(self.path.as_str(), mode.bits() as u32).is_err() || !self.nfs_is_granted(&my_nfs, &perm)? {
         return Ok(PERM_STATE_DENIED.into());
       }
+      return Ok(PERM_STATE_GRANTED.into());
     }
     self.query_permission(perm).await
   }
@@ -399,7 +1240,7 @@ impl FromNapiValue for JsNfsHandle {
         let kind = obj.get::<&str, &str>(FIELD_KIND)?.unwrap_or_default().into();
         let name = obj.get::<&str, &str>(FIELD_NAME)?.unwrap_or_default().into();
         let path = obj.get::<&str, &str>(FIELD_PATH)?.unwrap_or_default().into();
-        Ok(Self{nfs: None, path, kind, name})
+        Ok(Self{nfs: None, url: Default::default(), options: Default::default(), path, link: None, id: None, fh: Default::default(), parent_fh: None, kind, name, raw_name: None, prompt: None})
       },
       |handle| Ok(handle.to_owned())
     )
@@ -421,8 +1262,24 @@ pub struct JsNfsDirectoryHandle {
 impl JsNfsDirectoryHandle {
 
   #[napi(constructor)]
-  pub fn open(url: String) -> Self {
-    JsNfsHandle::open(url).into()
+  pub fn open(env: Env, url: String, #[napi(ts_arg_type="JsNfsConnectOptions")] options: Option<JsNfsConnectOptions>) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let prompt = match &options.permission_policy {
+      Some(Either::B(prompt)) => Some(create_permission_prompt(&env, prompt)?),
+      _ => None
+    };
+    let mut handle = JsNfsHandle::open(url, options.try_into()?)?;
+    handle.prompt = prompt;
+    Ok(handle.into())
+  }
+
+  #[napi(ts_return_type="JsNfsDirectoryHandle | JsNfsFileHandle")]
+  pub fn deserialize(serialized: String, #[napi(ts_arg_type="JsNfsDeserializeOptions")] options: Option<JsNfsDeserializeOptions>) -> Result<Either<JsNfsDirectoryHandle, JsNfsFileHandle>> {
+    let handle = JsNfsHandle::deserialize(serialized.as_str(), options.unwrap_or_default().url)?;
+    match handle.kind.as_str() {
+      KIND_DIRECTORY => Ok(Either::A(handle.into())),
+      _ => Ok(Either::B(handle.into()))
+    }
   }
 
   #[napi]
@@ -435,6 +1292,21 @@ impl JsNfsDirectoryHandle {
     self.handle.is_same_entry(other)
   }
 
+  #[napi]
+  pub fn get_unique_id(&self) -> Result<String> {
+    self.handle.get_unique_id()
+  }
+
+  #[napi]
+  pub fn serialize(&self) -> Result<String> {
+    self.handle.serialize()
+  }
+
+  #[napi]
+  pub fn with_credentials(&self, credentials: JsNfsCredentials) -> Result<JsNfsDirectoryHandle> {
+    Ok(self.handle.with_credentials(credentials)?.into())
+  }
+
   #[napi]
   pub async fn query_permission(&self, perm: JsNfsHandlePermissionDescriptor) -> Result<String> {
     self.handle.query_permission(perm).await
@@ -445,100 +1317,246 @@ impl JsNfsDirectoryHandle {
     self.handle.request_permission(perm).await
   }
 
-  fn nfs_entries(&self) -> Result<Vec<JsNfsHandle>> {
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    self.nfs_entries_guarded(&mut my_nfs)
+  #[napi]
+  pub async fn stat(&self, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> Result<JsNfsStat> {
+    self.handle.stat(options).await
+  }
+
+  #[napi]
+  pub async fn cache_stats(&self) -> Result<JsNfsCacheStats> {
+    self.handle.cache_stats().await
+  }
+
+  #[napi]
+  pub async fn get_xattr(&self, name: String) -> Result<Buffer> {
+    self.handle.get_xattr(name).await
+  }
+
+  #[napi]
+  pub async fn set_xattr(&self, name: String, value: Buffer) -> Result<Undefined> {
+    self.handle.set_xattr(name, value).await
+  }
+
+  #[napi]
+  pub async fn list_xattrs(&self) -> Result<Vec<String>> {
+    self.handle.list_xattrs().await
+  }
+
+  #[napi]
+  pub async fn remove_xattr(&self, name: String) -> Result<Undefined> {
+    self.handle.remove_xattr(name).await
+  }
+
+  #[napi]
+  pub async fn get_acl(&self) -> Result<Vec<JsNfsAce>> {
+    self.handle.get_acl().await
+  }
+
+  #[napi]
+  pub async fn set_acl(&self, aces: Vec<JsNfsAce>) -> Result<Undefined> {
+    self.handle.set_acl(aces).await
+  }
+
+  fn nfs_entries(&self, cancellation: &JsNfsCancellation) -> Result<Vec<JsNfsHandle>> {
+    let mut my_nfs = self.handle.nfs_guard()?;
+    self.nfs_entries_guarded(&mut my_nfs, cancellation)
+  }
+
+  fn nfs_entries_guarded(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, cancellation: &JsNfsCancellation) -> Result<Vec<JsNfsHandle>> {
+    self.nfs_list_guarded(my_nfs, self.handle.options.special_files, cancellation)
   }
 
-  fn nfs_entries_guarded(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>) -> Result<Vec<JsNfsHandle>> {
+  fn nfs_list_guarded(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, special_files: nfs::SpecialFiles, cancellation: &JsNfsCancellation) -> Result<Vec<JsNfsHandle>> {
     let mut entries = Vec::new();
-    let dir = my_nfs.opendir(self.handle.path.as_str())?;
+    cancellation.check()?;
+    let dir = self.handle.nfs_opendir(my_nfs)?;
     for entry in dir {
+      // XXX: directory is read a page at a time while iterating, so this also checks in between those reads
+      cancellation.check()?;
       if let Some(e) = entry.ok() {
+        let id = Some((&e).into());
         let name = e.path;
-        let (kind, path) = match e.d_type {
-          NFSEntryType::Directory => (KIND_DIRECTORY.into(), format_dir_path(&self.handle.path, &name)),
-          _ => (KIND_FILE.into(), format_file_path(&self.handle.path, &name))
+        let (kind, path) = match (&e.d_type, get_entry_kind(&e.d_type, special_files)) {
+          (NFSEntryType::Symlink, _) => {
+            // XXX: symlinks that cannot be resolved or that point outside of root are skipped
+            if let Ok(Some(entry)) = self.nfs_symlink_entry(my_nfs, name, special_files) {
+              entries.push(entry);
+            }
+            continue;
+          },
+          (_, None) => continue,
+          (_, Some(KIND_DIRECTORY)) => (KIND_DIRECTORY.into(), format_dir_path(&self.handle.path, &name)),
+          (_, Some(kind)) => (kind.into(), format_file_path(&self.handle.path, &name))
         };
         if kind != KIND_DIRECTORY || (name != DIR_CURRENT && name != DIR_PARENT) {
-          entries.push(JsNfsHandle{nfs: self.handle.nfs.clone(), path, kind, name});
+          // XXX: entry whose name is not valid UTF-8 is listed under lossy name, which it cannot be reached by
+          entries.push(JsNfsHandle{raw_name: e.raw_path, ..self.handle.new_child(path, id, kind, name)});
         }
       }
     }
     Ok(entries)
   }
 
+  fn nfs_symlink_entry(&self, my_nfs: &RwLockWriteGuard<Box<dyn NFS>>, name: String, special_files: nfs::SpecialFiles) -> Result<Option<JsNfsHandle>> {
+    let link = format_file_path(&self.handle.path, &name);
+    let target = nfs_realpath(my_nfs, &self.handle.path, &name)?;
+    let nfs_stat = my_nfs.stat64(target.as_str())?;
+    let id = Some((&nfs_stat).into());
+    let entry = match get_entry_kind(&NFSEntryType::from_mode(nfs_stat.mode), special_files) {
+      None => None,
+      Some(KIND_DIRECTORY) => {
+        let target = format!("{}/", target.trim_end_matches('/'));
+        Some(self.handle.new_link_child(link, target, id, KIND_DIRECTORY.into(), name))
+      },
+      Some(kind) => Some(self.handle.new_link_child(link, target, id, kind.into(), name))
+    };
+    Ok(entry)
+  }
+
   #[napi(iterator, ts_return_type="AsyncIterableIterator<[string, JsNfsDirectoryHandle | JsNfsFileHandle]>")]
   pub fn entries(&self, env: Env) -> Result<JsNfsDirectoryHandleEntries> {
-    Ok(JsNfsDirectoryHandleEntries{entries: self.nfs_entries()?, env: SendWrapper::new(env), count: 0, _sym: false})
+    Ok(JsNfsDirectoryHandleEntries{entries: self.nfs_entries(&Default::default())?, env: SendWrapper::new(env), count: 0, _sym: false})
   }
 
   #[napi(iterator, ts_return_type="AsyncIterableIterator<string>")]
   pub fn keys(&self) -> Result<JsNfsDirectoryHandleKeys> {
-    Ok(JsNfsDirectoryHandleKeys{entries: self.nfs_entries()?, count: 0, _sym: false})
+    Ok(JsNfsDirectoryHandleKeys{entries: self.nfs_entries(&Default::default())?, count: 0, _sym: false})
   }
 
   #[napi(iterator, ts_return_type="AsyncIterableIterator<JsNfsDirectoryHandle | JsNfsFileHandle>")]
   pub fn values(&self) -> Result<JsNfsDirectoryHandleValues> {
-    Ok(JsNfsDirectoryHandleValues{entries: self.nfs_entries()?, count: 0, _sym: false})
+    Ok(JsNfsDirectoryHandleValues{entries: self.nfs_entries(&Default::default())?, count: 0, _sym: false})
   }
 
   #[napi]
   pub async fn get_directory_handle(&self, name: String, #[napi(ts_arg_type="JsNfsGetDirectoryOptions")] options: Option<JsNfsGetDirectoryOptions>) -> Result<JsNfsDirectoryHandle> {
-    for entry in self.nfs_entries()? {
-      if entry.name == name {
+    let JsNfsGetDirectoryOptions{create, recursive, mode, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    let create = create.unwrap_or_default();
+    let mode = check_mode(mode.unwrap_or(DEFAULT_DIR_MODE))?;
+    if !recursive.unwrap_or_default() {
+      check_entry_name(&name)?;
+      return self.nfs_get_directory_handle(name, create, mode, &cancellation);
+    }
+    // XXX: like mkdir -p, tolerate leading, trailing and repeated slashes
+    let names: Vec<String> = name.split('/').filter(|name| !name.is_empty()).map(str::to_string).collect();
+    if names.is_empty() {
+      return Err(Error::new(Status::InvalidArg, format!("Name {:?} is not allowed", name)));
+    }
+    for name in &names {
+      check_entry_name(name)?;
+    }
+    let mut dir = JsNfsDirectoryHandle::from(self.handle.clone());
+    for name in names {
+      dir = dir.nfs_get_directory_handle(name, create, mode, &cancellation)?;
+    }
+    Ok(dir)
+  }
+
+  fn nfs_get_directory_handle(&self, name: String, create: bool, mode: u32, cancellation: &JsNfsCancellation) -> Result<JsNfsDirectoryHandle> {
+    for entry in self.nfs_entries(cancellation)? {
+      if entry.has_name(&name) {
         if entry.kind != KIND_DIRECTORY {
           return Err(Error::new(Status::GenericFailure, "The path supplied exists, but was not an entry of requested type.".to_string()));
         }
         return Ok(entry.into());
       }
     }
-    if !options.unwrap_or_default().create.unwrap_or_default() {
+    if !create {
       return Err(Error::new(Status::GenericFailure, format!("Directory {:?} not found", name)));
     }
     let path = format_dir_path(&self.handle.path, &name);
-    let nfs = &self.handle.nfs;
-    let my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let _ = my_nfs.mkdir(path.trim_end_matches('/'), 0o775)?;
-    Ok(JsNfsHandle{nfs: self.handle.nfs.clone(), path, kind: KIND_DIRECTORY.into(), name}.into())
+    let my_nfs = self.handle.nfs_guard()?;
+    cancellation.check()?;
+    let _ = my_nfs.mkdir(path.trim_end_matches('/'), mode)?;
+    let id = Some((&my_nfs.stat64(path.trim_end_matches('/'))?).into());
+    Ok(self.handle.new_child(path, id, KIND_DIRECTORY.into(), name).into())
   }
 
   #[napi]
   pub async fn get_file_handle(&self, name: String, #[napi(ts_arg_type="JsNfsGetFileOptions")] options: Option<JsNfsGetFileOptions>) -> Result<JsNfsFileHandle> {
-    for entry in self.nfs_entries()? {
-      if entry.name == name {
+    check_entry_name(&name)?;
+    let JsNfsGetFileOptions{create, exclusive, mode, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    let mode = check_mode(mode.unwrap_or(DEFAULT_FILE_MODE))?;
+    let exclusive = create.unwrap_or_default() && exclusive.unwrap_or_default();
+    let already_exists = || Error::new(Status::GenericFailure, format!("File {:?} already exists", name));
+    for entry in self.nfs_entries(&cancellation)? {
+      if entry.has_name(&name) {
+        if exclusive {
+          return Err(already_exists());
+        }
         if entry.kind != KIND_FILE {
           return Err(Error::new(Status::GenericFailure, "The path supplied exists, but was not an entry of requested type.".to_string()));
         }
         return Ok(entry.into());
       }
     }
-    if !options.unwrap_or_default().create.unwrap_or_default() {
+    if !create.unwrap_or_default() {
       return Err(Error::new(Status::GenericFailure, format!("File {:?} not found", name)));
     }
     let path = format_file_path(&self.handle.path, &name);
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let _ = my_nfs.create(path.as_str(), (OFlag::O_WRONLY | OFlag::O_SYNC).bits() as u32, (Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH | Mode::S_IWOTH).bits() as u32)?; // XXX: change mode value to 0o664?
-    Ok(JsNfsHandle{nfs: self.handle.nfs.clone(), path, kind: KIND_FILE.into(), name}.into())
+    let mut my_nfs = self.handle.nfs_guard()?;
+    cancellation.check()?;
+    // XXX: name may have been created by someone else since entries were listed, which only an exclusive create notices
+    let how = match exclusive {
+      false => CreateMode::Unchecked,
+      true if my_nfs.supports_exclusive_create() => CreateMode::exclusive(),
+      true => CreateMode::Guarded,
+    };
+    let nfs_file = my_nfs.create(path.as_str(), (OFlag::O_WRONLY | OFlag::O_SYNC).bits() as u32, mode, how)
+      .map_err(|e| if e.kind() == std::io::ErrorKind::AlreadyExists { already_exists() } else { e.into() })?;
+    let id = Some((&nfs_file.fstat64()?).into());
+    Ok(self.handle.new_child(path, id, KIND_FILE.into(), name).into())
+  }
+
+  #[napi]
+  pub async fn mknod(&self, name: String, #[napi(ts_arg_type="'blockDevice' | 'characterDevice' | 'fifo' | 'socket'")] kind: String, #[napi(ts_arg_type="JsNfsMknodOptions")] options: Option<JsNfsMknodOptions>) -> Result<()> {
+    let JsNfsMknodOptions{mode, major, minor, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    let fmt = get_special_kind_mode(&kind)?;
+    let rdev = nfs::makerdev(major.unwrap_or_default(), minor.unwrap_or_default());
+    self.nfs_make_special(name, mode, &cancellation, |my_nfs, path, mode| my_nfs.mknod(path, fmt | mode, rdev))
   }
 
-  fn nfs_remove(&self, entry: &JsNfsHandle, recursive: bool) -> Result<()> {
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    self.nfs_remove_guarded(&mut my_nfs, entry, recursive)
+  #[napi]
+  pub async fn mkfifo(&self, name: String, #[napi(ts_arg_type="JsNfsMknodOptions")] options: Option<JsNfsMknodOptions>) -> Result<()> {
+    let JsNfsMknodOptions{mode, timeout_ms, abort_flag, ..} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    self.nfs_make_special(name, mode, &cancellation, |my_nfs, path, mode| my_nfs.mkfifo(path, mode))
   }
 
-  fn nfs_remove_guarded(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, entry: &JsNfsHandle, recursive: bool) -> Result<()> {
-    if entry.kind == KIND_DIRECTORY {
-      let subentries = JsNfsDirectoryHandle::from(entry.to_owned()).nfs_entries_guarded(my_nfs)?;
+  fn nfs_make_special<F>(&self, name: String, mode: Option<u32>, cancellation: &JsNfsCancellation, make: F) -> Result<()>
+  where F: FnOnce(&RwLockWriteGuard<Box<dyn NFS>>, &str, u32) -> std::io::Result<()> {
+    check_entry_name(&name)?;
+    let mode = check_mode(mode.unwrap_or(DEFAULT_FILE_MODE))?;
+    let path = format_file_path(&self.handle.path, &name);
+    let my_nfs = self.handle.nfs_guard()?;
+    cancellation.check()?;
+    make(&my_nfs, path.as_str(), mode)
+      .map_err(|e| if e.kind() == std::io::ErrorKind::AlreadyExists { Error::new(Status::GenericFailure, format!("File {:?} already exists", name)) } else { e.into() })
+  }
+
+  fn nfs_remove(&self, entry: &JsNfsHandle, recursive: bool, cancellation: &JsNfsCancellation) -> Result<()> {
+    let mut my_nfs = self.handle.nfs_guard()?;
+    self.nfs_remove_guarded(&mut my_nfs, entry, recursive, cancellation)
+  }
+
+  fn nfs_remove_guarded(&self, my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, entry: &JsNfsHandle, recursive: bool, cancellation: &JsNfsCancellation) -> Result<()> {
+    entry.check_reachable()?;
+    cancellation.check()?;
+    if let Some(link) = &entry.link {
+      // XXX: only ever remove the symlink itself, never what it points to
+      my_nfs.unlink(link.as_str())?;
+    } else if entry.kind == KIND_DIRECTORY {
+      // XXX: list special files even if they are otherwise skipped, since directory cannot be removed while they are there
+      let subentries = JsNfsDirectoryHandle::from(entry.to_owned()).nfs_list_guarded(my_nfs, nfs::SpecialFiles::Expose, cancellation)?;
       if !recursive && subentries.len() > 0 {
         return Err(Error::new(Status::GenericFailure, format!("Directory {:?} is not empty", entry.name)));
       }
 
       for subentry in subentries {
-        let _ = self.nfs_remove_guarded(my_nfs, &subentry, recursive)?;
+        let _ = self.nfs_remove_guarded(my_nfs, &subentry, recursive, cancellation)?;
       }
 
       my_nfs.rmdir(entry.path.trim_end_matches('/'))?;
@@ -551,34 +1569,34 @@ impl JsNfsDirectoryHandle {
 
   #[napi]
   pub async fn remove_entry(&self, name: String, #[napi(ts_arg_type="JsNfsRemoveOptions")] options: Option<JsNfsRemoveOptions>) -> Result<()> {
-    for entry in self.nfs_entries()? {
-      if entry.name == name {
-        return self.nfs_remove(&entry, options.unwrap_or_default().recursive.unwrap_or_default());
+    check_entry_name(&name)?;
+    let JsNfsRemoveOptions{recursive, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    for entry in self.nfs_entries(&cancellation)? {
+      if entry.has_name(&name) {
+        return self.nfs_remove(&entry, recursive.unwrap_or_default(), &cancellation);
       }
     }
     Err(Error::new(Status::GenericFailure, format!("Entry {:?} not found", name)))
   }
 
-  fn nfs_resolve(&self, subentries: Vec<JsNfsHandle>, possible_descendant: &JsNfsHandle) -> Result<Vec<String>> {
-    for subentry in subentries {
-      if subentry.is_same(possible_descendant) {
-        return Ok(subentry.path.trim_matches('/').split('/').map(str::to_string).collect());
-      }
-
-      if subentry.kind == KIND_DIRECTORY {
-        let subdir = JsNfsDirectoryHandle::from(subentry);
-        let res = subdir.nfs_resolve(subdir.nfs_entries()?, possible_descendant);
-        if res.is_ok() {
-          return res;
-        }
+  fn nfs_resolve(&self, possible_descendant: &JsNfsHandle, cancellation: &JsNfsCancellation) -> Result<Vec<String>> {
+    if let Some(relative) = get_relative_path(&self.handle.path, &possible_descendant.path) {
+      let my_nfs = self.handle.nfs_guard()?;
+      cancellation.check()?;
+      let nfs_stat = my_nfs.stat64(possible_descendant.path.as_str())?;
+      if possible_descendant.id.is_none_or(|id| id == (&nfs_stat).into()) {
+        return Ok(relative);
       }
     }
     Err(Error::new(Status::GenericFailure, format!("Possible descendant {} {:?} not found", possible_descendant.kind, possible_descendant.name)))
   }
 
   #[napi(ts_return_type="Promise<Array<string> | null>")]
-  pub fn resolve(&self, possible_descendant: JsNfsHandle) -> AsyncTask<JsNfsDirectoryHandleResolve> {
-    AsyncTask::new(JsNfsDirectoryHandleResolve{handle: JsNfsDirectoryHandle{handle: self.handle.clone(), kind: self.kind.clone(), name: self.name.clone(), _sym: false}, possible_descendant})
+  pub fn resolve(&self, possible_descendant: JsNfsHandle, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> AsyncTask<JsNfsDirectoryHandleResolve> {
+    let options = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(options.timeout_ms, options.abort_flag);
+    AsyncTask::new(JsNfsDirectoryHandleResolve{handle: JsNfsDirectoryHandle{handle: self.handle.clone(), kind: self.kind.clone(), name: self.name.clone(), _sym: false}, possible_descendant, cancellation})
   }
 }
 
@@ -591,7 +1609,8 @@ impl From<JsNfsHandle> for JsNfsDirectoryHandle {
 
 pub struct JsNfsDirectoryHandleResolve {
   handle: JsNfsDirectoryHandle,
-  possible_descendant: JsNfsHandle
+  possible_descendant: JsNfsHandle,
+  cancellation: JsNfsCancellation
 }
 
 #[napi]
@@ -602,9 +1621,10 @@ impl Task for JsNfsDirectoryHandleResolve {
   type JsValue = Either<Vec<String>, Null>;
 
   fn compute(&mut self) -> Result<Self::Output> {
-    self.handle.nfs_resolve(self.handle.nfs_entries()?, &self.possible_descendant)
+    self.handle.nfs_resolve(&self.possible_descendant, &self.cancellation)
       .map_or_else(
-        |_| Ok(Either::B(Null)),
+        // XXX: any failure to resolve means "not a descendant", unless operation was aborted or timed out meanwhile
+        |_| self.cancellation.check().map(|_| Either::B(Null)),
         |resolved| Ok(Either::A(resolved))
       )
   }
@@ -636,6 +1656,21 @@ impl JsNfsFileHandle {
     self.handle.is_same_entry(other)
   }
 
+  #[napi]
+  pub fn get_unique_id(&self) -> Result<String> {
+    self.handle.get_unique_id()
+  }
+
+  #[napi]
+  pub fn serialize(&self) -> Result<String> {
+    self.handle.serialize()
+  }
+
+  #[napi]
+  pub fn with_credentials(&self, credentials: JsNfsCredentials) -> Result<JsNfsFileHandle> {
+    Ok(self.handle.with_credentials(credentials)?.into())
+  }
+
   #[napi]
   pub async fn query_permission(&self, perm: JsNfsHandlePermissionDescriptor) -> Result<String> {
     self.handle.query_permission(perm).await
@@ -646,22 +1681,202 @@ impl JsNfsFileHandle {
     self.handle.request_permission(perm).await
   }
 
+  #[napi]
+  pub async fn stat(&self, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> Result<JsNfsStat> {
+    self.handle.stat(options).await
+  }
+
+  #[napi]
+  pub async fn cache_stats(&self) -> Result<JsNfsCacheStats> {
+    self.handle.cache_stats().await
+  }
+
+  #[napi]
+  pub async fn get_xattr(&self, name: String) -> Result<Buffer> {
+    self.handle.get_xattr(name).await
+  }
+
+  #[napi]
+  pub async fn set_xattr(&self, name: String, value: Buffer) -> Result<Undefined> {
+    self.handle.set_xattr(name, value).await
+  }
+
+  #[napi]
+  pub async fn list_xattrs(&self) -> Result<Vec<String>> {
+    self.handle.list_xattrs().await
+  }
+
+  #[napi]
+  pub async fn remove_xattr(&self, name: String) -> Result<Undefined> {
+    self.handle.remove_xattr(name).await
+  }
+
+  #[napi]
+  pub async fn get_acl(&self) -> Result<Vec<JsNfsAce>> {
+    self.handle.get_acl().await
+  }
+
+  #[napi]
+  pub async fn set_acl(&self, aces: Vec<JsNfsAce>) -> Result<Undefined> {
+    self.handle.set_acl(aces).await
+  }
+
   #[napi(ts_return_type="Promise<File>")]
-  pub async fn get_file(&self) -> Result<JsNfsFile> {
+  pub async fn get_file(&self, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> Result<JsNfsFile> {
+    let JsNfsOperationOptions{timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
     let path = Path::new(self.handle.path.as_str());
     let type_ = mime_guess::from_path(path).first_raw().unwrap_or(MIME_TYPE_UNKNOWN).into();
-    let nfs = &self.handle.nfs;
-    let my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let nfs_stat = my_nfs.stat64(self.handle.path.as_str())?;
-    let last_modified = (nfs_stat.mtime as i64).checked_mul(1000).unwrap_or(nfs_stat.mtime as i64);
-    Ok(JsNfsFile{handle: self.handle.clone(), size: nfs_stat.size as i64, type_, last_modified, name: self.name.clone()})
+    let my_nfs = self.handle.nfs_guard()?;
+    cancellation.check()?;
+    let nfs_stat = self.handle.nfs_stat(&my_nfs)?;
+    // XXX: reading anything but a regular file, such as a FIFO, may block forever or fail in odd ways, so do not even try
+    if NFSEntryType::from_mode(nfs_stat.mode).is_special() {
+      return Err(not_regular_file(&self.name));
+    }
+    let last_modified = (nfs_stat.mtime as i64).checked_mul(1000)
+      .and_then(|ms| ms.checked_add((nfs_stat.mtime_nsec / 1000000) as i64))
+      .unwrap_or(nfs_stat.mtime as i64);
+    Ok(JsNfsFile{handle: self.handle.clone(), timeout_ms, size: nfs_stat.size as i64, type_, last_modified, name: self.name.clone()})
   }
 
   #[napi]
   pub async fn create_writable(&self, #[napi(ts_arg_type="JsNfsCreateWritableOptions")] options: Option<JsNfsCreateWritableOptions>) -> Result<JsNfsWritableFileStream> {
-    let keep_existing_data = options.unwrap_or_default().keep_existing_data;
+    if self.handle.options.read_only {
+      return Err(Error::new(Status::GenericFailure, nfs::NO_MODIFICATION_ALLOWED.to_string()));
+    }
+    if is_special_kind(&self.handle.kind) {
+      return Err(not_regular_file(&self.name));
+    }
+    let JsNfsCreateWritableOptions{keep_existing_data, lock, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    cancellation.check()?;
+    let lock = match lock.as_deref() {
+      None => None,
+      Some(LOCK_MODE_EXCLUSIVE) => Some(JsNfsHeldLock::acquire_off_runtime(self.handle.clone(), 0, 0, true, true, cancellation).await?),
+      Some(lock) => return Err(Error::new(Status::InvalidArg, format!("Lock mode {:?} is not allowed", lock))),
+    };
     let position = (!keep_existing_data.unwrap_or_default()).then(|| 0);
-    Ok(JsNfsWritableFileStream{handle: self.handle.clone(), keep_existing_data, position, locked: false})
+    Ok(JsNfsWritableFileStream{handle: self.handle.clone(), keep_existing_data, position, lock: Mutex::new(lock), locked: false})
+  }
+
+  #[napi]
+  pub async fn lock(&self, #[napi(ts_arg_type="JsNfsLockOptions")] options: Option<JsNfsLockOptions>) -> Result<JsNfsFileLock> {
+    let JsNfsLockOptions{offset, length, mode, wait, timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = JsNfsCancellation::new(timeout_ms, abort_flag);
+    let (offset, length) = (offset.unwrap_or_default(), length.unwrap_or_default());
+    if offset < 0 || length < 0 {
+      return Err(Error::new(Status::InvalidArg, format!("Range of offset {} and length {} is not allowed", offset, length)));
+    }
+    let mode = mode.unwrap_or_else(|| LOCK_MODE_EXCLUSIVE.into());
+    let exclusive = match mode.as_str() {
+      LOCK_MODE_EXCLUSIVE => true,
+      LOCK_MODE_SHARED => false,
+      _ => return Err(Error::new(Status::InvalidArg, format!("Lock mode {:?} is not allowed", mode))),
+    };
+    let held = JsNfsHeldLock::acquire_off_runtime(self.handle.clone(), offset as u64, length as u64, exclusive, wait.unwrap_or(true), cancellation).await?;
+    Ok(JsNfsFileLock{held: Mutex::new(Some(held)), offset, length, mode})
+  }
+}
+
+struct JsNfsOpenFile(Box<dyn NFSFile>);
+
+// XXX: open file is only ever used while holding lock of connection it was opened on
+unsafe impl Send for JsNfsOpenFile {}
+
+// JsNfsHeldLock keeps file open for as long as byte-range lock taken on it is held, since closing it would drop the lock.
+struct JsNfsHeldLock {
+  handle: JsNfsHandle,
+  file: Option<JsNfsOpenFile>,
+  offset: u64,
+  length: u64,
+  held: bool
+}
+
+impl JsNfsHeldLock {
+
+  fn acquire(handle: &JsNfsHandle, offset: u64, length: u64, exclusive: bool, wait: bool, cancellation: &JsNfsCancellation) -> Result<Self> {
+    let flags = if exclusive { OFlag::O_RDWR } else { OFlag::O_RDONLY };
+    let mut lock = {
+      let mut my_nfs = handle.nfs_guard()?;
+      cancellation.check()?;
+      let file = handle.nfs_open(&mut my_nfs, flags.bits() as u32)?;
+      Self{handle: handle.clone(), file: Some(JsNfsOpenFile(file)), offset, length, held: false}
+    };
+    let mut poll_ms = LOCK_POLL_MIN_MS;
+    loop {
+      let res = {
+        let _my_nfs = lock.handle.nfs_guard()?;
+        lock.file.as_ref().unwrap().0.lock(offset, length, exclusive, false)
+      };
+      match res {
+        Ok(_) => {
+          lock.held = true;
+          return Ok(lock);
+        },
+        // XXX: poll rather than have server block, since connection is shared and would be held up for everyone meanwhile
+        Err(e) if wait && e.kind() == std::io::ErrorKind::WouldBlock => {
+          std::thread::sleep(Duration::from_millis(poll_ms));
+          poll_ms = (poll_ms * 2).min(LOCK_POLL_MAX_MS);
+          cancellation.check()?;
+        },
+        Err(e) => return Err(e.into()),
+      }
+    }
+  }
+
+  // XXX: waiting for lock must not tie up a worker of async runtime, which may be the very one needed to release it
+  async fn acquire_off_runtime(handle: JsNfsHandle, offset: u64, length: u64, exclusive: bool, wait: bool, cancellation: JsNfsCancellation) -> Result<Self> {
+    napi::tokio::task::spawn_blocking(move || Self::acquire(&handle, offset, length, exclusive, wait, &cancellation)).await
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?
+  }
+
+  fn release(&mut self) -> Result<()> {
+    let Some(file) = self.file.take() else {
+      return Ok(());
+    };
+    let my_nfs = self.handle.nfs_guard()?;
+    let res = if self.held { file.0.unlock(self.offset, self.length) } else { Ok(()) };
+    self.held = false;
+    drop(file); // XXX: close file while still holding connection lock
+    drop(my_nfs);
+    Ok(res?)
+  }
+}
+
+impl Drop for JsNfsHeldLock {
+
+  fn drop(&mut self) {
+    if self.file.is_some() {
+      // XXX: lock dropped without release is garbage collected, which must not wait for connection, so release it aside
+      let mut held = Self{handle: self.handle.clone(), file: self.file.take(), offset: self.offset, length: self.length, held: self.held};
+      self.held = false;
+      let _ = std::thread::spawn(move || held.release());
+    }
+  }
+}
+
+#[napi]
+pub struct JsNfsFileLock {
+  held: Mutex<Option<JsNfsHeldLock>>,
+  #[napi(readonly)]
+  pub offset: i64,
+  #[napi(readonly)]
+  pub length: i64,
+  #[napi(readonly, ts_type="'exclusive' | 'shared'")]
+  pub mode: String
+}
+
+#[napi]
+impl JsNfsFileLock {
+
+  #[napi]
+  pub async fn release(&self) -> Result<Undefined> {
+    let held = self.held.lock().unwrap().take();
+    if let Some(mut held) = held {
+      held.release()?;
+    }
+    Ok(())
   }
 }
 
@@ -675,6 +1890,7 @@ impl From<JsNfsHandle> for JsNfsFileHandle {
 #[napi]
 pub struct JsNfsFile {
   handle: JsNfsHandle,
+  timeout_ms: Option<u32>, // XXX: default for reads, each of which gets a deadline of its own
   #[napi(readonly)]
   pub size: i64,
   #[napi(readonly)]
@@ -689,8 +1905,14 @@ pub struct JsNfsFile {
 impl JsNfsFile {
 
   #[napi(ts_return_type="Promise<ArrayBuffer>")]
-  pub fn array_buffer(&self) -> AsyncTask<JsNfsFileArrayBuffer> {
-    AsyncTask::new(JsNfsFileArrayBuffer(JsNfsFile{handle: self.handle.clone(), size: self.size, type_: self.type_.clone(), last_modified: self.last_modified, name: self.name.clone()}))
+  pub fn array_buffer(&self, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> AsyncTask<JsNfsFileArrayBuffer> {
+    let JsNfsOperationOptions{timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = self.nfs_cancellation(timeout_ms, abort_flag);
+    AsyncTask::new(JsNfsFileArrayBuffer(JsNfsFile{handle: self.handle.clone(), timeout_ms: self.timeout_ms, size: self.size, type_: self.type_.clone(), last_modified: self.last_modified, name: self.name.clone()}, cancellation))
+  }
+
+  fn nfs_cancellation(&self, timeout_ms: Option<u32>, abort_flag: Option<Int32Array>) -> JsNfsCancellation {
+    JsNfsCancellation::new(timeout_ms.or(self.timeout_ms), abort_flag)
   }
 
   fn get_index_from_optional(&self, pos: Option<i64>, max: i64, def: i64) -> usize {
@@ -708,7 +1930,7 @@ impl JsNfsFile {
   }
 
   pub fn nfs_slice(&self, start: Option<i64>, end: Option<i64>) -> Result<Vec<u8>> {
-    let content = self.nfs_bytes()?;
+    let content = self.nfs_bytes(&self.nfs_cancellation(None, None))?;
     let len = content.len() as i64;
     let start = self.get_index_from_optional(start, len, 0);
     let end = self.get_index_from_optional(end, len, len);
@@ -729,31 +1951,192 @@ impl JsNfsFile {
   }
 
   #[napi(ts_return_type="ReadableStream<Uint8Array>")]
-  pub fn stream(&self, env: Env) -> Result<Object> {
+  pub fn stream(&self, env: Env, #[napi(ts_arg_type="JsNfsOperationOptions")] options: Option<JsNfsOperationOptions>) -> Result<Object> {
+    let JsNfsOperationOptions{timeout_ms, abort_flag} = options.unwrap_or_default();
+    let cancellation = self.nfs_cancellation(timeout_ms, abort_flag);
     let global = env.get_global()?;
     let constructor = global.get_named_property::<JsFunction>(JS_TYPE_READABLE_STREAM)?;
-    let arg = JsNfsReadableStreamSource{handle: self.handle.clone(), offset: 0, type_: READABLE_STREAM_SOURCE_TYPE_BYTES.into()}.into_instance(env)?;
+    let arg = JsNfsReadableStreamSource{handle: self.handle.clone(), cancellation, offset: 0, type_: READABLE_STREAM_SOURCE_TYPE_BYTES.into()}.into_instance(env)?;
     let stream = constructor.new_instance(&[arg])?;
     Ok(stream)
   }
 
-  fn nfs_bytes(&self) -> Result<Vec<u8>> {
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let nfs_file = my_nfs.open(self.handle.path.as_str(), OFlag::O_RDONLY.bits() as u32)?;
+  fn nfs_bytes(&self, cancellation: &JsNfsCancellation) -> Result<Vec<u8>> {
+    let mut my_nfs = self.handle.nfs_guard()?;
+    cancellation.check()?;
+    let nfs_file = self.handle.nfs_open(&mut my_nfs, OFlag::O_RDONLY.bits() as u32)?;
     let nfs_stat = nfs_file.fstat64()?;
-    let buffer = &mut vec![0u8; nfs_stat.size as usize];
-    let _ = nfs_file.pread_into(nfs_stat.size as u32, 0, buffer)?;
-    Ok(buffer.to_vec())
+    let mut buffer = vec![0u8; nfs_stat.size as usize];
+    let check = || cancellation.check().map_err(|e| std::io::Error::other(e.reason));
+    let res = if is_sparse(&nfs_stat) {
+      nfs_pread_data_into(nfs_file.as_ref(), 0, &mut buffer, &check)
+    } else {
+      nfs_file.pread_all_into(0, &mut buffer, &check)
+    };
+    // XXX: report cancellation as such rather than as whatever I/O error it was turned into on the way
+    let len = res.map_err(|e| cancellation.check().err().unwrap_or(e.into()))?;
+    buffer.truncate(len as usize); // XXX: file may have been truncated since its size was read
+    Ok(buffer)
+  }
+
+  #[napi]
+  pub async fn text(&self, #[napi(ts_arg_type="JsNfsTextOptions")] options: Option<JsNfsTextOptions>) -> Result<String> {
+    let mut options = options.unwrap_or_default();
+    let cancellation = self.nfs_cancellation(options.timeout_ms, options.abort_flag.take());
+    let mut decoder = JsNfsTextDecoder::new(options)?;
+    decoder.decode(&self.nfs_bytes(&cancellation)?, true)
+  }
+
+  #[napi(ts_return_type="AsyncIterableIterator<string>")]
+  pub fn lines(&self, env: Env, #[napi(ts_arg_type="JsNfsTextOptions")] options: Option<JsNfsTextOptions>) -> Result<Object> {
+    let mut options = options.unwrap_or_default();
+    let cancellation = self.nfs_cancellation(options.timeout_ms, options.abort_flag.take());
+    let decoder = JsNfsTextDecoder::new(options)?;
+    let state = JsNfsTextLinesState{file: None, offset: 0, decoder, partial: String::new(), lines: VecDeque::new(), eof: false};
+    let lines = JsNfsTextLines{handle: self.handle.clone(), cancellation, state: Arc::new(Mutex::new(state))};
+    let mut obj = unsafe { Object::from_napi_value(env.raw(), lines.into_instance(env)?.raw())? };
+    // XXX: napi-derive cannot name a method after a symbol, so make the instance async iterable by hand
+    let symbol = env.get_global()?.get_named_property::<JsFunction>(JS_TYPE_SYMBOL)?.coerce_to_object()?.get_named_property::<JsSymbol>(FIELD_ASYNC_ITERATOR)?;
+    let iterator = env.create_function_from_closure(FIELD_ASYNC_ITERATOR, |ctx| ctx.this::<Object>())?;
+    obj.set_property(symbol, iterator)?;
+    Ok(obj)
+  }
+}
+
+struct JsNfsTextDecoder {
+  decoder: encoding_rs::Decoder,
+  fatal: bool
+}
+
+impl JsNfsTextDecoder {
+
+  fn new(options: JsNfsTextOptions) -> Result<Self> {
+    let label = options.encoding.unwrap_or_else(|| TEXT_ENCODING_DEFAULT.into());
+    let encoding = encoding_rs::Encoding::for_label(label.trim().as_bytes())
+      .filter(|encoding| [encoding_rs::UTF_8, encoding_rs::UTF_16LE, encoding_rs::UTF_16BE, encoding_rs::WINDOWS_1252].contains(encoding))
+      .ok_or_else(|| Error::new(Status::InvalidArg, format!("Encoding {:?} is not supported", label)))?;
+    // XXX: like TextDecoder, only a BOM of the given encoding is stripped, it never switches to another encoding
+    let decoder = if options.ignore_bom.unwrap_or_default() {
+      encoding.new_decoder_without_bom_handling()
+    } else {
+      encoding.new_decoder_with_bom_removal()
+    };
+    Ok(Self{decoder, fatal: options.fatal.unwrap_or_default()})
+  }
+
+  fn decode(&mut self, bytes: &[u8], last: bool) -> Result<String> {
+    let too_long = || Error::new(Status::GenericFailure, "Encoded data too long to decode".to_string());
+    if self.fatal {
+      let mut res = String::with_capacity(self.decoder.max_utf8_buffer_length_without_replacement(bytes.len()).ok_or_else(too_long)?);
+      match self.decoder.decode_to_string_without_replacement(bytes, &mut res, last) {
+        (encoding_rs::DecoderResult::InputEmpty, _) => Ok(res),
+        _ => Err(Error::new(Status::InvalidArg, format!("The encoded data was not valid for encoding {}", self.decoder.encoding().name().to_lowercase()))),
+      }
+    } else {
+      let mut res = String::with_capacity(self.decoder.max_utf8_buffer_length(bytes.len()).ok_or_else(too_long)?);
+      let _ = self.decoder.decode_to_string(bytes, &mut res, last);
+      Ok(res)
+    }
+  }
+}
+
+struct JsNfsTextLinesFile {
+  file: Box<dyn NFSFile>,
+  size: u64
+}
+
+// XXX: file is only ever used while holding lock of connection it was opened on
+unsafe impl Send for JsNfsTextLinesFile {}
+
+struct JsNfsTextLinesState {
+  file: Option<JsNfsTextLinesFile>,
+  offset: u64,
+  decoder: JsNfsTextDecoder,
+  partial: String,
+  lines: VecDeque<String>,
+  eof: bool
+}
+
+impl JsNfsTextLinesState {
+
+  fn push(&mut self, text: String) {
+    let start = self.partial.len();
+    self.partial.push_str(&text);
+    let Some(end) = self.partial[start..].rfind('\n').map(|end| start + end) else {
+      return;
+    };
+    let rest = self.partial.split_off(end + 1);
+    let complete = std::mem::replace(&mut self.partial, rest);
+    self.lines.extend(complete[..end].split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line).to_string()));
+  }
+}
+
+#[napi]
+pub struct JsNfsTextLines {
+  handle: JsNfsHandle,
+  cancellation: JsNfsCancellation,
+  state: Arc<Mutex<JsNfsTextLinesState>>
+}
+
+impl Drop for JsNfsTextLines {
+
+  fn drop(&mut self) {
+    // XXX: iteration may be given up on before end of file, which must then still be closed while connection is locked
+    let mut state = self.state.lock().unwrap();
+    if let (Some(nfs), Some(_)) = (&self.handle.nfs, &state.file) {
+      let _my_nfs = nfs.write().unwrap();
+      state.file = None;
+    }
   }
+}
+
+#[napi]
+impl JsNfsTextLines {
 
   #[napi]
-  pub async fn text(&self) -> Result<String> {
-    Ok(std::str::from_utf8(&self.nfs_bytes()?).unwrap().into())
+  pub async fn next(&self) -> Result<JsNfsTextLinesResult> {
+    let mut guard = self.state.lock().unwrap();
+    let state = &mut *guard;
+    let cancellation = self.cancellation.restart();
+    cancellation.check()?; // XXX: even when lines are left over from last chunk, so that abort takes effect right away
+    // XXX: read a chunk at a time, so that huge files never have to fit in memory as a whole
+    while state.lines.is_empty() && !state.eof {
+      let mut my_nfs = self.handle.nfs_guard()?;
+      cancellation.check()?;
+      // XXX: open file and read its size only for first chunk, like a File is a snapshot of what it was when taken
+      if state.file.is_none() {
+        let file = self.handle.nfs_open(&mut my_nfs, OFlag::O_RDONLY.bits() as u32)?;
+        let size = file.fstat64()?.size;
+        state.file = Some(JsNfsTextLinesFile{file, size});
+      }
+      let JsNfsTextLinesFile{file: nfs_file, size} = state.file.as_ref().unwrap();
+      let size = *size;
+      let text = if state.offset < size {
+        let count = nfs_file.get_max_read_size().min(size - state.offset) as u32;
+        let mut buffer = vec![0u8; count as usize];
+        let bytes_read = nfs_file.pread_into(count, state.offset, &mut buffer)?;
+        state.offset += bytes_read as u64;
+        state.eof = bytes_read == 0;
+        state.decoder.decode(&buffer[..bytes_read as usize], state.eof)?
+      } else {
+        state.eof = true;
+        state.decoder.decode(&[], true)?
+      };
+      if state.eof {
+        state.file = None; // XXX: while connection is still locked
+      }
+      state.push(text);
+      if state.eof && !state.partial.is_empty() {
+        let line = std::mem::take(&mut state.partial);
+        state.lines.push_back(line.strip_suffix('\r').map(str::to_string).unwrap_or(line));
+      }
+    }
+    let value = state.lines.pop_front();
+    Ok(JsNfsTextLinesResult{done: value.is_none(), value})
   }
 }
 
-pub struct JsNfsFileArrayBuffer(JsNfsFile);
+pub struct JsNfsFileArrayBuffer(JsNfsFile, JsNfsCancellation);
 
 #[napi]
 impl Task for JsNfsFileArrayBuffer {
@@ -763,7 +2146,7 @@ impl Task for JsNfsFileArrayBuffer {
   type JsValue = JsArrayBuffer;
 
   fn compute(&mut self) -> Result<Self::Output> {
-    self.0.nfs_bytes()
+    self.0.nfs_bytes(&self.1)
   }
 
   fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
@@ -774,6 +2157,7 @@ impl Task for JsNfsFileArrayBuffer {
 #[napi]
 pub struct JsNfsReadableStreamSource {
   handle: JsNfsHandle,
+  cancellation: JsNfsCancellation,
   offset: u64,
   #[napi(readonly, ts_type="'bytes'")]
   pub type_: String
@@ -785,15 +2169,23 @@ impl JsNfsReadableStreamSource {
   #[napi]
   pub fn pull(&mut self, env: Env, #[napi(ts_arg_type="ReadableByteStreamController")] controller: Unknown) -> Result<()> {
     let controller = controller.coerce_to_object()?;
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let nfs_file = my_nfs.open(self.handle.path.as_str(), OFlag::O_RDONLY.bits() as u32)?;
-    let size = nfs_file.fstat64()?.size;
+    let mut my_nfs = self.handle.nfs_guard()?;
+    self.cancellation.restart().check()?;
+    let nfs_file = self.handle.nfs_open(&mut my_nfs, OFlag::O_RDONLY.bits() as u32)?;
+    let nfs_stat = nfs_file.fstat64()?;
+    let size = nfs_stat.size;
     if self.offset < size {
-      let max_count = nfs_file.get_max_read_size();
-      let count = max_count.min(size - self.offset) as u32;
+      let max_count = nfs_file.get_max_read_size().min(size - self.offset);
+      // XXX: chunks end where holes start or end, so that a hole comes out as chunks of zeros that were never read
+      let (data, hole) = if is_sparse(&nfs_stat) {
+        let data = nfs_file.seek_data(self.offset)?;
+        (data, if data > self.offset { data } else { nfs_file.seek_hole(self.offset)? })
+      } else {
+        (self.offset, size)
+      };
+      let count = max_count.min(hole.max(self.offset + 1) - self.offset) as u32;
       let mut buffer = vec![0u8; count as usize];
-      let bytes_read = nfs_file.pread_into(count, self.offset, &mut buffer)?;
+      let bytes_read = if data > self.offset { count } else { nfs_file.pread_into(count, self.offset, &mut buffer)? };
 
       let enqueue = controller.get_named_property::<JsFunction>(FIELD_ENQUEUE)?;
       let arg = env.create_arraybuffer_with_data(buffer)?;
@@ -813,6 +2205,7 @@ pub struct JsNfsWritableFileStream {
   handle: JsNfsHandle,
   keep_existing_data: Option<bool>,
   position: Option<i64>,
+  lock: Mutex<Option<JsNfsHeldLock>>,
   #[napi(readonly)]
   pub locked: bool
 }
@@ -984,14 +2377,25 @@ impl JsNfsWritableFileStream {
   }
 
   fn nfs_write(&mut self, bytes: &[u8]) -> Result<Undefined> {
-    let nfs = &self.handle.nfs;
-    let mut my_nfs = nfs.as_ref().unwrap().write().unwrap();
-    let nfs_file = my_nfs.open(self.handle.path.as_str(), (OFlag::O_WRONLY | OFlag::O_SYNC).bits() as u32)?;
+    let mut my_nfs = self.handle.nfs_guard()?;
+    let nfs_file = self.handle.nfs_open(&mut my_nfs, (OFlag::O_WRONLY | OFlag::O_SYNC).bits() as u32)?;
     let offset = match self.position {
       None => nfs_file.fstat64()?.size,
       Some(pos) => pos as u64
     };
-    let _ = nfs_file.pwrite(bytes, offset)?;
+    // XXX: zeros written where there is no data yet are left a hole, so that copies of sparse files stay sparse
+    if !bytes.is_empty() && bytes.iter().all(|b| *b == 0) {
+      let end = offset + bytes.len() as u64;
+      let size = nfs_file.fstat64()?.size;
+      if nfs_file.seek_data(offset)? >= end.min(size) {
+        if end > size {
+          my_nfs.truncate(self.handle.path.as_str(), end)?;
+        }
+        self.position = Some(end as i64);
+        return Ok(());
+      }
+    }
+    nfs_file.pwrite_all(bytes, offset, &|| Ok(()))?;
     let post_write_pos = (offset as i64) + (bytes.len() as i64);
     self.position = Some(post_write_pos);
     Ok(())
@@ -1028,8 +2432,8 @@ impl JsNfsWritableFileStream {
   }
 
   fn nfs_truncate(&mut self, size: i64) -> Result<Undefined> {
-    let nfs = &self.handle.nfs;
-    let my_nfs = nfs.as_ref().unwrap().write().unwrap();
+    let my_nfs = self.handle.nfs_guard()?;
+    // XXX: truncate only goes by path, so stat by path too, lest size before be that of another entry after a rename
     let nfs_stat = my_nfs.stat64(self.handle.path.as_str())?;
     my_nfs.truncate(self.handle.path.as_str(), size as u64)?;
     let size_before = nfs_stat.size as i64;
@@ -1046,13 +2450,46 @@ impl JsNfsWritableFileStream {
     AsyncTask::new(JsNfsWritableFileStreamTruncate{stream: self, size})
   }
 
+  fn nfs_allocate(&self, offset: i64, length: i64, punch: bool) -> Result<Undefined> {
+    if offset < 0 || length < 0 {
+      return Err(Error::new(Status::InvalidArg, format!("Range of offset {} and length {} is not allowed", offset, length)));
+    }
+    let mut my_nfs = self.handle.nfs_guard()?;
+    let nfs_file = self.handle.nfs_open(&mut my_nfs, OFlag::O_WRONLY.bits() as u32)?;
+    if punch {
+      nfs_file.punch_hole(offset as u64, length as u64)?;
+    } else {
+      nfs_file.allocate(offset as u64, length as u64)?;
+    }
+    Ok(())
+  }
+
   #[napi]
-  pub async fn close(&self) -> Result<Undefined> {
+  pub async fn allocate(&self, offset: i64, length: i64) -> Result<Undefined> {
+    self.nfs_allocate(offset, length, false)
+  }
+
+  #[napi]
+  pub async fn punch_hole(&self, offset: i64, length: i64) -> Result<Undefined> {
+    self.nfs_allocate(offset, length, true)
+  }
+
+  fn nfs_release_lock(&self) -> Result<()> {
+    let held = self.lock.lock().unwrap().take();
+    if let Some(mut held) = held {
+      held.release()?;
+    }
     Ok(())
   }
 
+  #[napi]
+  pub async fn close(&self) -> Result<Undefined> {
+    self.nfs_release_lock()
+  }
+
   #[napi]
   pub async fn abort(&self, reason: String) -> Result<String> {
+    let _ = self.nfs_release_lock();
     Ok(reason)
   }
 
@@ -1154,12 +2591,13 @@ impl JsNfsWritableStreamSink {
 
   #[napi(ts_return_type="Promise<string>")]
   pub fn abort(&mut self, reason: String) -> Result<String> {
-    self.close_stream();
+    let _ = self.close_stream();
     Ok(reason)
   }
 
-  fn close_stream(&mut self) {
+  fn close_stream(&mut self) -> Result<()> {
     self.closed = true;
+    self.stream.nfs_release_lock()
   }
 
   #[napi(ts_args_type="controller?: WritableStreamDefaultController", ts_return_type="Promise<void>")]
@@ -1167,8 +2605,7 @@ impl JsNfsWritableStreamSink {
     if self.closed {
       return Err(Error::new(Status::GenericFailure, "Invalid state: WritableStream is closed".to_string()));
     }
-    self.close_stream();
-    Ok(())
+    self.close_stream()
   }
 
   #[napi(ts_return_type="Promise<void>")]
@@ -1209,6 +2646,220 @@ fn get_parent_path_and_name(path: &String) -> (String, String) {
   path.rsplit_once('/').map(|res| (res.0.to_string() + "/", res.1.to_string())).unwrap()
 }
 
+// Returns path and name that entry reopened by file handle is at now, which is where it was serialized, unless it has
+// been renamed since, in which case it is looked for by fileid in directory it was in.
+fn nfs_find_path(my_nfs: &mut RwLockWriteGuard<Box<dyn NFS>>, path: String, name: String, nfs_stat: &NFSStat64) -> Result<(String, String)> {
+  let id = JsNfsHandleId::from(nfs_stat);
+  let trimmed = path.trim_end_matches('/').to_string();
+  if trimmed.is_empty() || my_nfs.stat64(path.as_str()).is_ok_and(|nfs_stat| id == (&nfs_stat).into()) {
+    return Ok((path, name));
+  }
+  let (parent_path, _) = get_parent_path_and_name(&trimmed);
+  let is_dir = path.ends_with('/');
+  if let Ok(dir) = my_nfs.opendir(parent_path.as_str()) {
+    for entry in dir.flatten() {
+      if entry.inode == nfs_stat.ino && entry.path != DIR_CURRENT && entry.path != DIR_PARENT {
+        let path = if is_dir { format_dir_path(&parent_path, &entry.path) } else { format_file_path(&parent_path, &entry.path) };
+        if my_nfs.stat64(path.as_str()).is_ok_and(|nfs_stat| id == (&nfs_stat).into()) {
+          return Ok((path, entry.path));
+        }
+      }
+    }
+  }
+  Err(Error::new(Status::GenericFailure, format!("Entry {:?} not found in {:?}", name, parent_path)))
+}
+
+fn get_export_from_url(url: &str) -> &str {
+  url.split_once('?').map_or(url, |(export, _)| export).trim_end_matches('/')
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+  if hex.len() % 2 != 0 {
+    return None;
+  }
+  (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn split_path(path: &str) -> Vec<String> {
+  path.split('/').filter(|component| !component.is_empty()).map(str::to_string).collect()
+}
+
+// Returns components of path relative to ancestor path, which are none for ancestor itself, or None if path is not
+// ancestor or below it (so that "/ab" is not taken to be below "/a").
+fn get_relative_path(ancestor: &str, path: &str) -> Option<Vec<String>> {
+  let rest = path.strip_prefix(ancestor.trim_end_matches('/'))?;
+  (rest.is_empty() || rest.starts_with('/')).then(|| split_path(rest))
+}
+
+// Tells whether file takes up less storage than its size, which means it may have holes worth skipping when reading.
+fn is_sparse(nfs_stat: &NFSStat64) -> bool {
+  nfs_stat.used < nfs_stat.size
+}
+
+// Reads like pread_all_into, but only where file has data, so that buffer is left as it is (zeros) where it has holes.
+fn nfs_pread_data_into(nfs_file: &dyn NFSFile, offset: u64, buffer: &mut [u8], check: &nfs::Check) -> std::io::Result<u64> {
+  let end = offset + buffer.len() as u64;
+  let mut pos = offset;
+  while pos < end {
+    let data = nfs_file.seek_data(pos)?;
+    if data >= end {
+      break;
+    }
+    let hole = nfs_file.seek_hole(data)?.min(end);
+    if hole <= data {
+      return Ok(data - offset); // XXX: end of file, which must have been truncated since its size was read
+    }
+    let len = nfs_file.pread_all_into(data, &mut buffer[(data - offset) as usize..(hole - offset) as usize], check)?;
+    if len < hole - data {
+      return Ok(data - offset + len);
+    }
+    pos = hole;
+  }
+  Ok(buffer.len() as u64)
+}
+
+// Returns prompt that requestPermission can call from any thread, without keeping process alive just for it.
+fn create_permission_prompt(env: &Env, prompt: &JsFunction) -> Result<JsNfsPermissionPrompt> {
+  let mut tsfn: JsNfsPermissionPrompt = prompt.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<(JsNfsHandle, JsNfsHandlePermissionDescriptor)>| {
+    let (handle, perm) = ctx.value;
+    let env = ctx.env.raw();
+    unsafe { Ok(vec![JsUnknown::from_napi_value(env, JsNfsHandle::to_napi_value(env, handle)?)?, JsUnknown::from_napi_value(env, JsNfsHandlePermissionDescriptor::to_napi_value(env, perm)?)?]) }
+  })?;
+  tsfn.unref(env)?;
+  Ok(tsfn)
+}
+
+// Returns policy named by value of permissionPolicy, which must be one of the known names if given at all, so that a
+// typo is reported rather than silently meaning never.
+fn get_permission_policy(policy: Option<&str>) -> Result<nfs::PermissionPolicy> {
+  match policy {
+    None | Some(PERM_POLICY_NEVER) => Ok(nfs::PermissionPolicy::Never),
+    Some(PERM_POLICY_CHMOD) => Ok(nfs::PermissionPolicy::Chmod),
+    Some(PERM_POLICY_PROMPT) => Ok(nfs::PermissionPolicy::Prompt),
+    Some(other) => Err(Error::new(Status::InvalidArg, format!("Invalid {} {:?}, must be one of {:?}, {:?} or {:?}", FIELD_PERMISSION_POLICY, other, PERM_POLICY_NEVER, PERM_POLICY_CHMOD, PERM_POLICY_PROMPT)))
+  }
+}
+
+// Returns what names looked up are cached according to value of lookupcache, which must be one of those of the mount
+// option if given at all.
+fn get_lookup_cache(lookup_cache: Option<&str>) -> Result<nfs::LookupCache> {
+  match lookup_cache {
+    None | Some(LOOKUP_CACHE_ALL) => Ok(nfs::LookupCache::All),
+    Some(LOOKUP_CACHE_POSITIVE) => Ok(nfs::LookupCache::Positive),
+    Some(LOOKUP_CACHE_NONE) => Ok(nfs::LookupCache::None),
+    Some(other) => Err(Error::new(Status::InvalidArg, format!("Invalid {} {:?}, must be one of {:?}, {:?} or {:?}", FIELD_LOOKUPCACHE, other, LOOKUP_CACHE_ALL, LOOKUP_CACHE_POSITIVE, LOOKUP_CACHE_NONE)))
+  }
+}
+
+// Returns what special files are listed as according to value of specialFiles, which must be one of the known names if
+// given at all, and otherwise means file, as they always were before.
+fn get_special_files(special_files: Option<&str>) -> Result<nfs::SpecialFiles> {
+  match special_files {
+    None | Some(SPECIAL_FILES_FILE) => Ok(nfs::SpecialFiles::File),
+    Some(SPECIAL_FILES_SKIP) => Ok(nfs::SpecialFiles::Skip),
+    Some(SPECIAL_FILES_EXPOSE) => Ok(nfs::SpecialFiles::Expose),
+    Some(other) => Err(Error::new(Status::InvalidArg, format!("Invalid {} {:?}, must be one of {:?}, {:?} or {:?}", FIELD_SPECIAL_FILES, other, SPECIAL_FILES_FILE, SPECIAL_FILES_SKIP, SPECIAL_FILES_EXPOSE)))
+  }
+}
+
+// Returns kind entry of given type is listed as, or None if it is not listed at all, where symlinks do not have a kind
+// of their own since they are listed as whatever they point to.
+fn get_entry_kind(d_type: &NFSEntryType, special_files: nfs::SpecialFiles) -> Option<&'static str> {
+  let special_kind = match d_type {
+    NFSEntryType::Directory => return Some(KIND_DIRECTORY),
+    NFSEntryType::File | NFSEntryType::Symlink | NFSEntryType::Unknown => return Some(KIND_FILE),
+    NFSEntryType::Block => KIND_BLOCK_DEVICE,
+    NFSEntryType::Character => KIND_CHARACTER_DEVICE,
+    NFSEntryType::NamedPipe => KIND_FIFO,
+    NFSEntryType::Socket => KIND_SOCKET,
+  };
+  match special_files {
+    nfs::SpecialFiles::File => Some(KIND_FILE),
+    nfs::SpecialFiles::Skip => None,
+    nfs::SpecialFiles::Expose => Some(special_kind)
+  }
+}
+
+fn is_special_kind(kind: &str) -> bool {
+  [KIND_BLOCK_DEVICE, KIND_CHARACTER_DEVICE, KIND_FIFO, KIND_SOCKET].contains(&kind)
+}
+
+fn not_regular_file(name: &str) -> Error {
+  Error::new(Status::GenericFailure, format!("Entry {:?} is not a regular file", name))
+}
+
+// Returns file type bits of mode that special file of given kind is created with.
+fn get_special_kind_mode(kind: &str) -> Result<u32> {
+  let flag = match kind {
+    KIND_BLOCK_DEVICE => SFlag::S_IFBLK,
+    KIND_CHARACTER_DEVICE => SFlag::S_IFCHR,
+    KIND_FIFO => SFlag::S_IFIFO,
+    KIND_SOCKET => SFlag::S_IFSOCK,
+    _ => return Err(Error::new(Status::InvalidArg, format!("Kind {:?} is not allowed", kind)))
+  };
+  Ok(flag.bits() as u32)
+}
+
+fn check_xattr_name(name: &str) -> Result<&str> {
+  if name.is_empty() || name.contains('\0') {
+    return Err(Error::new(Status::InvalidArg, format!("Name {:?} is not allowed", name)));
+  }
+  Ok(name)
+}
+
+fn check_mode(mode: u32) -> Result<u32> {
+  if mode & !0o7777 != 0 {
+    return Err(Error::new(Status::InvalidArg, format!("Mode {:#o} is not allowed", mode)));
+  }
+  Ok(mode)
+}
+
+fn check_entry_name(name: &str) -> Result<()> {
+  if name.is_empty() || name == DIR_CURRENT || name == DIR_PARENT || name.contains('/') || name.contains('\0') {
+    return Err(Error::new(Status::InvalidArg, format!("Name {:?} is not allowed", name)));
+  }
+  Ok(())
+}
+
+fn nfs_realpath(my_nfs: &RwLockWriteGuard<Box<dyn NFS>>, parent_path: &str, name: &str) -> Result<String> {
+  let mut resolved = split_path(parent_path);
+  let mut pending = vec![name.to_string()];
+  let mut followed = 0;
+  while let Some(component) = pending.pop() {
+    if component.is_empty() || component == DIR_CURRENT {
+      continue;
+    }
+    if component == DIR_PARENT {
+      if resolved.pop().is_none() {
+        return Err(Error::new(Status::GenericFailure, format!("Symlink {:?} points outside of root", name)));
+      }
+      continue;
+    }
+    resolved.push(component);
+    let path = format!("/{}", resolved.join("/"));
+    match my_nfs.readlink(path.as_str()) {
+      Ok(target) => {
+        followed += 1;
+        if followed > MAX_SYMLINKS {
+          return Err(Error::new(Status::GenericFailure, format!("Too many levels of symlinks resolving {:?}", name)));
+        }
+        let _ = resolved.pop();
+        if target.starts_with('/') {
+          resolved.clear();
+        }
+        pending.extend(target.split('/').rev().map(str::to_string));
+      },
+      Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {}, // XXX: not a symlink
+      Err(e) => return Err(e.into()),
+    }
+  }
+  Ok(format!("/{}", resolved.join("/")))
+}
+
 fn format_dir_path(parent_path: &String, name: &String) -> String {
   format!("{}{}/", parent_path, name)
 }
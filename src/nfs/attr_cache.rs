@@ -0,0 +1,596 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nix::sys::stat::SFlag;
+
+use super::{Ace, AttrCacheOptions, CacheStats, Check, CreateMode, Credentials, NFS, NFSDirEntry, NFSEntryType, NFSStat64, NFSDirectory, NFSFile, Result};
+
+const MAX_ENTRIES: usize = 65536;
+const MAX_CREDENTIALS: usize = 8; // XXX: same as number of mounts per credentials kept by backends
+
+// Key names an entry by path, by file handle, or by name within directory listed by file handle.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Key {
+    Path(String),
+    Fh(Vec<u8>),
+    Child(Vec<u8>, String),
+}
+
+impl Key {
+    fn path(path: &str) -> Self {
+        let path = path.trim_end_matches('/');
+        Key::Path(if path.is_empty() { "/".into() } else { path.into() })
+    }
+
+    fn child(&self, name: &str) -> Option<Self> {
+        match self {
+            Key::Path(path) => Some(Key::Path(format!("{}/{}", path.trim_end_matches('/'), name))),
+            Key::Fh(fh) => Some(Key::Child(fh.clone(), name.into())),
+            Key::Child(..) => None,
+        }
+    }
+
+    fn has_name(&self, name: &str) -> bool {
+        match self {
+            Key::Path(path) => path.rsplit('/').next() == Some(name),
+            Key::Fh(_) => false,
+            Key::Child(_, child) => child == name,
+        }
+    }
+}
+
+type Inode = (u64, u64);
+
+#[derive(Debug)]
+struct Entry {
+    stat: NFSStat64,
+    fetched: Instant,
+    timeout: Duration,
+}
+
+impl Entry {
+    fn is_fresh(&self) -> bool {
+        self.fetched.elapsed() < self.timeout
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    names: HashMap<Key, Inode>,
+    attrs: HashMap<Inode, Entry>,
+}
+
+// AttrCache remembers attributes for a while, as NFS clients do, so that repeatedly looking at same entry costs a single
+// GETATTR per timeout. Timeout starts at its minimum and doubles, up to its maximum, every time attributes are fetched
+// again without having changed. Entries are keyed by inode, so that fresh attributes fetched by any name replace those
+// cached for others, and dropped whenever they are modified through this connection. Each credentials get entries of
+// their own, since whether a name can be looked up at all depends on who asks.
+#[derive(Debug)]
+pub(super) struct AttrCache {
+    nfs: Box<dyn NFS>,
+    credentials: Credentials,
+    attrs: Arc<Attrs>,
+}
+
+// Attrs is what AttrCache shares with directories and files opened through it, which go on seeding and invalidating
+// it for as long as they are around.
+#[derive(Debug)]
+struct Attrs {
+    options: AttrCacheOptions,
+    entries: Mutex<VecDeque<(Credentials, Entries)>>, // least recently used first
+}
+
+impl AttrCache {
+    pub(super) fn new(nfs: Box<dyn NFS>, options: &AttrCacheOptions) -> Box<dyn NFS> {
+        let attrs = Attrs{options: options.clone(), entries: Default::default()};
+        Box::new(AttrCache{nfs, credentials: Default::default(), attrs: Arc::new(attrs)})
+    }
+
+    // Makes alias name same inode as key, if known, e.g. file handle that a path was just looked up to.
+    fn alias(&self, key: &Key, alias: Key) {
+        self.attrs.with_entries(&self.credentials, |entries| {
+            if let Some(inode) = entries.names.get(key).copied() {
+                let _ = entries.names.insert(alias, inode);
+            }
+        });
+    }
+
+    fn wrap_file(&self, key: Key, file: Box<dyn NFSFile>) -> Box<dyn NFSFile> {
+        Box::new(CachedFile{attrs: self.attrs.clone(), credentials: self.credentials.clone(), key, file})
+    }
+
+    fn wrap_directory(&self, key: Key, dir: Box<dyn NFSDirectory>) -> Box<dyn NFSDirectory> {
+        Box::new(CachedDirectory{attrs: self.attrs.clone(), credentials: self.credentials.clone(), key, dir})
+    }
+}
+
+impl Attrs {
+    // Runs op with entries of credentials, which are set up if there are none yet.
+    fn with_entries<T>(&self, credentials: &Credentials, op: impl FnOnce(&mut Entries) -> T) -> T {
+        let mut all = self.entries.lock().unwrap();
+        let entries = match all.iter().position(|(other, _)| other == credentials) {
+            Some(index) => all.remove(index).unwrap(),
+            None => {
+                if all.len() >= MAX_CREDENTIALS {
+                    let _ = all.pop_front();
+                }
+                (credentials.clone(), Default::default())
+            }
+        };
+        all.push_back(entries);
+        op(&mut all.back_mut().unwrap().1)
+    }
+
+    // Runs op with entries of all credentials, e.g. to drop attributes modified by any of them.
+    fn with_all_entries(&self, mut op: impl FnMut(&mut Entries)) {
+        for (_, entries) in self.entries.lock().unwrap().iter_mut() {
+            op(entries);
+        }
+    }
+
+    fn get(&self, credentials: &Credentials, key: &Key) -> Option<NFSStat64> {
+        self.with_entries(credentials, |entries| {
+            let inode = entries.names.get(key)?;
+            entries.attrs.get(inode).filter(|entry| entry.is_fresh()).map(|entry| entry.stat)
+        })
+    }
+
+    fn fetch(&self, credentials: &Credentials, key: Key, getattr: impl FnOnce() -> Result<NFSStat64>) -> Result<NFSStat64> {
+        if let Some(stat) = self.get(credentials, &key) {
+            return Ok(stat);
+        }
+        let stat = getattr()?;
+        self.store(credentials, key, stat);
+        Ok(stat)
+    }
+
+    fn store(&self, credentials: &Credentials, key: Key, stat: NFSStat64) {
+        let (min, max) = self.get_timeouts(&stat);
+        if max.is_zero() {
+            return;
+        }
+        self.with_entries(credentials, |entries| Self::store_in(entries, key, stat, min, max));
+    }
+
+    fn store_in(entries: &mut Entries, key: Key, stat: NFSStat64, min: Duration, max: Duration) {
+        if entries.attrs.len() >= MAX_ENTRIES {
+            entries.attrs.retain(|_, entry| entry.is_fresh());
+            if entries.attrs.len() >= MAX_ENTRIES {
+                entries.attrs.clear();
+            }
+            let Entries{names, attrs} = &mut *entries;
+            names.retain(|_, inode| attrs.contains_key(inode));
+        }
+        let inode = (stat.dev, stat.ino);
+        let timeout = match entries.attrs.get(&inode) {
+            Some(prev) if !has_changed(&prev.stat, &stat) => (prev.timeout * 2).clamp(min, max),
+            _ => min,
+        };
+        let _ = entries.attrs.insert(inode, Entry{stat, fetched: Instant::now(), timeout});
+        let _ = entries.names.insert(key, inode);
+    }
+
+    fn invalidate(&self, key: &Key) {
+        self.with_all_entries(|entries| {
+            if let Some(inode) = entries.names.get(key).copied() {
+                let _ = entries.attrs.remove(&inode);
+            }
+        });
+    }
+
+    // Drops attributes at path along with those of any entry of same name listed by file handle, since those may well be
+    // same one, and also forgets which inode those names refer to if entry was created or removed.
+    fn invalidate_path(&self, path: &str, forget: bool) {
+        let key = Key::path(path);
+        let name = match &key {
+            Key::Path(path) => path.rsplit('/').next().unwrap_or_default().to_string(),
+            _ => unreachable!(),
+        };
+        self.with_all_entries(|Entries{names, attrs}| {
+            names.retain(|other, inode| {
+                if *other == key || (matches!(other, Key::Child(..)) && other.has_name(&name)) {
+                    let _ = attrs.remove(inode);
+                    return !forget;
+                }
+                true
+            });
+        });
+    }
+
+    fn invalidate_path_and_parent(&self, path: &str) {
+        self.invalidate_path(path, true);
+        let path = path.trim_end_matches('/');
+        self.invalidate_path(path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or_default(), false);
+    }
+
+    fn get_timeouts(&self, stat: &NFSStat64) -> (Duration, Duration) {
+        let options = &self.options;
+        let (min, max) = if stat.mode & SFlag::S_IFMT.bits() as u64 == SFlag::S_IFDIR.bits() as u64 {
+            (options.acdirmin, options.acdirmax)
+        } else {
+            (options.acregmin, options.acregmax)
+        };
+        (Duration::from_secs(min.min(max).into()), Duration::from_secs(max.into()))
+    }
+}
+
+fn has_changed(old: &NFSStat64, new: &NFSStat64) -> bool {
+    old.size != new.size || old.mtime != new.mtime || old.mtime_nsec != new.mtime_nsec || old.ctime != new.ctime || old.ctime_nsec != new.ctime_nsec
+}
+
+fn entry_to_stat64(entry: &NFSDirEntry) -> NFSStat64 {
+    // XXX: entries do not necessarily include file type bits in mode, so add them
+    NFSStat64{
+        dev: entry.dev,
+        ino: entry.inode,
+        mode: (entry.mode as u64 & !(SFlag::S_IFMT.bits() as u64)) | entry.d_type.to_mode(),
+        nlink: entry.nlink.into(),
+        uid: entry.uid.into(),
+        gid: entry.gid.into(),
+        rdev: entry.rdev,
+        size: entry.size,
+        used: entry.used,
+        blksize: entry.blksize,
+        blocks: entry.blocks,
+        atime: entry.atime.seconds.into(),
+        mtime: entry.mtime.seconds.into(),
+        ctime: entry.ctime.seconds.into(),
+        atime_nsec: entry.atime_nsec.into(),
+        mtime_nsec: entry.mtime_nsec.into(),
+        ctime_nsec: entry.ctime_nsec.into(),
+    }
+}
+
+impl NFS for AttrCache {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.nfs.access(path, mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.attrs.fetch(&self.credentials, Key::path(path), || self.nfs.stat64(path))
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        let fh = self.nfs.lookup_fh(path)?;
+        self.alias(&Key::path(path), Key::Fh(fh.clone()));
+        Ok(fh)
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        let fh = self.nfs.lookup_in(dir_fh, name)?;
+        self.alias(&Key::Child(dir_fh.to_vec(), name.into()), Key::Fh(fh.clone()));
+        Ok(fh)
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.attrs.fetch(&self.credentials, Key::Fh(fh.to_vec()), || self.nfs.getattr_fh(fh))
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.nfs.set_credentials(credentials)?;
+        self.credentials = credentials.clone();
+        Ok(())
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        let dir = self.nfs.readdir_fh(fh)?;
+        Ok(self.wrap_directory(Key::Fh(fh.to_vec()), dir))
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        let file = self.nfs.open_fh(fh, flags)?;
+        let key = Key::Fh(fh.to_vec());
+        self.attrs.invalidate(&key); // XXX: close-to-open consistency
+        Ok(self.wrap_file(key, file))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.nfs.readlink(path)
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        let res = self.nfs.lchmod(path, mode);
+        self.attrs.invalidate_path(path, false);
+        res
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        let dir = self.nfs.opendir(path)?;
+        Ok(self.wrap_directory(Key::path(path), dir))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        let res = self.nfs.mkdir(path, mode);
+        self.attrs.invalidate_path_and_parent(path);
+        res
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        let res = self.nfs.create(path, flags, mode, how);
+        self.attrs.invalidate_path_and_parent(path);
+        Ok(self.wrap_file(Key::path(path), res?))
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        let res = self.nfs.mknod(path, mode, rdev);
+        self.attrs.invalidate_path_and_parent(path);
+        res
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        let res = self.nfs.rmdir(path);
+        self.attrs.invalidate_path_and_parent(path);
+        res
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        let res = self.nfs.unlink(path);
+        self.attrs.invalidate_path_and_parent(path);
+        res
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        let file = self.nfs.open(path, flags)?;
+        self.attrs.invalidate_path(path, false); // XXX: close-to-open consistency
+        Ok(self.wrap_file(Key::path(path), file))
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        let res = self.nfs.truncate(path, len);
+        self.attrs.invalidate_path(path, false);
+        res
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.nfs.getxattr(path, name)
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        let res = self.nfs.setxattr(path, name, value);
+        self.attrs.invalidate_path(path, false);
+        res
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.nfs.listxattrs(path)
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        let res = self.nfs.removexattr(path, name);
+        self.attrs.invalidate_path(path, false);
+        res
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.nfs.getacl(path)
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        // XXX: server keeps mode in line with acl, so cached mode may be stale now
+        let res = self.nfs.setacl(path, acl);
+        self.attrs.invalidate_path(path, false);
+        res
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.nfs.supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.nfs.cache_stats()
+    }
+}
+
+// CachedDirectory seeds cache with attributes of every entry listed, so that going through a directory and then looking
+// at its entries one by one costs no further GETATTRs.
+#[derive(Debug)]
+struct CachedDirectory {
+    attrs: Arc<Attrs>,
+    credentials: Credentials,
+    key: Key,
+    dir: Box<dyn NFSDirectory>,
+}
+
+impl NFSDirectory for CachedDirectory {}
+
+impl Iterator for CachedDirectory {
+    type Item = Result<NFSDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.dir.next()?;
+        if let Ok(entry) = &res {
+            // XXX: skip symlinks, since stat64 follows them, and entries that came without attributes
+            let has_attributes = entry.ctime.seconds != 0 || entry.mtime.seconds != 0;
+            if entry.path != "." && entry.path != ".." && entry.d_type != NFSEntryType::Symlink && has_attributes {
+                if let Some(key) = self.key.child(&entry.path) {
+                    self.attrs.store(&self.credentials, key, entry_to_stat64(entry));
+                }
+            }
+        }
+        Some(res)
+    }
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    attrs: Arc<Attrs>,
+    credentials: Credentials,
+    key: Key,
+    file: Box<dyn NFSFile>,
+}
+
+impl NFSFile for CachedFile {
+    fn fstat64(&self) -> Result<NFSStat64> {
+        self.attrs.fetch(&self.credentials, self.key.clone(), || self.file.fstat64())
+    }
+
+    fn get_max_read_size(&self) -> u64 {
+        self.file.get_max_read_size()
+    }
+
+    fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
+        self.file.pread_into(count, offset, buffer)
+    }
+
+    fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
+        let res = self.file.pwrite(buffer, offset);
+        self.attrs.invalidate(&self.key);
+        res
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        self.file.lock(offset, len, exclusive, wait)?;
+        // XXX: whoever held the lock before may have changed the file meanwhile
+        self.attrs.invalidate(&self.key);
+        Ok(())
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.unlock(offset, len)
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        self.file.seek_data(offset)
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        self.file.seek_hole(offset)
+    }
+
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        let res = self.file.allocate(offset, len);
+        self.attrs.invalidate(&self.key);
+        res
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        let res = self.file.punch_hole(offset, len);
+        self.attrs.invalidate(&self.key);
+        res
+    }
+
+    fn pread_all_into(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        self.file.pread_all_into(offset, buffer, check)
+    }
+
+    fn pwrite_all(&self, buffer: &[u8], offset: u64, check: &Check) -> Result<()> {
+        let res = self.file.pwrite_all(buffer, offset, check);
+        self.attrs.invalidate(&self.key);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::fcntl::OFlag;
+
+    use super::*;
+    use super::super::mock;
+
+    fn connect(options: &AttrCacheOptions) -> (Box<dyn NFS>, mock::Server) {
+        let server = mock::Server::new();
+        (AttrCache::new(mock::NFS3::connect_to("nfs://localhost/export".into(), &server), options), server)
+    }
+
+    #[test]
+    fn attributes_are_cached_until_modified() {
+        let (nfs, server) = connect(&AttrCacheOptions::kernel_defaults());
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 123);
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 123);
+        assert_eq!(server.getattrs(), 1);
+        let fh = nfs.lookup_fh("/annar").unwrap();
+        assert_eq!(nfs.getattr_fh(&fh).unwrap().size, 123);
+        assert_eq!(server.getattrs(), 1);
+        nfs.truncate("/annar", 10).unwrap();
+        assert_eq!(nfs.getattr_fh(&fh).unwrap().size, 10);
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 10);
+        assert_eq!(server.getattrs(), 2);
+    }
+
+    #[test]
+    fn writes_invalidate_attributes() {
+        let (mut nfs, server) = connect(&AttrCacheOptions::kernel_defaults());
+        let file = nfs.open("/annar", OFlag::O_RDWR.bits() as u32).unwrap();
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        let _ = file.pwrite(b"more", 123).unwrap();
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 127);
+        assert_eq!(file.fstat64().unwrap().size, 127);
+        assert_eq!(server.getattrs(), 1); // XXX: fstat64 goes straight to mock file, so is not counted
+    }
+
+    #[test]
+    fn files_outlive_cache() {
+        let (mut nfs, _server) = connect(&AttrCacheOptions::kernel_defaults());
+        let file = nfs.open("/annar", OFlag::O_RDWR.bits() as u32).unwrap();
+        drop(nfs);
+        let _ = file.pwrite(b"more", 123).unwrap();
+        assert_eq!(file.fstat64().unwrap().size, 127);
+    }
+
+    #[test]
+    fn listing_seeds_attributes() {
+        let (mut nfs, server) = connect(&AttrCacheOptions::kernel_defaults());
+        let names: Vec<String> = nfs.opendir("/first/").unwrap().map(|entry| entry.unwrap().path).collect();
+        assert_eq!(names, vec!["comment".to_string()]);
+        let stat = nfs.stat64("/first/comment").unwrap();
+        assert_eq!(stat.mode & SFlag::S_IFMT.bits() as u64, SFlag::S_IFREG.bits() as u64);
+        let dir_fh = nfs.lookup_fh("/quatre/").unwrap();
+        assert_eq!(nfs.readdir_fh(&dir_fh).unwrap().count(), 1);
+        let fh = nfs.lookup_in(&dir_fh, "points").unwrap();
+        assert_eq!(nfs.getattr_fh(&fh).unwrap().size, 0);
+        assert_eq!(server.getattrs(), 0);
+        nfs.unlink("/quatre/points").unwrap();
+        assert!(nfs.getattr_fh(&fh).is_err());
+    }
+
+    #[test]
+    fn timeouts_grow_while_unchanged() {
+        let options = AttrCacheOptions{acregmin: 1, acregmax: 4, acdirmin: 0, acdirmax: 0};
+        let (nfs, server) = connect(&options);
+        let after = |secs: f64| {
+            std::thread::sleep(Duration::from_secs_f64(secs));
+            let _ = nfs.stat64("/annar").unwrap();
+            server.getattrs()
+        };
+        assert_eq!(after(0.0), 1);
+        assert_eq!(after(1.1), 2); // XXX: fetched again once minimum of 1s is up, which doubles timeout to 2s
+        assert_eq!(after(1.1), 2);
+        assert_eq!(after(1.1), 3);
+        let _ = nfs.stat64("/first").unwrap();
+        let _ = nfs.stat64("/first").unwrap();
+        assert_eq!(server.getattrs(), 5); // XXX: directories are not cached with acdirmax of 0
+    }
+
+    #[test]
+    fn attributes_are_cached_per_credentials() {
+        let (mut nfs, server) = connect(&AttrCacheOptions::kernel_defaults());
+        let _ = nfs.stat64("/annar").unwrap();
+        nfs.set_credentials(&Credentials{uid: Some(1001), gid: Some(1001), aux_gids: None}).unwrap();
+        let _ = nfs.stat64("/annar").unwrap();
+        let _ = nfs.stat64("/annar").unwrap();
+        assert_eq!(server.getattrs(), 2);
+        nfs.set_credentials(&Default::default()).unwrap();
+        let _ = nfs.stat64("/annar").unwrap();
+        assert_eq!(server.getattrs(), 2);
+        nfs.truncate("/annar", 10).unwrap();
+        nfs.set_credentials(&Credentials{uid: Some(1001), gid: Some(1001), aux_gids: None}).unwrap();
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 10); // XXX: modifications drop attributes cached for anyone
+    }
+}
@@ -0,0 +1,644 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use nix::fcntl::OFlag;
+
+use super::{Ace, CacheStats, Check, CreateMode, Credentials, DataCacheOptions, NFS, NFSStat64, NFSDirectory, NFSFile, Result};
+
+const PAGE_SIZE: u64 = 1048576;
+const MAX_FILES: usize = 65536;
+
+// FileKey identifies a file by its handle or, if it was opened by path, by its fsid and fileid, since libnfs does not
+// expose file handles.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum FileKey {
+    Fh(Vec<u8>),
+    Inode(u64, u64),
+}
+
+type PageKey = (FileKey, u64);
+
+type Inode = (u64, u64);
+
+// Validator is what pages cached for a file were read under, so that they can be dropped once it changes.
+type Validator = (u64, u64, u64, u64, u64);
+
+fn get_validator(stat: &NFSStat64) -> Validator {
+    (stat.size, stat.mtime, stat.mtime_nsec, stat.ctime, stat.ctime_nsec)
+}
+
+// Lru keeps track of which pages were used least recently, and of how many bytes they take up.
+#[derive(Debug)]
+struct Lru<V> {
+    entries: HashMap<PageKey, (V, u64, u64)>,
+    order: BTreeMap<u64, PageKey>,
+    tick: u64,
+    bytes: u64,
+}
+
+impl<V> Default for Lru<V> {
+    fn default() -> Self {
+        Self{entries: HashMap::new(), order: BTreeMap::new(), tick: 0, bytes: 0}
+    }
+}
+
+impl<V> Lru<V> {
+    fn insert(&mut self, key: PageKey, value: V, len: u64) {
+        let _ = self.remove(&key);
+        self.tick += 1;
+        let _ = self.order.insert(self.tick, key.clone());
+        let _ = self.entries.insert(key, (value, len, self.tick));
+        self.bytes += len;
+    }
+
+    fn get(&mut self, key: &PageKey) -> Option<&V> {
+        let (_, _, tick) = self.entries.get_mut(key)?;
+        let _ = self.order.remove(tick);
+        self.tick += 1;
+        *tick = self.tick;
+        let _ = self.order.insert(self.tick, key.clone());
+        self.entries.get(key).map(|(value, _, _)| value)
+    }
+
+    fn contains(&self, key: &PageKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn peek(&self, key: &PageKey) -> Option<&V> {
+        self.entries.get(key).map(|(value, _, _)| value)
+    }
+
+    fn remove(&mut self, key: &PageKey) -> Option<(V, u64)> {
+        let (value, len, tick) = self.entries.remove(key)?;
+        let _ = self.order.remove(&tick);
+        self.bytes -= len;
+        Some((value, len))
+    }
+
+    fn pop_oldest(&mut self) -> Option<(PageKey, V, u64)> {
+        let (_, key) = self.order.pop_first()?;
+        let (value, len, _) = self.entries.remove(&key)?;
+        self.bytes -= len;
+        Some((key, value, len))
+    }
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    validator: Validator,
+    inode: Inode,
+    pages: BTreeSet<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Pages {
+    files: HashMap<FileKey, CachedFile>,
+    memory: Lru<Arc<Vec<u8>>>,
+    spilled: Lru<u64>, // number of spill file that page was written to
+}
+
+// Spill is a directory of our own that pages evicted from memory are written to, rather than forgotten, until it is full.
+// Every page written gets a file of its own, so that writing a page never races with reading or removing an earlier
+// copy of it.
+#[derive(Debug)]
+struct Spill {
+    dir: PathBuf,
+    max_bytes: u64,
+    files: AtomicU64,
+}
+
+impl Spill {
+    fn new(dir: &str, max_bytes: u64) -> Result<Self> {
+        static SPILLS: AtomicU64 = AtomicU64::new(0);
+        // XXX: never share directory with other connections, since they may well have other files with same handles
+        let dir = PathBuf::from(dir).join(format!("nfs-js-{}-{}", std::process::id(), SPILLS.fetch_add(1, Ordering::SeqCst)));
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self{dir, max_bytes, files: AtomicU64::new(0)})
+    }
+
+    fn get_path(&self, (key, index): &PageKey, number: u64) -> PathBuf {
+        let name = match key {
+            FileKey::Fh(fh) => fh.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            FileKey::Inode(dev, ino) => format!("{}-{}", dev, ino),
+        };
+        self.dir.join(format!("{}.{}.{}", name, index, number))
+    }
+}
+
+// SpillOp is disk I/O on spill directory, which is left for after pages are unlocked, so that reads and writes of other
+// threads do not have to wait for it.
+#[derive(Debug)]
+enum SpillOp {
+    Write(PageKey, u64, Arc<Vec<u8>>),
+    Remove(PathBuf),
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// DataCache keeps pages of files read in memory, up to a byte budget beyond which least recently used ones are spilled
+// to disk or dropped, so that reading same files over and over again does not take the whole trip to server every time.
+// It follows close-to-open consistency: attributes are fetched again whenever a file is opened and whatever was cached
+// for it is dropped if they show that it has changed since, or if it is modified through this connection.
+#[derive(Debug)]
+pub(super) struct DataCache {
+    nfs: Box<dyn NFS>,
+    max_bytes: u64,
+    spill: Option<Spill>,
+    pages: Mutex<Pages>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl DataCache {
+    pub(super) fn new(nfs: Box<dyn NFS>, options: &DataCacheOptions) -> Result<Box<dyn NFS>> {
+        let spill = options.spill_dir.as_deref().map(|dir| Spill::new(dir, options.spill_max_bytes)).transpose()?;
+        Ok(Box::new(DataCache{nfs, max_bytes: options.max_bytes, spill, pages: Default::default(), hits: AtomicU64::new(0), misses: AtomicU64::new(0)}))
+    }
+
+    fn get_page(&self, key: &PageKey) -> Option<Arc<Vec<u8>>> {
+        let (number, len) = {
+            let mut pages = self.pages.lock().unwrap();
+            if let Some(page) = pages.memory.get(key) {
+                return Some(page.clone());
+            }
+            pages.spilled.remove(key)?
+        };
+        let spill = self.spill.as_ref()?;
+        let path = spill.get_path(key, number);
+        // XXX: page may not have been written in full yet, in which case it is as good as not cached
+        let data = std::fs::read(&path).ok().filter(|data| data.len() as u64 == len);
+        let _ = std::fs::remove_file(&path);
+        let mut pages = self.pages.lock().unwrap();
+        // XXX: file may have been invalidated or page cached again meanwhile, in which case leave it at that
+        let is_current = pages.files.get(&key.0).is_some_and(|file| file.pages.contains(&key.1))
+            && !pages.memory.contains(key) && !pages.spilled.contains(key);
+        let Some(data) = data else {
+            if is_current {
+                forget_page(&mut pages, key);
+            }
+            return None;
+        };
+        let page = Arc::new(data);
+        if is_current {
+            let ops = self.insert_page(&mut pages, key.clone(), page.clone());
+            drop(pages);
+            self.spill(ops);
+        }
+        Some(page)
+    }
+
+    fn has_page(&self, key: &PageKey) -> bool {
+        let pages = self.pages.lock().unwrap();
+        pages.memory.contains(key) || pages.spilled.contains(key)
+    }
+
+    fn store_page(&self, key: PageKey, page: Arc<Vec<u8>>) {
+        let mut pages = self.pages.lock().unwrap();
+        // XXX: file may have been invalidated while page was being read, in which case it is as good as stale
+        let Some(file) = pages.files.get_mut(&key.0) else {
+            return;
+        };
+        let _ = file.pages.insert(key.1);
+        let ops = self.insert_page(&mut pages, key, page);
+        drop(pages);
+        self.spill(ops);
+    }
+
+    // Inserts page in memory and evicts least recently used ones beyond budget, returning what has to be written to or
+    // removed from spill directory for that.
+    fn insert_page(&self, pages: &mut Pages, key: PageKey, page: Arc<Vec<u8>>) -> Vec<SpillOp> {
+        let mut ops = Vec::new();
+        let len = page.len() as u64;
+        pages.memory.insert(key, page, len);
+        while pages.memory.bytes > self.max_bytes {
+            let Some((key, page, len)) = pages.memory.pop_oldest() else {
+                break;
+            };
+            match &self.spill {
+                Some(spill) if len <= spill.max_bytes => {
+                    let number = spill.files.fetch_add(1, Ordering::SeqCst);
+                    pages.spilled.insert(key.clone(), number, len);
+                    ops.push(SpillOp::Write(key, number, page));
+                    while pages.spilled.bytes > spill.max_bytes {
+                        let Some((key, number, _)) = pages.spilled.pop_oldest() else {
+                            break;
+                        };
+                        ops.push(SpillOp::Remove(spill.get_path(&key, number)));
+                        forget_page(pages, &key);
+                    }
+                },
+                _ => forget_page(pages, &key),
+            }
+        }
+        ops
+    }
+
+    // Carries out disk I/O on spill directory, which must be done without holding pages lock.
+    fn spill(&self, ops: Vec<SpillOp>) {
+        let Some(spill) = &self.spill else {
+            return;
+        };
+        for op in ops {
+            match op {
+                SpillOp::Write(key, number, page) => {
+                    let path = spill.get_path(&key, number);
+                    let written = std::fs::write(&path, page.as_slice()).is_ok();
+                    let mut pages = self.pages.lock().unwrap();
+                    if pages.spilled.peek(&key) != Some(&number) {
+                        // XXX: page was read back, dropped or evicted while being written, so nobody is left to remove it
+                        drop(pages);
+                        let _ = std::fs::remove_file(&path);
+                    } else if !written {
+                        let _ = pages.spilled.remove(&key);
+                        forget_page(&mut pages, &key);
+                        drop(pages);
+                        let _ = std::fs::remove_file(&path);
+                    }
+                },
+                SpillOp::Remove(path) => {
+                    let _ = std::fs::remove_file(path);
+                },
+            }
+        }
+    }
+
+    fn drop_file(&self, pages: &mut Pages, key: &FileKey) -> Vec<SpillOp> {
+        let mut ops = Vec::new();
+        let Some(file) = pages.files.remove(key) else {
+            return ops;
+        };
+        for index in file.pages {
+            let key = (key.clone(), index);
+            let _ = pages.memory.remove(&key);
+            if let Some((number, _)) = pages.spilled.remove(&key) {
+                if let Some(spill) = &self.spill {
+                    ops.push(SpillOp::Remove(spill.get_path(&key, number)));
+                }
+            }
+        }
+        ops
+    }
+
+    fn drop_inode(&self, inode: Inode) {
+        let mut pages = self.pages.lock().unwrap();
+        let keys: Vec<FileKey> = pages.files.iter().filter(|(_, file)| file.inode == inode).map(|(key, _)| key.clone()).collect();
+        let ops = keys.iter().flat_map(|key| self.drop_file(&mut pages, key)).collect();
+        drop(pages);
+        self.spill(ops);
+    }
+
+    // Fetches attributes of file just opened, and drops whatever was cached for it if they show that it has changed.
+    fn revalidate(&self, key: Option<FileKey>, file: Box<dyn NFSFile>, flags: u32) -> Result<Box<dyn NFSFile>> {
+        let stat = file.fstat64()?;
+        let inode = (stat.dev, stat.ino);
+        let key = key.unwrap_or(FileKey::Inode(stat.dev, stat.ino));
+        let validator = get_validator(&stat);
+        let mut pages = self.pages.lock().unwrap();
+        let truncated = OFlag::from_bits_truncate(flags as i32).contains(OFlag::O_TRUNC);
+        let mut ops = Vec::new();
+        if truncated || pages.files.get(&key).is_some_and(|file| file.validator != validator || file.inode != inode) {
+            ops = self.drop_file(&mut pages, &key);
+        }
+        if pages.files.len() >= MAX_FILES {
+            pages.files.retain(|_, file| !file.pages.is_empty());
+        }
+        let _ = pages.files.entry(key.clone()).or_insert_with(|| CachedFile{validator, inode, pages: BTreeSet::new()});
+        drop(pages);
+        self.spill(ops);
+        Ok(Box::new(DataCacheFile{cache: self, key, inode, file}))
+    }
+}
+
+fn forget_page(pages: &mut Pages, (key, index): &PageKey) {
+    if let Some(file) = pages.files.get_mut(key) {
+        let _ = file.pages.remove(index);
+    }
+}
+
+impl NFS for DataCache {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.nfs.access(path, mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.nfs.stat64(path)
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        self.nfs.lookup_fh(path)
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        self.nfs.lookup_in(dir_fh, name)
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.nfs.getattr_fh(fh)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.nfs.set_credentials(credentials)
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.readdir_fh(fh)
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        let file = self.nfs.open_fh(fh, flags)?;
+        self.revalidate(Some(FileKey::Fh(fh.to_vec())), file, flags)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.nfs.readlink(path)
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.nfs.lchmod(path, mode)
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.opendir(path)
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.nfs.mkdir(path, mode)
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        let file = self.nfs.create(path, flags, mode, how)?;
+        self.revalidate(None, file, flags | OFlag::O_TRUNC.bits() as u32)
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        self.nfs.mknod(path, mode, rdev)
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        self.nfs.rmdir(path)
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        self.nfs.unlink(path)
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        let file = self.nfs.open(path, flags)?;
+        self.revalidate(None, file, flags)
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        self.nfs.truncate(path, len)?;
+        match self.nfs.stat64(path) {
+            Ok(stat) => self.drop_inode((stat.dev, stat.ino)),
+            Err(_) => *self.pages.lock().unwrap() = Default::default(),
+        }
+        Ok(())
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.nfs.getxattr(path, name)
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.nfs.setxattr(path, name, value)
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.nfs.listxattrs(path)
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        self.nfs.removexattr(path, name)
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.nfs.getacl(path)
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        self.nfs.setacl(path, acl)
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.nfs.supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        let pages = self.pages.lock().unwrap();
+        CacheStats{
+            data_hits: self.hits.load(Ordering::SeqCst),
+            data_misses: self.misses.load(Ordering::SeqCst),
+            data_bytes: pages.memory.bytes,
+            ..self.nfs.cache_stats()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DataCacheFile {
+    cache: *const DataCache,
+    key: FileKey,
+    inode: Inode,
+    file: Box<dyn NFSFile>,
+}
+
+impl DataCacheFile {
+    fn cache(&self) -> &DataCache {
+        unsafe { &*self.cache }
+    }
+
+    // Reads as much of buffer as there is data for from offset on, taking whatever pages are cached from cache and
+    // reading each run of missing ones from server in one go.
+    fn read(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        let cache = self.cache();
+        let len = buffer.len() as u64;
+        let mut pos = 0;
+        // XXX: keep pages read for this call at hand, since budget may be too small to hold them all until copied
+        let mut fetched = HashMap::new();
+        while pos < len {
+            let index = (offset + pos) / PAGE_SIZE;
+            let page = match fetched.remove(&index).or_else(|| cache.get_page(&(self.key.clone(), index)).inspect(|_| { let _ = cache.hits.fetch_add(1, Ordering::SeqCst); })) {
+                Some(page) => page,
+                None => {
+                    let last = (offset + len - 1) / PAGE_SIZE;
+                    let mut count = 1;
+                    while index + count <= last && !cache.has_page(&(self.key.clone(), index + count)) {
+                        count += 1;
+                    }
+                    let _ = cache.misses.fetch_add(count, Ordering::SeqCst);
+                    let mut data = vec![0u8; (count * PAGE_SIZE) as usize];
+                    let read = self.file.pread_all_into(index * PAGE_SIZE, &mut data, check)? as usize;
+                    // XXX: only a short read means end of file, so only then may the last page be short, while a page
+                    //      for where a full read ends would make it look as if file ended there
+                    for (i, start) in (0..read).step_by(PAGE_SIZE as usize).enumerate() {
+                        let page = Arc::new(data[start..read.min(start + PAGE_SIZE as usize)].to_vec());
+                        cache.store_page((self.key.clone(), index + i as u64), page.clone());
+                        let _ = fetched.insert(index + i as u64, page);
+                    }
+                    fetched.remove(&index).unwrap_or_default()
+                },
+            };
+            let start = (offset + pos - index * PAGE_SIZE) as usize;
+            if start >= page.len() {
+                break; // XXX: end of file
+            }
+            let count = (page.len() - start).min((len - pos) as usize);
+            buffer[pos as usize..pos as usize + count].copy_from_slice(&page[start..start + count]);
+            pos += count as u64;
+            if page.len() < PAGE_SIZE as usize {
+                break; // XXX: end of file
+            }
+        }
+        Ok(pos)
+    }
+}
+
+impl NFSFile for DataCacheFile {
+    fn fstat64(&self) -> Result<NFSStat64> {
+        self.file.fstat64()
+    }
+
+    fn get_max_read_size(&self) -> u64 {
+        self.file.get_max_read_size()
+    }
+
+    fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
+        let count = buffer.len().min(count as usize);
+        Ok(self.read(offset, &mut buffer[..count], &|| Ok(()))? as u32)
+    }
+
+    fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
+        let res = self.file.pwrite(buffer, offset);
+        self.cache().drop_inode(self.inode);
+        res
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        self.file.lock(offset, len, exclusive, wait)?;
+        // XXX: whoever held the lock before may have changed the file meanwhile
+        self.cache().drop_inode(self.inode);
+        Ok(())
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.unlock(offset, len)
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        self.file.seek_data(offset)
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        self.file.seek_hole(offset)
+    }
+
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        let res = self.file.allocate(offset, len);
+        self.cache().drop_inode(self.inode);
+        res
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        let res = self.file.punch_hole(offset, len);
+        self.cache().drop_inode(self.inode);
+        res
+    }
+
+    fn pread_all_into(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        self.read(offset, buffer, check)
+    }
+
+    fn pwrite_all(&self, buffer: &[u8], offset: u64, check: &Check) -> Result<()> {
+        let res = self.file.pwrite_all(buffer, offset, check);
+        self.cache().drop_inode(self.inode);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock;
+
+    fn connect(options: &DataCacheOptions) -> Box<dyn NFS> {
+        DataCache::new(mock::NFS3::connect("nfs://localhost/export".into()), options).unwrap()
+    }
+
+    fn read_all(nfs: &mut Box<dyn NFS>, path: &str) -> Vec<u8> {
+        let file = nfs.open(path, OFlag::O_RDONLY.bits() as u32).unwrap();
+        let mut buffer = vec![0u8; file.fstat64().unwrap().size as usize];
+        let len = file.pread_all_into(0, &mut buffer, &|| Ok(())).unwrap();
+        buffer.truncate(len as usize);
+        buffer
+    }
+
+    #[test]
+    fn reads_are_served_from_cache_until_file_changes() {
+        let mut nfs = connect(&DataCacheOptions{max_bytes: 4 * PAGE_SIZE, ..Default::default()});
+        let content = read_all(&mut nfs, "/annar");
+        assert_eq!(content.len(), 123);
+        assert_eq!(read_all(&mut nfs, "/annar"), content);
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.data_hits, stats.data_misses, stats.data_bytes), (1, 1, 123));
+        let file = nfs.open("/annar", OFlag::O_RDWR.bits() as u32).unwrap();
+        let _ = file.pwrite(b"On", 0).unwrap();
+        assert_eq!(&read_all(&mut nfs, "/annar")[..10], b"On order t");
+        nfs.truncate("/annar", 2).unwrap();
+        assert_eq!(read_all(&mut nfs, "/annar"), b"On");
+        assert_eq!(nfs.cache_stats().data_misses, 3);
+    }
+
+    #[test]
+    fn reading_past_range_read_before_does_not_end_file_there() {
+        let mut nfs = connect(&DataCacheOptions{max_bytes: 4 * PAGE_SIZE, ..Default::default()});
+        let content: Vec<u8> = (0..2 * PAGE_SIZE + 10).map(|i| i as u8).collect();
+        nfs.create("/big", OFlag::O_RDWR.bits() as u32, 0o664, CreateMode::Unchecked).unwrap().pwrite_all(&content, 0, &|| Ok(())).unwrap();
+        let file = nfs.open("/big", OFlag::O_RDONLY.bits() as u32).unwrap();
+        let mut buffer = vec![0u8; PAGE_SIZE as usize];
+        assert_eq!(file.pread_all_into(0, &mut buffer, &|| Ok(())).unwrap(), PAGE_SIZE);
+        let mut buffer = vec![0u8; content.len()];
+        assert_eq!(file.pread_all_into(PAGE_SIZE, &mut buffer, &|| Ok(())).unwrap(), PAGE_SIZE + 10);
+        assert_eq!(&buffer[..(PAGE_SIZE + 10) as usize], &content[PAGE_SIZE as usize..]);
+        assert_eq!(read_all(&mut nfs, "/big"), content);
+    }
+
+    #[test]
+    fn pages_are_spilled_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("nfs-js-test-{}", std::process::id()));
+        let options = DataCacheOptions{max_bytes: PAGE_SIZE, spill_dir: Some(dir.to_str().unwrap().into()), spill_max_bytes: 4 * PAGE_SIZE};
+        let mut nfs = connect(&options);
+        let content: Vec<u8> = (0..3 * PAGE_SIZE + 10).map(|i| i as u8).collect();
+        nfs.create("/big", OFlag::O_RDWR.bits() as u32, 0o664, CreateMode::Unchecked).unwrap().pwrite_all(&content, 0, &|| Ok(())).unwrap();
+        assert_eq!(read_all(&mut nfs, "/big"), content);
+        assert_eq!(nfs.cache_stats().data_bytes, 10);
+        assert_eq!(read_all(&mut nfs, "/big"), content);
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.data_hits, stats.data_misses), (4, 4));
+        drop(nfs);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir(&dir);
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashSet;
+use std::io::Error;
+use std::sync::Mutex;
+
+use super::{Ace, CacheStats, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, Result};
+
+// At most this many file handles are remembered to be inside of root, after which they are all forgotten, so that
+// they have to be looked up by path again.
+const MAX_TRUSTED_FHS: usize = 65536;
+
+// Jail scopes an NFS implementation to a subdirectory of the export, so that "/" refers to that subdirectory and
+// no path or name can be used to reach anything outside of it. Since file handles say nothing about where entries they
+// refer to are, only those that were looked up through the jail itself are accepted.
+#[derive(Debug)]
+pub(super) struct Jail {
+    nfs: Box<dyn NFS>,
+    root: String,
+    trusted: Mutex<HashSet<Vec<u8>>>,
+}
+
+impl Jail {
+    pub(super) fn new(nfs: Box<dyn NFS>, root: &str) -> Result<Box<dyn NFS>> {
+        let root = format!("/{}", root.trim_matches('/'));
+        check_path(&root)?;
+        let mut jail = Jail{nfs, root, trusted: Mutex::new(HashSet::new())};
+        let _ = jail.nfs.opendir(format!("{}/", jail.root).as_str())?; // XXX: fail early if root does not exist or is not a directory
+        Ok(Box::new(jail))
+    }
+
+    fn jailed(&self, path: &str) -> Result<String> {
+        check_path(path)?;
+        Ok(format!("{}/{}", self.root, path.trim_start_matches('/')))
+    }
+
+    fn trust(&self, fh: Vec<u8>) -> Vec<u8> {
+        let mut trusted = self.trusted.lock().unwrap();
+        if trusted.len() >= MAX_TRUSTED_FHS {
+            trusted.clear();
+        }
+        let _ = trusted.insert(fh.clone());
+        fh
+    }
+
+    fn check_fh(&self, fh: &[u8]) -> Result<()> {
+        if !self.trusted.lock().unwrap().contains(fh) {
+            return Err(Error::new(std::io::ErrorKind::PermissionDenied, "file handle not known to be inside of root"));
+        }
+        Ok(())
+    }
+}
+
+fn outside_of_root() -> Error {
+    Error::new(std::io::ErrorKind::PermissionDenied, "path points outside of root")
+}
+
+fn check_path(path: &str) -> Result<()> {
+    if path.contains('\0') || path.split('/').any(|component| component == "..") {
+        return Err(outside_of_root());
+    }
+    Ok(())
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        return Err(outside_of_root());
+    }
+    Ok(())
+}
+
+impl NFS for Jail {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.nfs.access(self.jailed(path)?.as_str(), mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.nfs.stat64(self.jailed(path)?.as_str())
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(self.trust(self.nfs.lookup_fh(self.jailed(path)?.as_str())?))
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        check_name(name)?;
+        self.check_fh(dir_fh)?;
+        Ok(self.trust(self.nfs.lookup_in(dir_fh, name)?))
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.check_fh(fh)?;
+        self.nfs.getattr_fh(fh)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.nfs.set_credentials(credentials)
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.check_fh(fh)?;
+        self.nfs.readdir_fh(fh)
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.check_fh(fh)?;
+        self.nfs.open_fh(fh, flags)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        let target = self.nfs.readlink(self.jailed(path)?.as_str())?;
+        if !target.starts_with('/') {
+            return Ok(target);
+        }
+        // XXX: absolute targets are relative to the export, so translate them to be relative to root instead
+        match target.strip_prefix(self.root.as_str()) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => Ok(format!("/{}", rest.trim_start_matches('/'))),
+            _ => Err(outside_of_root()),
+        }
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.nfs.lchmod(self.jailed(path)?.as_str(), mode)
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        let path = self.jailed(path)?;
+        self.nfs.opendir(path.as_str())
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.nfs.mkdir(self.jailed(path)?.as_str(), mode)
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        let path = self.jailed(path)?;
+        self.nfs.create(path.as_str(), flags, mode, how)
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        self.nfs.mknod(self.jailed(path)?.as_str(), mode, rdev)
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        self.nfs.rmdir(self.jailed(path)?.as_str())
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        self.nfs.unlink(self.jailed(path)?.as_str())
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        let path = self.jailed(path)?;
+        self.nfs.open(path.as_str(), flags)
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        self.nfs.truncate(self.jailed(path)?.as_str(), len)
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.nfs.getxattr(self.jailed(path)?.as_str(), name)
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.nfs.setxattr(self.jailed(path)?.as_str(), name, value)
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.nfs.listxattrs(self.jailed(path)?.as_str())
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        self.nfs.removexattr(self.jailed(path)?.as_str(), name)
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.nfs.getacl(self.jailed(path)?.as_str())
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        self.nfs.setacl(self.jailed(path)?.as_str(), acl)
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.nfs.supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.nfs.cache_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock;
+
+    #[test]
+    fn jail_prefixes_paths_with_root() {
+        let jail = Jail::new(mock::NFS3::connect("nfs://localhost/export".into()), "first").unwrap();
+        assert!(jail.stat64("/comment").is_ok());
+        assert!(jail.stat64("/annar").is_err());
+        assert!(Jail::new(mock::NFS3::connect("nfs://localhost/export".into()), "missing").is_err());
+    }
+
+    #[test]
+    fn jail_rejects_paths_outside_of_root() {
+        let mut jail = Jail::new(mock::NFS3::connect("nfs://localhost/export".into()), "/first/").unwrap();
+        assert_eq!(jail.stat64("/../annar").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(jail.opendir("/..").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(jail.lookup_in(b"mockfh:/first", "..").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn jail_rejects_file_handles_not_looked_up_through_it() {
+        let mut jail = Jail::new(mock::NFS3::connect("nfs://localhost/export".into()), "first").unwrap();
+        let crafted = b"mockfh:/annar";
+        assert_eq!(jail.getattr_fh(crafted).unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(jail.open_fh(crafted, 0).unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(jail.readdir_fh(b"mockfh:/").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(jail.lookup_in(b"mockfh:/", "annar").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+        let root = jail.lookup_fh("/").unwrap();
+        let comment = jail.lookup_in(&root, "comment").unwrap();
+        assert!(jail.getattr_fh(&comment).is_ok());
+        assert!(jail.readdir_fh(&root).is_ok());
+    }
+
+    #[test]
+    fn jail_translates_absolute_symlinks() {
+        let links = [("/first/inside", "/first/comment"), ("/first/outside", "/annar"), ("/first/relative", "comment")];
+        let jail = Jail::new(mock::NFS3::connect_with_links("nfs://localhost/export".into(), &links), "first").unwrap();
+        assert_eq!(jail.readlink("/inside").unwrap(), "/comment");
+        assert_eq!(jail.readlink("/relative").unwrap(), "comment");
+        assert_eq!(jail.readlink("/outside").unwrap_err().kind(), std::io::ErrorKind::PermissionDenied);
+    }
+}
@@ -15,26 +15,61 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use core::fmt::Debug;
+use std::collections::VecDeque;
+use std::io::Error;
+use std::os::unix::ffi::OsStringExt;
 use std::path::Path;
 use std::sync::{Arc, RwLock};
-use nix::sys::stat::Mode;
+use nix::sys::stat::{major, makedev, minor, Mode, SFlag};
 use nix::fcntl::OFlag;
+use nix::unistd::AccessFlags;
 use libnfs::Nfs;
 
-use super::{NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, Result, Time};
+use super::{ACCESS_DELETE, ACCESS_EXECUTE, ACCESS_EXTEND, ACCESS_LOOKUP, ACCESS_MODIFY, ACCESS_READ};
+use super::{makerdev, name_from_bytes, rdev_major, rdev_minor, Ace, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, NFSEntryType, Result, Time};
+
+const MAX_CONTEXTS: usize = 8;
 
 pub(super) struct NFS3 {
     nfs: Arc<RwLock<Nfs>>,
+    url: String,
+    timeout_ms: Option<u32>,
+    credentials: Credentials,
+    contexts: VecDeque<(Credentials, Arc<RwLock<Nfs>>)>,
 }
 
 impl NFS3 {
-    pub(super) fn connect(url: String) -> Result<Box<dyn NFS>> {
+    pub(super) fn connect(url: String, credentials: &Credentials, timeout_ms: Option<u32>) -> Result<Box<dyn NFS>> {
+        let nfs = Self::mount(&url, credentials, timeout_ms)?;
+        let contexts = VecDeque::from([(credentials.clone(), nfs.clone())]);
+        Ok(Box::new(NFS3{nfs, url, timeout_ms, credentials: credentials.clone(), contexts}))
+    }
+
+    fn mount(url: &str, credentials: &Credentials, timeout_ms: Option<u32>) -> Result<Arc<RwLock<Nfs>>> {
+        check_credentials(credentials)?;
         let mut nfs = Nfs::new()?;
-        let _ = nfs.parse_url_mount(url.as_str())?;
-        Ok(Box::new(NFS3{nfs: Arc::new(RwLock::new(nfs))}))
+        if let Some(timeout_ms) = timeout_ms {
+            nfs.set_timeout(timeout_ms.min(i32::MAX as u32) as i32)?;
+        }
+        let _ = nfs.parse_url_mount(credentials.apply_to_url(url).as_str())?;
+        Ok(Arc::new(RwLock::new(nfs)))
     }
 }
 
+// Returns rdev kept the way NFS does given one libnfs made of specdata1 and specdata2 the way the local system makes dev,
+// which is the reverse of what mknod does.
+fn rdev_from_dev(dev: u64) -> u64 {
+    makerdev(major(dev) as u32, minor(dev) as u32)
+}
+
+fn check_credentials(credentials: &Credentials) -> Result<()> {
+    if credentials.aux_gids.as_ref().is_some_and(|aux_gids| !aux_gids.is_empty()) {
+        // XXX: libnfs only sends uid and gid in AUTH_SYS, so connect fails over to nfs-rs when auxiliary gids are given
+        return Err(Error::new(std::io::ErrorKind::Unsupported, "auxiliary gids not supported by libnfs"));
+    }
+    Ok(())
+}
+
 impl Debug for NFS3 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NFS3").finish()
@@ -42,9 +77,32 @@ impl Debug for NFS3 {
 }
 
 impl NFS for NFS3 {
-    fn access(&self, path: &str, mode: u32) -> Result<()> {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        // XXX: libnfs only tells whether all of R_OK, W_OK and X_OK asked for are granted, so ask for each one mode needs
+        //      separately and grant those ACCESS_* bits that map to it, out of those that apply to kind of entry
         let my_nfs = self.nfs.write().unwrap();
-        my_nfs.access(Path::new(path), mode as i32).map(|_| ())
+        let is_dir = my_nfs.stat64(Path::new(path))?.nfs_mode & SFlag::S_IFMT.bits() as u64 == SFlag::S_IFDIR.bits() as u64;
+        let mode = mode & if is_dir {
+            ACCESS_READ | ACCESS_LOOKUP | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE
+        } else {
+            ACCESS_READ | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_EXECUTE
+        };
+        let mut granted = 0;
+        for (bits, flag) in [
+            (ACCESS_READ, AccessFlags::R_OK),
+            (ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE, AccessFlags::W_OK),
+            (ACCESS_LOOKUP | ACCESS_EXECUTE, AccessFlags::X_OK),
+        ] {
+            if mode & bits == 0 {
+                continue;
+            }
+            match my_nfs.access(Path::new(path), flag.bits()) {
+                Ok(_) => granted |= mode & bits,
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(granted)
     }
 
     fn stat64(&self, path: &str) -> Result<NFSStat64> {
@@ -56,7 +114,7 @@ impl NFS for NFS3 {
             nlink: res.nfs_nlink,
             uid: res.nfs_uid,
             gid: res.nfs_gid,
-            rdev: res.nfs_rdev,
+            rdev: rdev_from_dev(res.nfs_rdev),
             size: res.nfs_size,
             used: res.nfs_used,
             blksize: res.nfs_blksize,
@@ -70,6 +128,53 @@ impl NFS for NFS3 {
         })
     }
 
+    fn lookup_fh(&self, _path: &str) -> Result<Vec<u8>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "file handles not supported by libnfs"))
+    }
+
+    fn lookup_in(&self, _dir_fh: &[u8], _name: &str) -> Result<Vec<u8>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "file handles not supported by libnfs"))
+    }
+
+    fn getattr_fh(&self, _fh: &[u8]) -> Result<NFSStat64> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "file handles not supported by libnfs"))
+    }
+
+    fn readdir_fh(&mut self, _fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "file handles not supported by libnfs"))
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        if *credentials == self.credentials {
+            return Ok(());
+        }
+        // XXX: libnfs sets credentials per context and files opened in a context keep using it, so keep the most
+        //      recently used contexts, one per set of credentials, instead of changing credentials of open files
+        let nfs = match self.contexts.iter().position(|(creds, _)| creds == credentials) {
+            Some(index) => self.contexts.remove(index).unwrap().1,
+            None => Self::mount(&self.url, credentials, self.timeout_ms)?,
+        };
+        if self.contexts.len() >= MAX_CONTEXTS {
+            let _ = self.contexts.pop_front();
+        }
+        self.contexts.push_back((credentials.clone(), nfs.clone()));
+        self.nfs = nfs;
+        self.credentials = credentials.clone();
+        Ok(())
+    }
+
+    fn open_fh(&mut self, _fh: &[u8], _flags: u32) -> Result<Box<dyn NFSFile>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "file handles not supported by libnfs"))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        let my_nfs = self.nfs.write().unwrap();
+        let mut buf = vec![0u8; 4096]; // XXX: PATH_MAX
+        my_nfs.readlink(Path::new(path), &mut buf)?;
+        let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+        Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+    }
+
     fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
         let my_nfs = self.nfs.write().unwrap();
         my_nfs.lchmod(Path::new(path), Mode::from_bits_truncate((mode as u16).into()))
@@ -81,17 +186,38 @@ impl NFS for NFS3 {
         Ok(Box::new(NFSDirectory3{dir}))
     }
 
-    fn mkdir(&self, path: &str, _mode: u32) -> Result<()> { // FIXME: mode
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
         let my_nfs = self.nfs.write().unwrap();
-        my_nfs.mkdir(Path::new(path))
+        my_nfs.mkdir(Path::new(path))?;
+        // XXX: libnfs bindings only expose mkdir with libnfs' default mode, so set requested one afterwards
+        //      and remove directory again if that fails, rather than leave it behind with the wrong mode
+        my_nfs.lchmod(Path::new(path), Mode::from_bits_truncate((mode as u16).into())).inspect_err(|_| {
+            let _ = my_nfs.rmdir(Path::new(path));
+        })
     }
 
-    fn create(&mut self, path: &str, flags: u32, mode: u32) -> Result<Box<dyn NFSFile>> {
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
         let mut my_nfs = self.nfs.write().unwrap();
-        let file = my_nfs.create(Path::new(path), OFlag::from_bits_truncate(flags as i32), Mode::from_bits_truncate((mode as u16).into()))?;
+        let mut flags = OFlag::from_bits_truncate(flags as i32);
+        match how {
+            CreateMode::Unchecked => {},
+            CreateMode::Guarded => flags |= OFlag::O_EXCL,
+            // XXX: libnfs sends GUARDED for O_EXCL and takes no verifier, so callers are told to use Guarded instead
+            CreateMode::Exclusive(_) => return Err(Error::new(std::io::ErrorKind::Unsupported, "exclusive create not supported by libnfs")),
+        }
+        let file = my_nfs.create(Path::new(path), flags, Mode::from_bits_truncate((mode as u16).into()))?;
         Ok(Box::new(NFSFile3{file}))
     }
 
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        let my_nfs = self.nfs.write().unwrap();
+        // XXX: libnfs splits dev into major and minor the way the local system does, so hand it one made that way, which
+        // must fit in the int libnfs takes rather than have major or minor silently cut short
+        let dev = u32::try_from(makedev(rdev_major(rdev) as u64, rdev_minor(rdev) as u64))
+            .map_err(|_| Error::new(std::io::ErrorKind::InvalidInput, "device numbers too large for libnfs"))?;
+        my_nfs.mknod(Path::new(path), mode as i32, dev as i32)
+    }
+
     fn rmdir(&self, path: &str) -> Result<()> {
         let my_nfs = self.nfs.write().unwrap();
         my_nfs.rmdir(Path::new(path))
@@ -112,6 +238,30 @@ impl NFS for NFS3 {
         let my_nfs = self.nfs.write().unwrap();
         my_nfs.truncate(Path::new(path), len)
     }
+
+    fn getxattr(&self, _path: &str, _name: &str) -> Result<Vec<u8>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by libnfs"))
+    }
+
+    fn setxattr(&self, _path: &str, _name: &str, _value: &[u8]) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by libnfs"))
+    }
+
+    fn listxattrs(&self, _path: &str) -> Result<Vec<String>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by libnfs"))
+    }
+
+    fn removexattr(&self, _path: &str, _name: &str) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by libnfs"))
+    }
+
+    fn getacl(&self, _path: &str) -> Result<Vec<Ace>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "ACLs not supported by libnfs"))
+    }
+
+    fn setacl(&self, _path: &str, _acl: &[Ace]) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "ACLs not supported by libnfs"))
+    }
 }
 
 pub struct NFSDirectory3 {
@@ -130,26 +280,31 @@ impl Iterator for NFSDirectory3 {
     type Item = Result<NFSDirEntry>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.dir.next().map(|res| res.map(|entry| NFSDirEntry{
-            path: entry.path.into_os_string().into_string().unwrap(),
-            inode: entry.inode,
-            d_type: (entry.d_type as u32).into(),
-            mode: entry.mode.bits() as u32,
-            size: entry.size,
-            used: entry.used,
-            atime: Time{seconds: entry.atime.tv_sec as u32, nseconds: entry.atime_nsec},
-            mtime: Time{seconds: entry.mtime.tv_sec as u32, nseconds: entry.mtime_nsec},
-            ctime: Time{seconds: entry.ctime.tv_sec as u32, nseconds: entry.ctime_nsec},
-            uid: entry.uid,
-            gid: entry.gid,
-            nlink: entry.nlink,
-            dev: entry.dev,
-            rdev: entry.rdev,
-            blksize: entry.blksize,
-            blocks: entry.blocks,
-            atime_nsec: entry.atime_nsec,
-            mtime_nsec: entry.mtime_nsec,
-            ctime_nsec: entry.ctime_nsec,
+        self.dir.next().map(|res| res.map(|entry| {
+            let (path, raw_path) = name_from_bytes(entry.path.into_os_string().into_vec());
+            NFSDirEntry{
+                path,
+                raw_path,
+                inode: entry.inode,
+                // XXX: entry types libnfs does not know of are still listed, rather than failing whole listing
+                d_type: NFSEntryType::try_from(entry.d_type as u32).unwrap_or(NFSEntryType::Unknown),
+                mode: entry.mode.bits() as u32,
+                size: entry.size,
+                used: entry.used,
+                atime: Time{seconds: entry.atime.tv_sec as u32, nseconds: entry.atime_nsec},
+                mtime: Time{seconds: entry.mtime.tv_sec as u32, nseconds: entry.mtime_nsec},
+                ctime: Time{seconds: entry.ctime.tv_sec as u32, nseconds: entry.ctime_nsec},
+                uid: entry.uid,
+                gid: entry.gid,
+                nlink: entry.nlink,
+                dev: entry.dev,
+                rdev: rdev_from_dev(entry.rdev),
+                blksize: entry.blksize,
+                blocks: entry.blocks,
+                atime_nsec: entry.atime_nsec,
+                mtime_nsec: entry.mtime_nsec,
+                ctime_nsec: entry.ctime_nsec,
+            }
         }))
     }
 }
@@ -173,7 +328,7 @@ impl NFSFile for NFSFile3 {
             nlink: res.nfs_nlink,
             uid: res.nfs_uid,
             gid: res.nfs_gid,
-            rdev: res.nfs_rdev,
+            rdev: rdev_from_dev(res.nfs_rdev),
             size: res.nfs_size,
             used: res.nfs_used,
             blksize: res.nfs_blksize,
@@ -198,4 +353,13 @@ impl NFSFile for NFSFile3 {
     fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
         self.file.pwrite(buffer, offset).map(|res| res as u32)
     }
+
+    fn lock(&self, _offset: u64, _len: u64, _exclusive: bool, _wait: bool) -> Result<()> {
+        // XXX: libnfs bindings do not expose NLM
+        Err(Error::new(std::io::ErrorKind::Unsupported, "byte-range locks not supported by libnfs"))
+    }
+
+    fn unlock(&self, _offset: u64, _len: u64) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "byte-range locks not supported by libnfs"))
+    }
 }
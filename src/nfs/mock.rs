@@ -15,13 +15,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::collections::{BTreeSet, BTreeMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Error;
-use std::path::Path;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
 use bytes::BufMut;
+use nix::sys::stat::SFlag;
 use regex::Regex;
 
-use super::{NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, NFSEntryType, Result, Time};
+use super::{ACCESS_DELETE, ACCESS_EXECUTE, ACCESS_EXTEND, ACCESS_LOOKUP, ACCESS_MODIFY, ACCESS_READ};
+use super::{ACE_FLAG_INHERIT_ONLY, ACE_MASK_APPEND_DATA, ACE_MASK_DELETE_CHILD, ACE_MASK_EXECUTE, ACE_MASK_READ_DATA, ACE_MASK_WRITE_DATA};
+use super::{ACE_TYPE_ALLOW, ACE_TYPE_DENY, ACE_WHO_EVERYONE, ACE_WHO_GROUP, ACE_WHO_OWNER};
+use super::{Ace, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, NFSEntryType, Result, Time};
 use crate::get_parent_path_and_name;
 
 fn get_rsize_from_url(url: &str) -> u32 {
@@ -33,80 +39,405 @@ fn get_rsize_from_url(url: &str) -> u32 {
         )
 }
 
+const MOCK_FH_PREFIX: &[u8] = b"mockfh:";
+
+fn get_path_from_fh(fh: &[u8]) -> Result<&str> {
+    fh.strip_prefix(MOCK_FH_PREFIX)
+        .and_then(|path| std::str::from_utf8(path).ok())
+        .ok_or(Error::new(std::io::ErrorKind::InvalidInput, "invalid file handle"))
+}
+
+fn get_inode_from_path(path: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    path.trim_end_matches('/').hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug)]
 struct Mocks {
     dirs: BTreeSet<String>,
-    files: BTreeMap<String, Vec<u8>>
+    files: BTreeMap<String, Vec<u8>>,
+    links: BTreeMap<String, String>,
+    locks: Vec<MockLock>,
+    verifiers: BTreeMap<String, u64>,
+    modes: BTreeMap<String, u32>,
+    extents: BTreeMap<String, BTreeMap<u64, u64>>,
+    xattrs: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
+    acls: BTreeMap<String, Vec<Ace>>,
+    specials: BTreeMap<String, (u32, u64)>, // file type bits of mode and rdev
+    owners: BTreeMap<String, (u32, u32)>, // uid and gid
+    getattrs: AtomicUsize, // GETATTRs served, for tests to tell how many reached the server
+    dir_changes: u32, // XXX: entries created or removed in any directory, which changes ctime of all of them
+}
+
+impl Mocks {
+    // Returns extents of data in file at path, which are only tracked once file gets any holes, so until then all of
+    // it is data.
+    fn extents_mut(&mut self, path: &str) -> &mut BTreeMap<u64, u64> {
+        let len = self.files.get(path).map_or(0, |contents| contents.len() as u64);
+        self.extents.entry(path.to_string()).or_insert_with(|| {
+            let mut extents = BTreeMap::new();
+            add_extent(&mut extents, 0, len);
+            extents
+        })
+    }
+
+    fn get_used(&self, path: &str, size: u64) -> u64 {
+        self.extents.get(path).map_or(size, |extents| extents.iter().map(|(start, end)| (*end).min(size).saturating_sub(*start)).sum())
+    }
+
+    fn new_file(&mut self, path: &str) {
+        let _ = self.files.insert(path.to_string(), Vec::new());
+        let _ = self.extents.insert(path.to_string(), BTreeMap::new());
+    }
+
+    // Returns permission bits entry at path was created with, or given default for entries that were there from start.
+    fn get_mode(&self, path: &str, default: u32) -> u32 {
+        self.modes.get(path.trim_end_matches('/')).copied().unwrap_or(default)
+    }
+
+    // Returns uid and gid of whoever created entry at path, or those of root for entries that were there from start.
+    fn get_owner(&self, path: &str) -> (u32, u32) {
+        self.owners.get(path.trim_end_matches('/')).copied().unwrap_or_default()
+    }
+}
+
+// Returns ACL equivalent to mode, which is what entries without ACL of their own get, with deny entries for owner and
+// group so that later entries (which apply to them too) grant no more than owner and group bits do.
+fn mode_to_acl(mode: u32, is_dir: bool) -> Vec<Ace> {
+    let to_mask = |bits: u32| {
+        let mut mask = 0;
+        if bits & 0o4 != 0 {
+            mask |= ACE_MASK_READ_DATA;
+        }
+        if bits & 0o2 != 0 {
+            mask |= ACE_MASK_WRITE_DATA | ACE_MASK_APPEND_DATA | if is_dir { ACE_MASK_DELETE_CHILD } else { 0 };
+        }
+        if bits & 0o1 != 0 {
+            mask |= ACE_MASK_EXECUTE;
+        }
+        mask
+    };
+    let owner = to_mask(mode >> 6 & 0o7);
+    let mut acl = vec![Ace{type_: ACE_TYPE_ALLOW, flags: 0, mask: owner, who: ACE_WHO_OWNER.into()}];
+    let denied = to_mask(0o7) & !owner;
+    if denied != 0 {
+        acl.push(Ace{type_: ACE_TYPE_DENY, flags: 0, mask: denied, who: ACE_WHO_OWNER.into()});
+    }
+    let group = to_mask(mode >> 3 & 0o7);
+    acl.push(Ace{type_: ACE_TYPE_ALLOW, flags: 0, mask: group, who: ACE_WHO_GROUP.into()});
+    let denied = to_mask(0o7) & !group;
+    if denied != 0 {
+        acl.push(Ace{type_: ACE_TYPE_DENY, flags: 0, mask: denied, who: ACE_WHO_GROUP.into()});
+    }
+    acl.push(Ace{type_: ACE_TYPE_ALLOW, flags: 0, mask: to_mask(mode & 0o7), who: ACE_WHO_EVERYONE.into()});
+    acl
+}
+
+// Returns ACE mask bits that given ACCESS_* bits take, the way Linux maps them.
+fn access_to_ace_mask(access: u32, is_dir: bool) -> u32 {
+    let mut mask = 0;
+    if access & ACCESS_READ != 0 {
+        mask |= ACE_MASK_READ_DATA;
+    }
+    if access & (ACCESS_LOOKUP | ACCESS_EXECUTE) != 0 {
+        mask |= ACE_MASK_EXECUTE;
+    }
+    if access & ACCESS_MODIFY != 0 {
+        mask |= ACE_MASK_WRITE_DATA;
+    }
+    if access & ACCESS_EXTEND != 0 {
+        mask |= ACE_MASK_APPEND_DATA;
+    }
+    if access & ACCESS_DELETE != 0 && is_dir {
+        mask |= ACE_MASK_DELETE_CHILD;
+    }
+    mask
+}
+
+// Evaluates acl for mask as in RFC 7530, section 6.2.1, for caller who is_owner and/or in_group of entry, where only
+// special who of OWNER@, GROUP@ and EVERYONE@ apply.
+fn is_granted(acl: &[Ace], mask: u32, is_owner: bool, in_group: bool) -> bool {
+    let mut undecided = mask;
+    for ace in acl {
+        let applies = match ace.who.as_str() {
+            ACE_WHO_OWNER => is_owner,
+            ACE_WHO_GROUP => in_group,
+            ACE_WHO_EVERYONE => true,
+            _ => false,
+        };
+        if ace.flags & ACE_FLAG_INHERIT_ONLY != 0 || !applies {
+            continue;
+        }
+        match ace.type_ {
+            ACE_TYPE_ALLOW => undecided &= !ace.mask,
+            ACE_TYPE_DENY if undecided & ace.mask != 0 => return false,
+            _ => (),
+        }
+    }
+    undecided == 0
+}
+
+// MockLock is byte-range lock held on path by open file identified by owner, covering start up to but excluding end.
+#[derive(Debug, Clone)]
+struct MockLock {
+    path: String,
+    owner: u64,
+    start: u64,
+    end: u64,
+    exclusive: bool,
+}
+
+// Adds start up to end to extents, merging it with those it overlaps or touches.
+fn add_extent(extents: &mut BTreeMap<u64, u64>, start: u64, end: u64) {
+    if start >= end {
+        return;
+    }
+    let (mut start, mut end) = (start, end);
+    let touching: Vec<(u64, u64)> = extents.range(..=end).filter(|(_, e)| **e >= start).map(|(s, e)| (*s, *e)).collect();
+    for (s, e) in touching {
+        let _ = extents.remove(&s);
+        start = start.min(s);
+        end = end.max(e);
+    }
+    let _ = extents.insert(start, end);
+}
+
+// Removes start up to end from extents, splitting those that cover more than that.
+fn remove_extent(extents: &mut BTreeMap<u64, u64>, start: u64, end: u64) {
+    if start >= end {
+        return;
+    }
+    let overlapping: Vec<(u64, u64)> = extents.range(..end).filter(|(_, e)| **e > start).map(|(s, e)| (*s, *e)).collect();
+    for (s, e) in overlapping {
+        let _ = extents.remove(&s);
+        if s < start {
+            let _ = extents.insert(s, start);
+        }
+        if end < e {
+            let _ = extents.insert(end, e);
+        }
+    }
+}
+
+static NEXT_LOCK_OWNER: AtomicU64 = AtomicU64::new(1);
+
+fn get_lock_range(offset: u64, len: u64) -> (u64, u64) {
+    (offset, if len == 0 { u64::MAX } else { offset.saturating_add(len) })
+}
+
+// Removes given range from locks held by owner on path, splitting those that cover more than that.
+fn release_lock_range(locks: &mut Vec<MockLock>, path: &str, owner: u64, start: u64, end: u64) {
+    let mut kept = Vec::with_capacity(locks.len());
+    for lock in locks.drain(..) {
+        if lock.path != path || lock.owner != owner || lock.end <= start || end <= lock.start {
+            kept.push(lock);
+            continue;
+        }
+        if lock.start < start {
+            kept.push(MockLock{end: start, ..lock.clone()});
+        }
+        if end < lock.end {
+            kept.push(MockLock{start: end, ..lock});
+        }
+    }
+    *locks = kept;
+}
+
+// Server holds state of mock server, which is shared by all connections made to it.
+#[derive(Clone, Debug)]
+pub(super) struct Server(Arc<RwLock<Mocks>>);
+
+impl Server {
+    pub(super) fn new() -> Self {
+        let mut mocks = Mocks{dirs: BTreeSet::new(), files: BTreeMap::new(), links: BTreeMap::new(), locks: Vec::new(), verifiers: BTreeMap::new(), modes: BTreeMap::new(), extents: BTreeMap::new(), xattrs: BTreeMap::new(), acls: BTreeMap::new(), specials: BTreeMap::new(), owners: BTreeMap::new(), getattrs: AtomicUsize::new(0), dir_changes: 0};
+        let _ = mocks.dirs.insert("/first/".into());
+        let _ = mocks.dirs.insert("/quatre/".into());
+        let _ = mocks.files.insert("/3".into(), Vec::new());
+        let _ = mocks.files.insert("/annar".into(), "In order to make sure that this file is exactly 123 bytes in size, I have written this text while watching its chars count.".as_bytes().to_vec());
+        let _ = mocks.files.insert("/first/comment".into(), Vec::new());
+        let _ = mocks.files.insert("/quatre/points".into(), Vec::new());
+        Server(Arc::new(RwLock::new(mocks)))
+    }
+
+    #[cfg(test)]
+    pub(super) fn getattrs(&self) -> usize {
+        self.0.read().unwrap().getattrs.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug)]
 pub(super) struct NFS3 {
     mocks: Arc<RwLock<Mocks>>,
     rsize: u32,
+    credentials: Credentials,
+    broken: bool,
 }
 
 impl NFS3 {
+    #[cfg(test)]
     pub(super) fn connect(url: String) -> Box<dyn NFS> {
+        Self::connect_to(url, &Server::new())
+    }
+
+    pub(super) fn connect_to(url: String, server: &Server) -> Box<dyn NFS> {
+        Box::new(Self::new(url, server))
+    }
+
+    fn new(url: String, server: &Server) -> Self {
         const MAXIMUM_READ_SIZE: u32 = 4194304; // XXX: according to libnfs, 4 MiB is the maximum
         const MINIMUM_READ_SIZE: u32 = 8192; // XXX: according to libnfs, 8 KiB is the minimum
         let rsize = get_rsize_from_url(&url).min(MAXIMUM_READ_SIZE).max(MINIMUM_READ_SIZE);
-        let mut mocks = Mocks{dirs: BTreeSet::new(), files: BTreeMap::new()};
-        let _ = mocks.dirs.insert("/first/".into());
-        let _ = mocks.dirs.insert("/quatre/".into());
-        let _ = mocks.files.insert("/3".into(), Vec::new());
-        let _ = mocks.files.insert("/annar".into(), "In order to make sure that this file is exactly 123 bytes in size, I have written this text while watching its chars count.".as_bytes().to_vec());
-        let _ = mocks.files.insert("/first/comment".into(), Vec::new());
-        let _ = mocks.files.insert("/quatre/points".into(), Vec::new());
-        Box::new(NFS3{mocks: Arc::new(RwLock::new(mocks)), rsize})
+        NFS3{mocks: server.0.clone(), rsize, credentials: Default::default(), broken: false}
     }
-}
 
-impl NFS for NFS3 {
-    fn access(&self, path: &str, mode: u32) -> Result<()> {
-        let p = Path::new(path);
-        if let Some(name) = p.file_name() {
-            if (name != "3" && name != "quatre") || mode & 0o222 != 0 {
-                return Ok(());
-            }
-        }
-        Err(Error::new(std::io::ErrorKind::PermissionDenied, "permission denied"))
+    #[cfg(test)]
+    pub(super) fn connect_broken(url: String) -> Box<dyn NFS> {
+        Box::new(NFS3{broken: true, ..Self::new(url, &Server::new())})
     }
 
-    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+    // Returns attributes of entry at path, like GETATTR does, for operations that need them without sending one.
+    fn getattr(&self, path: &str) -> Result<NFSStat64> {
+        self.check_connection()?;
         let mocks = &self.mocks.read().unwrap();
         let size = if let Some(c) = mocks.files.get(&path.to_string()) {
             Some(c.len() as u64)
         } else {
             None
         };
-        let mode = if size.is_some() {
-            if path == "/3" { 0o444 } else { 0o664 }
+        let special = mocks.specials.get(path).copied();
+        if size.is_none() && special.is_none() && path != "/" && !mocks.dirs.contains(path) && !mocks.dirs.contains(&(path.to_string() + "/")) {
+            return Err(Error::new(std::io::ErrorKind::NotFound, "not found"));
+        }
+        let mode = if let Some((fmt, _)) = special {
+            fmt as u64 | mocks.get_mode(path, 0o644) as u64
+        } else if size.is_some() {
+            SFlag::S_IFREG.bits() as u64 | mocks.get_mode(path, if path == "/3" { 0o444 } else { 0o664 }) as u64
         } else {
-            if path == "/quatre" || path == "/quatre/" { 0o555 } else { 0o775 }
+            SFlag::S_IFDIR.bits() as u64 | mocks.get_mode(path, if path == "/quatre" || path == "/quatre/" { 0o555 } else { 0o775 }) as u64
         };
+        let (uid, gid) = mocks.get_owner(path);
+        let dir_changes = if size.is_none() && special.is_none() { mocks.dir_changes } else { 0 };
 
         Ok(NFSStat64{
             dev: Default::default(),
-            ino: Default::default(),
+            ino: get_inode_from_path(path),
             mode,
             nlink: Default::default(),
-            uid: Default::default(),
-            gid: Default::default(),
-            rdev: Default::default(),
+            uid: uid.into(),
+            gid: gid.into(),
+            rdev: special.map_or(0, |(_, rdev)| rdev),
             size: size.unwrap_or_default(),
-            used: Default::default(),
+            used: size.map_or(0, |size| mocks.get_used(path, size)),
             blksize: Default::default(),
             blocks: Default::default(),
-            atime: 1658159058723,
-            mtime: 1658159058723,
-            ctime: 1658159058720,
-            atime_nsec: Default::default(),
-            mtime_nsec: Default::default(),
-            ctime_nsec: Default::default(),
+            atime: 1658159058,
+            mtime: 1658159058,
+            ctime: 1658159058,
+            atime_nsec: 723000000,
+            mtime_nsec: 723000000,
+            ctime_nsec: 720000000 + u64::from(dir_changes),
         })
     }
 
-    fn lchmod(&self, _path: &str, _mode: u32) -> Result<()> {
+    fn check_connection(&self) -> Result<()> {
+        if self.broken {
+            return Err(Error::new(std::io::ErrorKind::BrokenPipe, "broken pipe"));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(super) fn connect_many(url: String, count: usize) -> Vec<Box<dyn NFS>> {
+        let server = Server::new();
+        (0..count).map(|_| Self::connect_to(url.clone(), &server)).collect()
+    }
+
+    #[cfg(test)]
+    pub(super) fn connect_with_links(url: String, links: &[(&str, &str)]) -> Box<dyn NFS> {
+        let nfs = Self::new(url, &Server::new());
+        for (path, target) in links {
+            let _ = nfs.mocks.write().unwrap().links.insert(path.to_string(), target.to_string());
+        }
+        Box::new(nfs)
+    }
+
+    // Returns uid and gid that mock entries get created with, which are those of root unless credentials say otherwise.
+    fn get_caller(&self) -> (u32, u32) {
+        (self.credentials.uid.unwrap_or_default(), self.credentials.gid.unwrap_or_default())
+    }
+}
+
+impl NFS for NFS3 {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        let stat = self.getattr(path)?;
+        let is_dir = stat.mode & SFlag::S_IFMT.bits() as u64 == SFlag::S_IFDIR.bits() as u64;
+        let acl = self.getacl(path)?;
+        let (uid, gid) = self.get_caller();
+        let is_owner = stat.uid == uid as u64;
+        let in_group = stat.gid == gid as u64 || self.credentials.aux_gids.iter().flatten().any(|aux_gid| stat.gid == *aux_gid as u64);
+        let applicable = if is_dir {
+            ACCESS_READ | ACCESS_LOOKUP | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE
+        } else {
+            ACCESS_READ | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_EXECUTE
+        };
+        let granted = (0..6).map(|bit| 1 << bit)
+            .filter(|bit| mode & applicable & bit != 0 && is_granted(&acl, access_to_ace_mask(*bit, is_dir), is_owner, in_group))
+            .fold(0, |granted, bit| granted | bit);
+        Ok(granted)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        let _ = self.mocks.read().unwrap().getattrs.fetch_add(1, Ordering::SeqCst);
+        self.getattr(path)
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        let _ = self.getattr(path)?;
+        Ok([MOCK_FH_PREFIX, path.as_bytes()].concat())
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        let dir_path = get_path_from_fh(dir_fh)?;
+        let path = format!("{}/{}", dir_path.trim_end_matches('/'), name);
+        let is_dir = self.mocks.read().unwrap().dirs.contains(&(path.clone() + "/"));
+        self.lookup_fh(if is_dir { path + "/" } else { path }.as_str())
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        let _ = self.mocks.read().unwrap().getattrs.fetch_add(1, Ordering::SeqCst);
+        self.getattr(get_path_from_fh(fh)?)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.credentials = credentials.clone();
+        Ok(())
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        let path = get_path_from_fh(fh)?.to_string();
+        self.opendir(path.as_str())
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        let _ = self.getattr(get_path_from_fh(fh)?)?;
+        let path = get_path_from_fh(fh)?.to_string();
+        self.open(path.as_str(), flags)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        let mocks = &self.mocks.read().unwrap();
+        mocks.links.get(path)
+            .cloned()
+            .ok_or(Error::new(std::io::ErrorKind::InvalidInput, "not a symlink"))
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        let _ = self.getattr(path)?;
+        let mocks = &mut self.mocks.write().unwrap();
+        // XXX: mimic server that replaces ACL with one equivalent to new mode
+        let _ = mocks.acls.remove(path.trim_end_matches('/'));
+        let _ = mocks.modes.insert(path.trim_end_matches('/').to_string(), mode & 0o7777);
         Ok(())
     }
 
@@ -118,48 +449,158 @@ impl NFS for NFS3 {
         Ok(Box::new(NFSDirectory3{nfs: &*self, path: path.to_string(), entries: None, index: 0}))
     }
 
-    fn mkdir(&self, path: &str, _mode: u32) -> Result<()> {
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
         let mocks = &mut self.mocks.write().unwrap();
         let _ = mocks.dirs.insert(path.to_string() + "/");
+        let _ = mocks.modes.insert(path.to_string(), mode & 0o7777);
+        let _ = mocks.owners.insert(path.to_string(), self.get_caller());
+        mocks.dir_changes += 1;
         Ok(())
     }
 
-    fn create(&mut self, path: &str, _flags: u32, _mode: u32) -> Result<Box<dyn NFSFile>> {
+    fn create(&mut self, path: &str, _flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
         let mocks = &mut self.mocks.write().unwrap();
-        let _ = mocks.files.insert(path.to_string(), Vec::new());
-        Ok(Box::new(NFSFile3{nfs: &*self, path: path.to_string()}))
+        let exists = mocks.files.contains_key(path) || mocks.specials.contains_key(path) || mocks.dirs.contains(&(path.to_string() + "/"));
+        match how {
+            CreateMode::Unchecked => {
+                mocks.new_file(path);
+                if !exists {
+                    let _ = mocks.modes.insert(path.to_string(), mode & 0o7777);
+                    let _ = mocks.owners.insert(path.to_string(), self.get_caller());
+                    mocks.dir_changes += 1;
+                }
+            },
+            CreateMode::Exclusive(verifier) if exists && mocks.verifiers.get(path) == Some(&verifier) => (),
+            _ if exists => return Err(Error::new(std::io::ErrorKind::AlreadyExists, "file exists")),
+            _ => {
+                mocks.new_file(path);
+                let _ = mocks.modes.insert(path.to_string(), mode & 0o7777);
+                let _ = mocks.owners.insert(path.to_string(), self.get_caller());
+                mocks.dir_changes += 1;
+                if let CreateMode::Exclusive(verifier) = how {
+                    let _ = mocks.verifiers.insert(path.to_string(), verifier);
+                }
+            },
+        }
+        Ok(Box::new(NFSFile3{nfs: &*self, path: path.to_string(), owner: NEXT_LOCK_OWNER.fetch_add(1, Ordering::Relaxed)}))
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        let fmt = mode & SFlag::S_IFMT.bits() as u32;
+        if ![SFlag::S_IFBLK, SFlag::S_IFCHR, SFlag::S_IFIFO, SFlag::S_IFSOCK].iter().any(|flag| fmt == flag.bits() as u32) {
+            return Err(Error::new(std::io::ErrorKind::InvalidInput, "not a special file type"));
+        }
+        if self.getattr(path).is_ok() {
+            return Err(Error::new(std::io::ErrorKind::AlreadyExists, "file exists"));
+        }
+        let mocks = &mut self.mocks.write().unwrap();
+        let _ = mocks.specials.insert(path.to_string(), (fmt, rdev));
+        let _ = mocks.modes.insert(path.to_string(), mode & 0o7777);
+        let _ = mocks.owners.insert(path.to_string(), self.get_caller());
+        mocks.dir_changes += 1;
+        Ok(())
     }
 
     fn rmdir(&self, path: &str) -> Result<()> {
         let mocks = &mut self.mocks.write().unwrap();
+        let _ = mocks.modes.remove(path);
+        let _ = mocks.owners.remove(path);
+        let _ = mocks.xattrs.remove(path);
+        let _ = mocks.acls.remove(path);
         let path = path.to_string() + "/";
-        let _ = mocks.dirs.remove(&path);
+        if mocks.dirs.remove(&path) {
+            mocks.dir_changes += 1;
+        }
         Ok(())
     }
 
     fn unlink(&self, path: &str) -> Result<()> {
         let mocks = &mut self.mocks.write().unwrap();
-        let _ = mocks.files.remove(&path.to_string());
+        if mocks.files.remove(&path.to_string()).is_some() || mocks.specials.remove(path).is_some() {
+            mocks.dir_changes += 1;
+        }
+        let _ = mocks.verifiers.remove(path);
+        let _ = mocks.modes.remove(path);
+        let _ = mocks.owners.remove(path);
+        let _ = mocks.extents.remove(path);
+        let _ = mocks.xattrs.remove(path);
+        let _ = mocks.acls.remove(path);
         Ok(())
     }
 
     fn open(&mut self, path: &str, _flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.check_connection()?;
         let mocks = &mut self.mocks.write().unwrap();
         if mocks.dirs.get(&path.to_string()).is_some() {
             return Err(Error::new(std::io::ErrorKind::Other, "is a directory"));
         }
+        if mocks.specials.contains_key(path) {
+            return Err(Error::new(std::io::ErrorKind::InvalidInput, "not a regular file"));
+        }
         if mocks.files.get(&path.to_string()).is_none() {
-            mocks.files.insert(path.to_string(), Vec::new());
+            mocks.new_file(path);
         }
-        Ok(Box::new(NFSFile3{nfs: &*self, path: path.to_string()}))
+        Ok(Box::new(NFSFile3{nfs: &*self, path: path.to_string(), owner: NEXT_LOCK_OWNER.fetch_add(1, Ordering::Relaxed)}))
     }
 
     fn truncate(&self, path: &str, len: u64) -> Result<()> {
         let mocks = &mut self.mocks.write().unwrap();
+        remove_extent(mocks.extents_mut(path), len, u64::MAX);
         let contents = mocks.files.entry(path.to_string()).or_default();
         contents.resize(len as usize, 0);
         Ok(())
       }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        let _ = self.getattr(path)?;
+        let mocks = &self.mocks.read().unwrap();
+        mocks.xattrs.get(path.trim_end_matches('/'))
+            .and_then(|xattrs| xattrs.get(name))
+            .cloned()
+            .ok_or(Error::new(std::io::ErrorKind::NotFound, "xattr not found"))
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        let _ = self.getattr(path)?;
+        let mocks = &mut self.mocks.write().unwrap();
+        let _ = mocks.xattrs.entry(path.trim_end_matches('/').to_string()).or_default().insert(name.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        let _ = self.getattr(path)?;
+        let mocks = &self.mocks.read().unwrap();
+        Ok(mocks.xattrs.get(path.trim_end_matches('/')).map_or(Vec::new(), |xattrs| xattrs.keys().cloned().collect()))
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        let _ = self.getattr(path)?;
+        let mocks = &mut self.mocks.write().unwrap();
+        mocks.xattrs.get_mut(path.trim_end_matches('/'))
+            .and_then(|xattrs| xattrs.remove(name))
+            .map(|_| ())
+            .ok_or(Error::new(std::io::ErrorKind::NotFound, "xattr not found"))
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        let stat = self.getattr(path)?;
+        let mocks = &self.mocks.read().unwrap();
+        if let Some(acl) = mocks.acls.get(path.trim_end_matches('/')) {
+            return Ok(acl.clone());
+        }
+        Ok(mode_to_acl(stat.mode as u32 & 0o777, stat.mode & SFlag::S_IFMT.bits() as u64 == SFlag::S_IFDIR.bits() as u64))
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        let _ = self.getattr(path)?;
+        let mocks = &mut self.mocks.write().unwrap();
+        let _ = mocks.acls.insert(path.trim_end_matches('/').to_string(), acl.to_vec());
+        Ok(())
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -178,59 +619,117 @@ impl Iterator for NFSDirectory3 {
     fn next(&mut self) -> Option<Self::Item> {
         if self.entries.is_none() {
             let mut entries = Vec::new();
-            let mocks = unsafe { &(*self.nfs).mocks.read().unwrap() };
+            let nfs = unsafe { &*self.nfs };
+            let mocks = &nfs.mocks.read().unwrap();
+            // XXX: keep entries in line with stat64, since attribute cache is seeded from them
             // XXX: technically should add '.' and '..' to entries but don't bother since they will be ignored anyway
             for (mock_file, content) in &mocks.files {
                 let (parent_path, name) = get_parent_path_and_name(&mock_file);
                 if parent_path == self.path {
-                        let mode = if mock_file == "/3" { 0o444 } else { 0o664 };
+                        let mode = mocks.get_mode(mock_file, if mock_file == "/3" { 0o444 } else { 0o664 });
                         entries.push(NFSDirEntry{
                         path: name,
-                        inode: Default::default(),
+                        raw_path: None,
+                        inode: get_inode_from_path(mock_file),
                         d_type: NFSEntryType::File,
                         mode,
                         size: content.len() as u64,
+                        used: mocks.get_used(mock_file, content.len() as u64),
+                        atime: Time{seconds: 1658159058, nseconds: 0},
+                        mtime: Time{seconds: 1658159058, nseconds: 0},
+                        ctime: Time{seconds: 1658159058, nseconds: 0},
+                        uid: mocks.get_owner(mock_file).0,
+                        gid: mocks.get_owner(mock_file).1,
+                        nlink: Default::default(),
+                        dev: Default::default(),
+                        rdev: Default::default(),
+                        blksize: Default::default(),
+                        blocks: Default::default(),
+                        atime_nsec: 723000000,
+                        mtime_nsec: 723000000,
+                        ctime_nsec: 720000000,
+                    });
+                }
+            }
+            for (mock_special, (fmt, rdev)) in &mocks.specials {
+                let (parent_path, name) = get_parent_path_and_name(mock_special);
+                if parent_path == self.path {
+                    entries.push(NFSDirEntry{
+                        path: name,
+                        raw_path: None,
+                        inode: get_inode_from_path(mock_special),
+                        d_type: NFSEntryType::from_mode(*fmt as u64),
+                        mode: mocks.get_mode(mock_special, 0o644),
+                        size: Default::default(),
+                        used: Default::default(),
+                        atime: Time{seconds: 1658159058, nseconds: 0},
+                        mtime: Time{seconds: 1658159058, nseconds: 0},
+                        ctime: Time{seconds: 1658159058, nseconds: 0},
+                        uid: mocks.get_owner(mock_special).0,
+                        gid: mocks.get_owner(mock_special).1,
+                        nlink: Default::default(),
+                        dev: Default::default(),
+                        rdev: *rdev,
+                        blksize: Default::default(),
+                        blocks: Default::default(),
+                        atime_nsec: 723000000,
+                        mtime_nsec: 723000000,
+                        ctime_nsec: 720000000,
+                    });
+                }
+            }
+            for mock_link in mocks.links.keys() {
+                let (parent_path, name) = get_parent_path_and_name(mock_link);
+                if parent_path == self.path {
+                    entries.push(NFSDirEntry{
+                        path: name,
+                        raw_path: None,
+                        inode: get_inode_from_path(mock_link),
+                        d_type: NFSEntryType::Symlink,
+                        mode: 0o777,
+                        size: Default::default(),
                         used: Default::default(),
                         atime: Time{seconds: 1658159058, nseconds: 0},
                         mtime: Time{seconds: 1658159058, nseconds: 0},
-                        ctime: Time{seconds: 1658159055, nseconds: 0},
-                        uid: Default::default(),
-                        gid: Default::default(),
+                        ctime: Time{seconds: 1658159058, nseconds: 0},
+                        uid: mocks.get_owner(mock_link).0,
+                        gid: mocks.get_owner(mock_link).1,
                         nlink: Default::default(),
                         dev: Default::default(),
                         rdev: Default::default(),
                         blksize: Default::default(),
                         blocks: Default::default(),
-                        atime_nsec: Default::default(),
-                        mtime_nsec: Default::default(),
-                        ctime_nsec: Default::default(),
+                        atime_nsec: 723000000,
+                        mtime_nsec: 723000000,
+                        ctime_nsec: 720000000,
                     });
                 }
             }
             for mock_dir in mocks.dirs.iter().rev() {
                 let (parent_path, name) = get_parent_path_and_name(&mock_dir.trim_end_matches('/').into());
                 if parent_path == self.path {
-                    let mode = if mock_dir == "/quatre/" { 0o555 } else { 0o775 };
+                    let mode = mocks.get_mode(mock_dir, if mock_dir == "/quatre/" { 0o555 } else { 0o775 });
                     entries.push(NFSDirEntry{
                         path: name,
-                        inode: Default::default(),
+                        raw_path: None,
+                        inode: get_inode_from_path(mock_dir),
                         d_type: NFSEntryType::Directory,
                         mode,
                         size: Default::default(),
                         used: Default::default(),
                         atime: Time{seconds: 1658159058, nseconds: 0},
                         mtime: Time{seconds: 1658159058, nseconds: 0},
-                        ctime: Time{seconds: 1658159055, nseconds: 0},
-                        uid: Default::default(),
-                        gid: Default::default(),
+                        ctime: Time{seconds: 1658159058, nseconds: 0},
+                        uid: mocks.get_owner(mock_dir).0,
+                        gid: mocks.get_owner(mock_dir).1,
                         nlink: Default::default(),
                         dev: Default::default(),
                         rdev: Default::default(),
                         blksize: Default::default(),
                         blocks: Default::default(),
-                        atime_nsec: Default::default(),
-                        mtime_nsec: Default::default(),
-                        ctime_nsec: Default::default(),
+                        atime_nsec: 723000000,
+                        mtime_nsec: 723000000,
+                        ctime_nsec: 720000000,
                     });
                 }
             }
@@ -256,6 +755,15 @@ impl Iterator for NFSDirectory3 {
 pub struct NFSFile3 {
     nfs: *const NFS3,
     path: String,
+    owner: u64,
+}
+
+impl Drop for NFSFile3 {
+    fn drop(&mut self) {
+        // XXX: closing file releases all its locks, like with POSIX locks
+        let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+        mocks.locks.retain(|lock| lock.owner != self.owner);
+    }
 }
 
 impl NFSFile for NFSFile3 {
@@ -268,22 +776,22 @@ impl NFSFile for NFSFile3 {
         };
         Ok(NFSStat64{
             dev: Default::default(),
-            ino: Default::default(),
+            ino: get_inode_from_path(&self.path),
             mode: Default::default(),
             nlink: Default::default(),
-            uid: Default::default(),
-            gid: Default::default(),
+            uid: mocks.get_owner(&self.path).0.into(),
+            gid: mocks.get_owner(&self.path).1.into(),
             rdev: Default::default(),
             size,
-            used: Default::default(),
+            used: mocks.get_used(&self.path, size),
             blksize: Default::default(),
             blocks: Default::default(),
-            atime: 1658159058723,
-            mtime: 1658159058723,
-            ctime: 1658159058720,
-            atime_nsec: Default::default(),
-            mtime_nsec: Default::default(),
-            ctime_nsec: Default::default(),
+            atime: 1658159058,
+            mtime: 1658159058,
+            ctime: 1658159058,
+            atime_nsec: 723000000,
+            mtime_nsec: 723000000,
+            ctime_nsec: 720000000,
         })
     }
 
@@ -308,18 +816,81 @@ impl NFSFile for NFSFile3 {
 
     fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
         let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+        add_extent(mocks.extents_mut(&self.path), offset, offset + buffer.len() as u64);
         let contents = mocks.files.entry(self.path.clone()).or_default();
-        let offset = offset as usize;
-        let writelen = if contents.len() >= offset + buffer.len() {
-            contents.splice(offset..(offset + buffer.len()), buffer.iter().cloned());
-            buffer.len() as u32
-        } else {
-            let padlen = offset - contents.len();
-            contents.resize(offset, 0);
-            contents.append(&mut buffer.to_vec());
-            (padlen + buffer.len()) as u32
+        let (offset, end) = (offset as usize, offset as usize + buffer.len());
+        if contents.len() < end {
+            contents.resize(end, 0);
+        }
+        contents[offset..end].copy_from_slice(buffer);
+        Ok(buffer.len() as u32)
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        let (start, end) = get_lock_range(offset, len);
+        loop {
+            {
+                let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+                let conflicts = mocks.locks.iter().any(|lock| {
+                    lock.path == self.path && lock.owner != self.owner && lock.start < end && start < lock.end && (exclusive || lock.exclusive)
+                });
+                if !conflicts {
+                    release_lock_range(&mut mocks.locks, &self.path, self.owner, start, end);
+                    mocks.locks.push(MockLock{path: self.path.clone(), owner: self.owner, start, end, exclusive});
+                    return Ok(());
+                }
+            }
+            if !wait {
+                return Err(Error::new(std::io::ErrorKind::WouldBlock, "conflicting lock held"));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        let (start, end) = get_lock_range(offset, len);
+        let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+        release_lock_range(&mut mocks.locks, &self.path, self.owner, start, end);
+        Ok(())
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        let mocks = unsafe { &(*self.nfs).mocks.read().unwrap() };
+        let size = mocks.files.get(&self.path).map_or(0, |contents| contents.len() as u64);
+        let Some(extents) = mocks.extents.get(&self.path) else {
+            return Ok(offset.min(size));
+        };
+        let data = extents.iter().find(|(_, end)| **end > offset).map_or(size, |(start, _)| (*start).max(offset));
+        Ok(data.min(size))
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        let mocks = unsafe { &(*self.nfs).mocks.read().unwrap() };
+        let size = mocks.files.get(&self.path).map_or(0, |contents| contents.len() as u64);
+        let Some(extents) = mocks.extents.get(&self.path) else {
+            return Ok(offset.max(size));
         };
-        Ok(writelen)
+        let hole = extents.range(..=offset).next_back().filter(|(_, end)| **end > offset).map_or(offset, |(_, end)| *end);
+        Ok(hole.min(size).max(offset))
+    }
+
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+        add_extent(mocks.extents_mut(&self.path), offset, offset + len);
+        let contents = mocks.files.entry(self.path.clone()).or_default();
+        if (contents.len() as u64) < offset + len {
+            contents.resize((offset + len) as usize, 0);
+        }
+        Ok(())
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        let mocks = unsafe { &mut (*self.nfs).mocks.write().unwrap() };
+        remove_extent(mocks.extents_mut(&self.path), offset, offset + len);
+        let contents = mocks.files.entry(self.path.clone()).or_default();
+        let (start, end) = ((offset as usize).min(contents.len()), ((offset + len) as usize).min(contents.len()));
+        contents[start..end].fill(0);
+        Ok(())
     }
 }
 
@@ -394,4 +965,130 @@ mod tests {
         ];
         assert_eq!(subentries, expected_subentries);
     }
+
+    #[test]
+    fn mock_inodes_match_between_stat_and_entries() {
+        let mut nfs = NFS3::connect(String::new());
+        let dir = nfs.opendir("/").unwrap();
+        for entry in dir {
+            let e = entry.unwrap();
+            let path = if e.d_type == NFSEntryType::Directory { format!("/{}/", e.path) } else { format!("/{}", e.path) };
+            let stat = nfs.stat64(&path).unwrap();
+            assert_eq!(stat.ino, e.inode, "path = {}", path);
+        }
+        assert_ne!(nfs.stat64("/annar").unwrap().ino, nfs.stat64("/3").unwrap().ino);
+        let file = nfs.open("/annar", 0).unwrap();
+        assert_eq!(file.fstat64().unwrap().ino, nfs.stat64("/annar").unwrap().ino);
+    }
+
+    #[test]
+    fn mock_file_handles_work() {
+        let mut nfs = NFS3::connect(String::new());
+        let root_fh = nfs.lookup_fh("/").unwrap();
+        let dir_fh = nfs.lookup_in(&root_fh, "first").unwrap();
+        assert_eq!(dir_fh, nfs.lookup_fh("/first/").unwrap());
+        let file_fh = nfs.lookup_in(&dir_fh, "comment").unwrap();
+        assert_eq!(nfs.getattr_fh(&file_fh).unwrap().ino, nfs.stat64("/first/comment").unwrap().ino);
+        let names: Vec<String> = nfs.readdir_fh(&dir_fh).unwrap().map(|e| e.unwrap().path).collect();
+        assert_eq!(names, vec!["comment".to_string()]);
+        let file = nfs.open_fh(&nfs.lookup_fh("/annar").unwrap(), 0).unwrap();
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        assert!(nfs.lookup_in(&root_fh, "unknown").is_err());
+        assert!(nfs.getattr_fh(b"garbage").is_err());
+    }
+
+    #[test]
+    fn mock_entries_are_owned_by_creator() {
+        let mut nfs = NFS3::connect(String::new());
+        nfs.set_credentials(&Credentials{uid: Some(1001), gid: Some(1002), aux_gids: None}).unwrap();
+        let _ = nfs.create("/owned", 0, 0o640, CreateMode::Guarded).unwrap();
+        nfs.set_credentials(&Credentials{uid: Some(1003), gid: Some(1003), aux_gids: None}).unwrap();
+        let stat = nfs.stat64("/owned").unwrap();
+        assert_eq!((stat.uid, stat.gid), (1001, 1002));
+        assert_eq!(nfs.stat64("/annar").unwrap().uid, 0);
+        assert_eq!(nfs.access("/owned", ACCESS_READ | ACCESS_MODIFY).unwrap(), 0);
+        nfs.set_credentials(&Credentials{uid: Some(1003), gid: Some(1003), aux_gids: Some(vec![1002])}).unwrap();
+        assert_eq!(nfs.access("/owned", ACCESS_READ | ACCESS_MODIFY).unwrap(), ACCESS_READ);
+        nfs.set_credentials(&Credentials{uid: Some(1001), gid: Some(1003), aux_gids: None}).unwrap();
+        assert_eq!(nfs.access("/owned", ACCESS_READ | ACCESS_MODIFY).unwrap(), ACCESS_READ | ACCESS_MODIFY);
+    }
+
+    #[test]
+    fn mock_exclusive_create_fails_on_existing_name() {
+        let mut nfs = NFS3::connect(String::new());
+        assert_eq!(nfs.create("/annar", 0, 0o664, CreateMode::Guarded).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(nfs.create("/first", 0, 0o664, CreateMode::exclusive()).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        let how = CreateMode::exclusive();
+        let _ = nfs.create("/new", 0, 0o664, how).unwrap();
+        let _ = nfs.create("/new", 0, 0o664, how).unwrap();
+        assert_eq!(nfs.create("/new", 0, 0o664, CreateMode::exclusive()).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        nfs.unlink("/new").unwrap();
+        let _ = nfs.create("/new", 0, 0o664, CreateMode::Guarded).unwrap();
+    }
+
+    #[test]
+    fn mock_files_model_holes() {
+        let mut nfs = NFS3::connect(String::new());
+        let file = nfs.open("/annar", 0).unwrap();
+        assert_eq!((file.seek_data(0).unwrap(), file.seek_hole(0).unwrap()), (0, 123));
+        file.punch_hole(10, 20).unwrap();
+        assert_eq!((file.seek_hole(0).unwrap(), file.seek_data(10).unwrap()), (10, 30));
+        assert_eq!(file.fstat64().unwrap().used, 103);
+        let _ = file.pwrite(b"x", 200).unwrap();
+        assert_eq!((file.seek_hole(30).unwrap(), file.seek_data(123).unwrap(), file.seek_hole(200).unwrap()), (123, 200, 201));
+        nfs.truncate("/annar", 300).unwrap();
+        assert_eq!((file.seek_data(201).unwrap(), file.fstat64().unwrap().size, file.fstat64().unwrap().used), (300, 300, 104));
+        file.allocate(250, 100).unwrap();
+        assert_eq!((file.seek_data(201).unwrap(), file.seek_hole(250).unwrap(), file.fstat64().unwrap().size), (250, 350, 350));
+        let mut buffer = [1u8; 20];
+        assert_eq!(file.pread_into(20, 10, &mut buffer).unwrap(), 20);
+        assert_eq!(buffer, [0u8; 20]);
+    }
+
+    #[test]
+    fn mock_locks_conflict_between_open_files() {
+        let mut nfs = NFS3::connect(String::new());
+        let first = nfs.open("/annar", 0).unwrap();
+        let second = nfs.open("/annar", 0).unwrap();
+        first.lock(0, 10, false, false).unwrap();
+        second.lock(5, 10, false, false).unwrap();
+        assert_eq!(second.lock(0, 0, true, false).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+        first.lock(20, 0, true, false).unwrap();
+        assert_eq!(second.lock(30, 1, false, false).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+        first.unlock(0, 0).unwrap();
+        second.lock(0, 0, true, false).unwrap();
+        drop(second);
+        first.lock(0, 0, true, false).unwrap();
+    }
+
+    #[test]
+    fn mock_xattrs_follow_their_entry() {
+        let mut nfs = NFS3::connect(String::new());
+        assert_eq!(nfs.listxattrs("/first/").unwrap(), Vec::<String>::new());
+        nfs.setxattr("/first/", "origin", b"pipeline").unwrap();
+        nfs.setxattr("/first/comment", "origin", b"upload").unwrap();
+        assert_eq!(nfs.getxattr("/first", "origin").unwrap(), b"pipeline");
+        assert_eq!(nfs.listxattrs("/first/comment").unwrap(), vec!["origin".to_string()]);
+        assert_eq!(nfs.getxattr("/first/comment", "other").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(nfs.setxattr("/missing", "origin", b"").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        nfs.removexattr("/first/comment", "origin").unwrap();
+        assert_eq!(nfs.removexattr("/first/comment", "origin").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        nfs.unlink("/first/comment").unwrap();
+        assert_eq!(nfs.listxattrs("/first/comment").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        let _ = nfs.open("/first/comment", 0).unwrap();
+        assert_eq!(nfs.listxattrs("/first/comment").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn mock_access_grants_part_of_mode() {
+        let nfs = NFS3::connect(String::new());
+        let all = ACCESS_READ | ACCESS_LOOKUP | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE | ACCESS_EXECUTE;
+        assert_eq!(nfs.access("/first/", all).unwrap(), ACCESS_READ | ACCESS_LOOKUP | ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE);
+        assert_eq!(nfs.access("/quatre/", all).unwrap(), ACCESS_READ | ACCESS_LOOKUP);
+        assert_eq!(nfs.access("/annar", all).unwrap(), ACCESS_READ | ACCESS_MODIFY | ACCESS_EXTEND);
+        assert_eq!(nfs.access("/3", ACCESS_READ | ACCESS_MODIFY).unwrap(), ACCESS_READ);
+        nfs.setacl("/annar", &[Ace{type_: ACE_TYPE_DENY, flags: 0, mask: ACE_MASK_APPEND_DATA, who: ACE_WHO_EVERYONE.into()}, Ace{type_: ACE_TYPE_ALLOW, flags: 0, mask: ACE_MASK_WRITE_DATA | ACE_MASK_APPEND_DATA, who: ACE_WHO_OWNER.into()}]).unwrap();
+        assert_eq!(nfs.access("/annar", all).unwrap(), ACCESS_MODIFY);
+        assert_eq!(nfs.access("/missing", ACCESS_READ).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
 }
@@ -14,12 +14,26 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 
-use std::io::Result;
+use std::io::{Error, Result};
 use std::fmt::Debug;
+use std::sync::Mutex;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use nix::sys::stat::SFlag;
 
 mod libnfs;
 mod nfs_rs;
 mod mock;
+mod jail;
+mod read_only;
+mod reconnect;
+mod nconnect;
+mod attr_cache;
+mod name_cache;
+mod data_cache;
+
+pub(crate) use read_only::NO_MODIFICATION_ALLOWED;
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Time {
@@ -27,27 +41,274 @@ pub struct Time {
     pub nseconds: u32,
 }
 
+// Bits of mode passed to access, which are those of ACCESS in NFSv3 and NFSv4 (RFC 1813 and RFC 7530).
+pub const ACCESS_READ: u32 = 0x01;
+pub const ACCESS_LOOKUP: u32 = 0x02;
+pub const ACCESS_MODIFY: u32 = 0x04;
+pub const ACCESS_EXTEND: u32 = 0x08;
+pub const ACCESS_DELETE: u32 = 0x10;
+pub const ACCESS_EXECUTE: u32 = 0x20;
+
+// Returns rdev of device with given major and minor numbers, which are kept like specdata1 and specdata2 of NFSv3 and
+// NFSv4 (RFC 1813 and RFC 7530) in upper and lower 32 bits respectively.
+pub fn makerdev(major: u32, minor: u32) -> u64 {
+    ((major as u64) << 32) | minor as u64
+}
+
+pub fn rdev_major(rdev: u64) -> u32 {
+    (rdev >> 32) as u32
+}
+
+pub fn rdev_minor(rdev: u64) -> u32 {
+    rdev as u32
+}
+
 pub trait NFS: Debug + Send + Sync {
-    #[allow(unused)]
-    fn access(&self, path: &str, mode: u32) -> Result<()>;
+    // Returns which of given ACCESS_* bits credentials in use are granted on path, like ACCESS does, so that bits which
+    // do not apply to kind of entry (such as LOOKUP and DELETE on files, or EXECUTE on directories) are never granted.
+    fn access(&self, path: &str, mode: u32) -> Result<u32>;
     fn stat64(&self, path: &str) -> Result<NFSStat64>;
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>>;
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>>;
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64>;
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()>;
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>>;
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>>;
+    // Returns target of symlink at path, or fails with InvalidInput if there is something else at path, like EINVAL.
+    fn readlink(&self, path: &str) -> Result<String>;
     fn lchmod(&self, path: &str, mode: u32) -> Result<()>;
     fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>>;
     fn mkdir(&self, path: &str, mode: u32) -> Result<()>;
-    fn create(&mut self, path: &str, flags: u32, mode: u32) -> Result<Box<dyn NFSFile>>;
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>>;
+    // Creates special file at path, whose kind is given by file type bits of mode (one of S_IFBLK, S_IFCHR, S_IFIFO or
+    // S_IFSOCK), where rdev only matters for devices.
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()>;
     fn rmdir(&self, path: &str) -> Result<()>;
     fn unlink(&self, path: &str) -> Result<()>;
     fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>>;
     fn truncate(&self, path: &str, len: u64) -> Result<()>;
+
+    // Extended attributes as in RFC 8276, which only has user namespace, so names are given without "user." prefix.
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>>;
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()>;
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>>;
+    fn removexattr(&self, path: &str, name: &str) -> Result<()>;
+
+    // Access control list as in acl attribute of NFSv4, which servers keep in line with mode bits.
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>>;
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()>;
+
+    fn mkfifo(&self, path: &str, mode: u32) -> Result<()> {
+        self.mknod(path, SFlag::S_IFIFO.bits() as u32 | mode & 0o7777, 0)
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        Default::default()
+    }
+
+    // Tells whether create takes CreateMode::Exclusive, which callers go by before falling back to Guarded.
+    fn supports_exclusive_create(&self) -> bool {
+        false
+    }
+}
+
+// CreateMode is what create does when name already exists: Unchecked just opens existing file, Guarded fails, and so
+// does Exclusive, unless existing file was created with same verifier, so that a retried create can tell its own lost
+// attempt apart from somebody else's file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CreateMode {
+    #[default]
+    Unchecked,
+    Guarded,
+    Exclusive(u64),
 }
 
+impl CreateMode {
+    // Returns Exclusive with verifier unique to this call.
+    pub fn exclusive() -> Self {
+        static COUNT: AtomicU64 = AtomicU64::new(0);
+        let mut hasher = DefaultHasher::new();
+        (std::process::id(), SystemTime::now(), COUNT.fetch_add(1, Ordering::Relaxed)).hash(&mut hasher);
+        CreateMode::Exclusive(hasher.finish())
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        *self != CreateMode::Unchecked
+    }
+}
+
+// CacheStats counts how many lookups and pages were answered from cache and how many had to go to server.
+#[derive(Clone, Debug, Default)]
+pub struct CacheStats {
+    pub name_hits: u64,
+    pub name_misses: u64,
+    pub name_entries: u64,
+    pub data_hits: u64,
+    pub data_misses: u64,
+    pub data_bytes: u64,
+}
+
+// Ace is access control entry of NFSv4 (RFC 7530, section 6.2.1), which allows, denies, audits or alarms on accesses
+// in mask by who, which is either principal like "user@domain" or special one like "OWNER@", "GROUP@" or "EVERYONE@".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ace {
+    pub type_: u32,
+    pub flags: u32,
+    pub mask: u32,
+    pub who: String,
+}
+
+pub const ACE_TYPE_ALLOW: u32 = 0;
+pub const ACE_TYPE_DENY: u32 = 1;
+pub const ACE_TYPE_AUDIT: u32 = 2;
+pub const ACE_TYPE_ALARM: u32 = 3;
+
+pub const ACE_FLAG_INHERIT_ONLY: u32 = 0x08;
+
+pub const ACE_MASK_READ_DATA: u32 = 0x01;
+pub const ACE_MASK_WRITE_DATA: u32 = 0x02;
+pub const ACE_MASK_APPEND_DATA: u32 = 0x04;
+pub const ACE_MASK_EXECUTE: u32 = 0x20;
+pub const ACE_MASK_DELETE_CHILD: u32 = 0x40;
+
+pub const ACE_WHO_OWNER: &str = "OWNER@";
+pub const ACE_WHO_GROUP: &str = "GROUP@";
+pub const ACE_WHO_EVERYONE: &str = "EVERYONE@";
+
 pub trait NFSDirectory: Debug + Iterator<Item = Result<NFSDirEntry>> {}
 
+pub type Check<'a> = dyn Fn() -> Result<()> + Sync + 'a;
+
 pub trait NFSFile: Debug {
     fn fstat64(&self) -> Result<NFSStat64>;
     fn get_max_read_size(&self) -> u64;
     fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32>;
     fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32>;
+
+    // Takes advisory byte-range lock on len bytes from offset on, where len of 0 means up to end of file and beyond, for
+    // this open file, replacing whatever this file held of that range before. If a conflicting lock is held elsewhere,
+    // then waits for it to go away if wait is set, or fails with WouldBlock otherwise.
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()>;
+    fn unlock(&self, offset: u64, len: u64) -> Result<()>;
+
+    // Returns where data starts at or after offset, or size of file if there is none, like SEEK_DATA. By default, all
+    // of file is taken to be data.
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        Ok(offset.min(self.fstat64()?.size))
+    }
+
+    // Returns where hole starts at or after offset, where end of file counts as one, like SEEK_HOLE. By default, the
+    // only hole there is, is at end of file.
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        Ok(offset.max(self.fstat64()?.size))
+    }
+
+    // Makes sure len bytes from offset on are backed by storage, extending file if need be, but leaving its data be.
+    // By default, this writes zeros past end of file, since what is before it is taken to be allocated already.
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        let size = self.fstat64()?.size;
+        let start = offset.max(size);
+        let end = offset.saturating_add(len);
+        write_zeros(self, start, end)
+    }
+
+    // Turns len bytes from offset on into hole that reads as zeros, without changing size of file. By default, this
+    // writes zeros there, which reads the same, but does not free any storage.
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        let size = self.fstat64()?.size;
+        let end = offset.saturating_add(len).min(size);
+        write_zeros(self, offset, end)
+    }
+
+    // Reads as much of buffer as there is data for from offset on, in chunks of at most get_max_read_size() with check
+    // called before each one, and returns how many bytes were read.
+    fn pread_all_into(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        let chunk_size = self.get_max_read_size().max(1) as usize;
+        let len = buffer.len() as u64;
+        let chunks = Mutex::new(buffer.chunks_mut(chunk_size).enumerate());
+        let end = read_chunks(self, offset, chunk_size, &chunks, check, &AtomicBool::new(false))?;
+        Ok(end.unwrap_or(len))
+    }
+
+    // Writes all of buffer at offset, in chunks of at most get_max_read_size() with check called before each one.
+    fn pwrite_all(&self, buffer: &[u8], offset: u64, check: &Check) -> Result<()> {
+        let chunk_size = self.get_max_read_size().max(1) as usize; // XXX: servers practically always use same size for writes
+        let chunks = Mutex::new(buffer.chunks(chunk_size).enumerate());
+        write_chunks(self, offset, chunk_size, &chunks, check, &AtomicBool::new(false))
+    }
+}
+
+fn write_zeros<F: NFSFile + ?Sized>(file: &F, start: u64, end: u64) -> Result<()> {
+    let zeros = vec![0u8; file.get_max_read_size().max(1).min(end.saturating_sub(start)) as usize];
+    let mut offset = start;
+    while offset < end {
+        let count = zeros.len().min((end - offset) as usize);
+        file.pwrite_all(&zeros[..count], offset, &|| Ok(()))?;
+        offset += count as u64;
+    }
+    Ok(())
+}
+
+type ReadChunks<'a> = Mutex<std::iter::Enumerate<std::slice::ChunksMut<'a, u8>>>;
+type WriteChunks<'a> = Mutex<std::iter::Enumerate<std::slice::Chunks<'a, u8>>>;
+
+// Reads chunks taken off the shared queue until it runs empty or reading failed anywhere, and returns where data ended
+// if it ended in one of them.
+fn read_chunks<F: NFSFile + ?Sized>(file: &F, offset: u64, chunk_size: usize, chunks: &ReadChunks, check: &Check, failed: &AtomicBool) -> Result<Option<u64>> {
+    let mut end: Option<u64> = None;
+    while !failed.load(Ordering::SeqCst) {
+        let Some((index, chunk)) = chunks.lock().unwrap().next() else {
+            break;
+        };
+        let start = index * chunk_size;
+        match check().and_then(|_| read_chunk(file, offset + start as u64, chunk)) {
+            Ok(count) if count < chunk.len() => end = Some(end.map_or((start + count) as u64, |end| end.min((start + count) as u64))),
+            Ok(_) => (),
+            Err(e) => {
+                failed.store(true, Ordering::SeqCst);
+                return Err(e);
+            },
+        }
+    }
+    Ok(end)
+}
+
+fn read_chunk<F: NFSFile + ?Sized>(file: &F, offset: u64, chunk: &mut [u8]) -> Result<usize> {
+    let mut count = 0;
+    while count < chunk.len() {
+        let bytes_read = file.pread_into((chunk.len() - count) as u32, offset + count as u64, &mut chunk[count..])? as usize;
+        if bytes_read == 0 {
+            break; // XXX: end of file
+        }
+        count += bytes_read;
+    }
+    Ok(count)
+}
+
+// Writes chunks taken off the shared queue until it runs empty or writing failed anywhere.
+fn write_chunks<F: NFSFile + ?Sized>(file: &F, offset: u64, chunk_size: usize, chunks: &WriteChunks, check: &Check, failed: &AtomicBool) -> Result<()> {
+    while !failed.load(Ordering::SeqCst) {
+        let Some((index, chunk)) = chunks.lock().unwrap().next() else {
+            break;
+        };
+        if let Err(e) = check().and_then(|_| write_chunk(file, offset + (index * chunk_size) as u64, chunk)) {
+            failed.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+fn write_chunk<F: NFSFile + ?Sized>(file: &F, offset: u64, chunk: &[u8]) -> Result<()> {
+    let mut count = 0;
+    while count < chunk.len() {
+        let bytes_written = file.pwrite(&chunk[count..], offset + count as u64)? as usize;
+        if bytes_written == 0 {
+            return Err(Error::new(std::io::ErrorKind::WriteZero, "failed to write whole chunk"));
+        }
+        count += bytes_written;
+    }
+    Ok(())
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -59,27 +320,66 @@ pub enum NFSEntryType {
     NamedPipe,
     Symlink,
     Socket,
+    Unknown,
 }
 
-impl From<u32> for NFSEntryType {
-    fn from(val: u32) -> Self {
+// Returns name given as raw bytes as string, which is lossy if name is not valid UTF-8, along with raw bytes if so.
+pub fn name_from_bytes(bytes: Vec<u8>) -> (String, Option<Vec<u8>>) {
+    match String::from_utf8(bytes) {
+        Ok(name) => (name, None),
+        Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), Some(e.into_bytes())),
+    }
+}
+
+impl TryFrom<u32> for NFSEntryType {
+    type Error = Error;
+
+    fn try_from(val: u32) -> Result<Self> {
         match val {
-            0 => Self::Block,
-            1 => Self::Character,
-            2 => Self::Directory,
-            3 => Self::File,
-            4 => Self::NamedPipe,
-            5 => Self::Symlink,
-            6 => Self::Socket,
-            _ => panic!("invalid entry type"),
+            0 => Ok(Self::Block),
+            1 => Ok(Self::Character),
+            2 => Ok(Self::Directory),
+            3 => Ok(Self::File),
+            4 => Ok(Self::NamedPipe),
+            5 => Ok(Self::Symlink),
+            6 => Ok(Self::Socket),
+            _ => Err(Error::new(std::io::ErrorKind::InvalidData, format!("invalid entry type {}", val))),
         }
     }
 }
 
+impl NFSEntryType {
+    const MODE_TYPES: [(SFlag, Self); 7] = [
+        (SFlag::S_IFBLK, Self::Block),
+        (SFlag::S_IFCHR, Self::Character),
+        (SFlag::S_IFDIR, Self::Directory),
+        (SFlag::S_IFREG, Self::File),
+        (SFlag::S_IFIFO, Self::NamedPipe),
+        (SFlag::S_IFLNK, Self::Symlink),
+        (SFlag::S_IFSOCK, Self::Socket),
+    ];
+
+    // Returns type of entry given file type bits of its mode, where anything else is Unknown.
+    pub fn from_mode(mode: u64) -> Self {
+        let fmt = mode & SFlag::S_IFMT.bits() as u64;
+        Self::MODE_TYPES.into_iter().find(|(flag, _)| fmt == flag.bits() as u64).map_or(Self::Unknown, |(_, type_)| type_)
+    }
+
+    // Returns file type bits of mode for entries of this type, which Unknown has none of.
+    pub fn to_mode(&self) -> u64 {
+        Self::MODE_TYPES.into_iter().find(|(_, type_)| type_ == self).map_or(0, |(flag, _)| flag.bits() as u64)
+    }
+
+    pub fn is_special(&self) -> bool {
+        matches!(self, Self::Block | Self::Character | Self::NamedPipe | Self::Socket)
+    }
+}
+
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct NFSDirEntry {
     pub path: String,
+    pub raw_path: Option<Vec<u8>>, // XXX: only set if name is not valid UTF-8, in which case path is lossy version of it
     pub inode: u64,
     pub d_type: NFSEntryType,
     pub mode: u32,
@@ -122,18 +422,260 @@ pub struct NFSStat64 {
   pub ctime_nsec: u64,
 }
 
-pub(crate) fn connect(url: String) -> Result<Box<dyn NFS>> {
-    if std::env::var("TEST_USING_MOCKS").is_ok() {
-        Ok(mock::NFS3::connect(url))
+// Credentials sent with every call as AUTH_SYS, with unset fields falling back to whatever the connection defaults to.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Credentials {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub aux_gids: Option<Vec<u32>>,
+}
+
+impl Credentials {
+    pub(super) fn apply_to_url(&self, url: &str) -> String {
+        let (base, query) = url.split_once('?').unwrap_or((url, ""));
+        let overridden = |key: &str| match key {
+            "uid" => self.uid.is_some(),
+            "gid" => self.gid.is_some(),
+            "auxgids" => self.aux_gids.is_some(),
+            _ => false,
+        };
+        let mut params: Vec<String> = query.split('&')
+            .filter(|param| !param.is_empty() && !overridden(param.split('=').next().unwrap_or_default()))
+            .map(str::to_string)
+            .collect();
+        if let Some(uid) = self.uid {
+            params.push(format!("uid={}", uid));
+        }
+        if let Some(gid) = self.gid {
+            params.push(format!("gid={}", gid));
+        }
+        if let Some(aux_gids) = &self.aux_gids {
+            params.push(format!("auxgids={}", aux_gids.iter().map(u32::to_string).collect::<Vec<_>>().join(",")));
+        }
+        if params.is_empty() {
+            return base.to_string();
+        }
+        format!("{}?{}", base, params.join("&"))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    pub root: Option<String>,
+    pub read_only: bool,
+    pub permission_policy: PermissionPolicy,
+    pub special_files: SpecialFiles,
+    pub credentials: Credentials,
+    pub tls: TlsOptions,
+    pub retry: RetryOptions,
+    pub nconnect: Option<u32>,
+    pub attr_cache: AttrCacheOptions,
+    pub lookup_cache: LookupCache,
+    pub data_cache: DataCacheOptions,
+}
+
+// PermissionPolicy is what requesting permission that is not granted does: Never leaves it at that, Chmod adds missing
+// mode bits, and Prompt does the same only once caller confirms, so that it can ask user first.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PermissionPolicy {
+    #[default]
+    Never,
+    Chmod,
+    Prompt,
+}
+
+// SpecialFiles is how block and character devices, FIFOs and sockets are listed: File lists them as regular files, as
+// they always were, Skip leaves them out, and Expose lists them with kinds of their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SpecialFiles {
+    #[default]
+    File,
+    Skip,
+    Expose,
+}
+
+// LookupCache is what names looked up are cached, as the lookupcache mount option does: All caches both names found and
+// those that were not, Positive caches only those found, and None caches none at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LookupCache {
+    #[default]
+    All,
+    Positive,
+    None,
+}
+
+// DataCacheOptions bound how many bytes of file data are cached in memory, where 0 disables caching, and how many more
+// may be spilled to a directory on local disk if one is given.
+#[derive(Clone, Debug)]
+pub struct DataCacheOptions {
+    pub max_bytes: u64,
+    pub spill_dir: Option<String>,
+    pub spill_max_bytes: u64,
+}
+
+impl Default for DataCacheOptions {
+    fn default() -> Self {
+        Self{max_bytes: 0, spill_dir: None, spill_max_bytes: 1073741824}
+    }
+}
+
+// AttrCacheOptions bound for how many seconds attributes of regular files and of directories are cached, as the
+// acregmin, acregmax, acdirmin and acdirmax mount options do, where maximums of 0 disable caching altogether, as it is
+// unless asked for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AttrCacheOptions {
+    pub acregmin: u32,
+    pub acregmax: u32,
+    pub acdirmin: u32,
+    pub acdirmax: u32,
+}
+
+impl AttrCacheOptions {
+    // Returns timeouts that Linux NFS clients default to, for those not given when caching is asked for.
+    pub fn kernel_defaults() -> Self {
+        Self{acregmin: 3, acregmax: 60, acdirmin: 30, acdirmax: 60}
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.acregmax > 0 || self.acdirmax > 0
+    }
+}
+
+// RetryOptions control how often and for how long operations are retried after the transport breaks, waiting backoff
+// between attempts and doubling it every time, where timeout also bounds each RPC if backend allows it.
+#[derive(Clone, Debug)]
+pub struct RetryOptions {
+    pub retries: u32,
+    pub timeout_ms: Option<u32>,
+    pub backoff_ms: u32,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self{retries: 3, timeout_ms: None, backoff_ms: 100}
+    }
+}
+
+// TlsOptions selects RPC-over-TLS (RFC 9289) transport security, where "tls" authenticates the server only and
+// "mtls" additionally presents a client certificate.
+#[derive(Clone, Debug, Default)]
+pub struct TlsOptions {
+    pub xprtsec: Option<String>,
+}
+
+const XPRTSEC_NONE: &str = "none";
+const XPRTSEC_TLS: &str = "tls";
+const XPRTSEC_MTLS: &str = "mtls";
+
+impl TlsOptions {
+    fn get_xprtsec<'a>(&'a self, url: &'a str) -> &'a str {
+        // XXX: also honour xprtsec given in URL, as libnfs does, so that it can never be silently ignored
+        self.xprtsec.as_deref()
+            .or_else(|| url.split_once('?')?.1.split('&').find_map(|param| param.strip_prefix("xprtsec=")))
+            .unwrap_or(XPRTSEC_NONE)
+    }
+
+    pub(super) fn check(&self, url: &str) -> Result<()> {
+        match self.get_xprtsec(url) {
+            XPRTSEC_NONE => Ok(()),
+            // XXX: neither libnfs nor nfs-rs has a TLS transport yet, so refuse rather than send traffic in the clear
+            xprtsec @ (XPRTSEC_TLS | XPRTSEC_MTLS) => Err(Error::new(std::io::ErrorKind::Unsupported, format!("xprtsec {:?} not supported, since RPC-over-TLS is not implemented", xprtsec))),
+            xprtsec => Err(Error::new(std::io::ErrorKind::InvalidInput, format!("invalid xprtsec {:?}, must be one of {:?}, {:?} or {:?}", xprtsec, XPRTSEC_NONE, XPRTSEC_TLS, XPRTSEC_MTLS))),
+        }
+    }
+}
+
+pub(crate) fn connect(url: String, options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+    options.tls.check(&url)?;
+    let mut nfs = connect_pool(url, options)?;
+    if options.attr_cache.is_enabled() {
+        nfs = attr_cache::AttrCache::new(nfs, &options.attr_cache);
+    }
+    if options.data_cache.max_bytes > 0 {
+        nfs = data_cache::DataCache::new(nfs, &options.data_cache)?;
+    }
+    if options.lookup_cache != LookupCache::None {
+        let attr_cache = &options.attr_cache;
+        let dir_timeout = Duration::from_secs(attr_cache.acdirmin.min(attr_cache.acdirmax).into());
+        nfs = name_cache::NameCache::new(nfs, options.lookup_cache == LookupCache::All, dir_timeout);
+    }
+    if let Some(root) = options.root.as_deref().filter(|root| !root.trim_matches('/').is_empty()) {
+        nfs = jail::Jail::new(nfs, root)?;
+    }
+    if options.read_only {
+        nfs = read_only::ReadOnly::new(nfs);
+    }
+    Ok(nfs)
+}
+
+fn connect_pool(url: String, options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+    let nconnect = options.nconnect.unwrap_or(1);
+    if nconnect < 1 || nconnect > nconnect::MAX_NCONNECT {
+        return Err(Error::new(std::io::ErrorKind::InvalidInput, format!("invalid nconnect {}, must be between 1 and {}", nconnect, nconnect::MAX_NCONNECT)));
+    }
+    // XXX: mock connections of a pool all go to same mock server, like real ones go to same server
+    let server = std::env::var("TEST_USING_MOCKS").is_ok().then(mock::Server::new);
+    let mut connections = Vec::new();
+    for _ in 0..nconnect {
+        let server = server.clone();
+        connections.push(reconnect::Reconnect::new(url.clone(), options, move |url, options| connect_url(url.to_string(), options, server.as_ref()))?);
+    }
+    if connections.len() == 1 {
+        return Ok(connections.remove(0));
+    }
+    Ok(nconnect::Pool::new(connections))
+}
+
+fn connect_url(url: String, options: &ConnectOptions, server: Option<&mock::Server>) -> Result<Box<dyn NFS>> {
+    let credentials = &options.credentials;
+    if let Some(server) = server {
+        let mut nfs = mock::NFS3::connect_to(url, server);
+        nfs.set_credentials(credentials)?;
+        Ok(nfs)
     } else if std::env::var("TEST_USING_PURE_RUST").is_ok() {
-        nfs_rs::NFS3::connect(url)
+        nfs_rs::NFS3::connect(url, credentials)
     } else {
-        let res = libnfs::NFS3::connect(url.clone());
+        let res = libnfs::NFS3::connect(url.clone(), credentials, options.retry.timeout_ms);
         if res.is_ok() {
             return res;
         }
-        // XXX: attempt using nfs_rs, since it has support for NFSv4.1
-        nfs_rs::NFS3::connect(url)
+        // XXX: attempt using nfs_rs, since it has support for NFSv4.1 and auxiliary gids
+        nfs_rs::NFS3::connect(url, credentials)
             .map_err(|_err| res.unwrap_err()) // XXX: return original error from libnfs
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tls_options_are_checked() {
+        assert!(TlsOptions::default().check("nfs://localhost/export").is_ok());
+        assert_eq!(TlsOptions::default().check("nfs://localhost/export?xprtsec=tls").unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+        assert_eq!(TlsOptions::default().check("nfs://localhost/export?xprtsec=bogus").unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        let tls = TlsOptions{xprtsec: Some("mtls".into())};
+        assert_eq!(tls.check("nfs://localhost/export?xprtsec=none").unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+        let none = TlsOptions{xprtsec: Some("none".into())};
+        assert!(none.check("nfs://localhost/export?xprtsec=tls").is_ok());
+    }
+
+    #[test]
+    fn entry_types_and_names_do_not_panic() {
+        assert_eq!(NFSEntryType::try_from(2).unwrap(), NFSEntryType::Directory);
+        assert_eq!(NFSEntryType::try_from(42).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(NFSEntryType::from_mode(SFlag::S_IFIFO.bits() as u64 | 0o644), NFSEntryType::NamedPipe);
+        assert_eq!(NFSEntryType::from_mode(0o644), NFSEntryType::Unknown);
+        assert_eq!(NFSEntryType::Unknown.to_mode(), 0);
+        assert_eq!(name_from_bytes(b"plain".to_vec()), ("plain".to_string(), None));
+        assert_eq!(name_from_bytes(b"caf\xe9".to_vec()), ("caf\u{fffd}".to_string(), Some(b"caf\xe9".to_vec())));
+    }
+
+    #[test]
+    fn credentials_apply_to_url() {
+        let credentials = Credentials{uid: Some(1000), gid: None, aux_gids: Some(vec![10, 20])};
+        assert_eq!(credentials.apply_to_url("nfs://localhost/export"), "nfs://localhost/export?uid=1000&auxgids=10,20");
+        assert_eq!(credentials.apply_to_url("nfs://localhost/export?uid=0&gid=0&rsize=8192"), "nfs://localhost/export?gid=0&rsize=8192&uid=1000&auxgids=10,20");
+        assert_eq!(Credentials::default().apply_to_url("nfs://localhost/export?uid=0"), "nfs://localhost/export?uid=0");
+    }
+}
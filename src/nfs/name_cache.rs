@@ -0,0 +1,446 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Error;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use nix::sys::stat::SFlag;
+
+use super::{Ace, CacheStats, CreateMode, Credentials, NFS, NFSEntryType, NFSStat64, NFSDirectory, NFSFile, Result};
+
+const MAX_ENTRIES: usize = 65536;
+const MAX_CREDENTIALS: usize = 8; // XXX: same as number of mounts per credentials kept by backends
+const TTL: Duration = Duration::from_secs(60);
+
+// Change identifies a version of a directory, so that names cached for it can be dropped as soon as it is seen to change.
+type Change = (u64, u64, u64, u64);
+
+fn get_change(stat: &NFSStat64) -> Change {
+    (stat.mtime, stat.mtime_nsec, stat.ctime, stat.ctime_nsec)
+}
+
+fn get_entry_type(stat: &NFSStat64) -> NFSEntryType {
+    match SFlag::from_bits_truncate(stat.mode as u32 & SFlag::S_IFMT.bits()) {
+        SFlag::S_IFBLK => NFSEntryType::Block,
+        SFlag::S_IFCHR => NFSEntryType::Character,
+        SFlag::S_IFDIR => NFSEntryType::Directory,
+        SFlag::S_IFIFO => NFSEntryType::NamedPipe,
+        SFlag::S_IFLNK => NFSEntryType::Symlink,
+        SFlag::S_IFSOCK => NFSEntryType::Socket,
+        _ => NFSEntryType::File,
+    }
+}
+
+fn not_found() -> Error {
+    Error::new(std::io::ErrorKind::NotFound, "not found")
+}
+
+// Name is what a name in a directory was last looked up to, where no target means that there was no such entry.
+#[derive(Debug)]
+struct Name {
+    target: Option<(Vec<u8>, NFSEntryType)>,
+    cached: Instant,
+}
+
+#[derive(Debug)]
+struct Directory {
+    change: Change,
+    validated: Instant,
+    names: HashMap<String, Name>,
+}
+
+#[derive(Debug, Default)]
+struct Directories {
+    dirs: HashMap<Vec<u8>, Directory>,
+    len: usize,
+}
+
+// NameCache is a directory name lookup cache (DNLC), which remembers what names were looked up to in which directory,
+// including that they did not exist unless told not to, so that resolving a path does not take a LOOKUP per component
+// every time. Names cached for a directory are dropped once its attributes are seen to change, once they have been
+// cached for a minute, and whenever they are created or removed through this connection. Attributes of a directory are
+// trusted for as long as they would be cached, so that only looking up names in it again after that takes a GETATTR.
+// Each credentials get names of their own, since whether a name can be looked up at all depends on who asks.
+#[derive(Debug)]
+pub(super) struct NameCache {
+    nfs: Box<dyn NFS>,
+    negative: bool,
+    dir_timeout: Duration,
+    credentials: Credentials,
+    root_fh: Mutex<Option<Vec<u8>>>,
+    dirs: Mutex<VecDeque<(Credentials, Directories)>>, // least recently used first
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl NameCache {
+    pub(super) fn new(nfs: Box<dyn NFS>, negative: bool, dir_timeout: Duration) -> Box<dyn NFS> {
+        Box::new(NameCache{nfs, negative, dir_timeout, credentials: Default::default(), root_fh: Default::default(), dirs: Default::default(), hits: AtomicU64::new(0), misses: AtomicU64::new(0)})
+    }
+
+    // Runs op with names cached for current credentials, which are set up if there are none yet.
+    fn with_dirs<T>(&self, op: impl FnOnce(&mut Directories) -> T) -> T {
+        let mut all = self.dirs.lock().unwrap();
+        let dirs = match all.iter().position(|(other, _)| *other == self.credentials) {
+            Some(index) => all.remove(index).unwrap(),
+            None => {
+                if all.len() >= MAX_CREDENTIALS {
+                    let _ = all.pop_front();
+                }
+                (self.credentials.clone(), Default::default())
+            }
+        };
+        all.push_back(dirs);
+        op(&mut all.back_mut().unwrap().1)
+    }
+
+    // Returns change of directory, as last seen if that was recently enough to trust that it has not changed since.
+    fn get_dir_change(&self, dir_fh: &[u8]) -> Result<Change> {
+        let validated = self.with_dirs(|dirs| dirs.dirs.get(dir_fh)
+            .filter(|dir| dir.validated.elapsed() < self.dir_timeout)
+            .map(|dir| dir.change));
+        if let Some(change) = validated {
+            return Ok(change);
+        }
+        let change = get_change(&self.nfs.getattr_fh(dir_fh)?);
+        self.with_dirs(|Directories{dirs, len}| {
+            if let Some(dir) = dirs.get_mut(dir_fh) {
+                if dir.change != change {
+                    *len -= dir.names.len();
+                    dir.names.clear();
+                    dir.change = change;
+                }
+                dir.validated = Instant::now();
+            }
+        });
+        Ok(change)
+    }
+
+    fn get(&self, dir_fh: &[u8], change: Change, name: &str) -> Option<Option<(Vec<u8>, NFSEntryType)>> {
+        self.with_dirs(|Directories{dirs, len}| {
+            let dir = dirs.get_mut(dir_fh)?;
+            if dir.change != change {
+                *len -= dir.names.len();
+                dir.names.clear();
+                dir.change = change;
+                return None;
+            }
+            dir.names.get(name).filter(|name| name.cached.elapsed() < TTL).map(|name| name.target.clone())
+        })
+    }
+
+    fn store(&self, dir_fh: &[u8], change: Change, name: &str, target: Option<(Vec<u8>, NFSEntryType)>) {
+        self.with_dirs(|Directories{dirs, len}| {
+            if *len >= MAX_ENTRIES {
+                for dir in dirs.values_mut() {
+                    dir.names.retain(|_, name| name.cached.elapsed() < TTL);
+                }
+                dirs.retain(|_, dir| !dir.names.is_empty());
+                *len = dirs.values().map(|dir| dir.names.len()).sum();
+                if *len >= MAX_ENTRIES {
+                    dirs.clear();
+                    *len = 0;
+                }
+            }
+            let dir = dirs.entry(dir_fh.to_vec()).or_insert_with(|| Directory{change, validated: Instant::now(), names: HashMap::new()});
+            if dir.change != change {
+                *len -= dir.names.len();
+                dir.names.clear();
+                dir.change = change;
+            }
+            if dir.names.insert(name.into(), Name{target, cached: Instant::now()}).is_none() {
+                *len += 1;
+            }
+        })
+    }
+
+    // Drops name from every directory, for all credentials, since path does not tell which file handle its parent
+    // directory has.
+    fn forget(&self, path: &str) {
+        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        for (_, Directories{dirs, len}) in self.dirs.lock().unwrap().iter_mut() {
+            for dir in dirs.values_mut() {
+                if dir.names.remove(name).is_some() {
+                    *len -= 1;
+                }
+            }
+        }
+    }
+
+    fn lookup_child(&self, dir_fh: &[u8], name: &str) -> Result<(Vec<u8>, NFSEntryType)> {
+        let change = self.get_dir_change(dir_fh)?;
+        if let Some(target) = self.get(dir_fh, change, name) {
+            let _ = self.hits.fetch_add(1, Ordering::SeqCst);
+            return target.ok_or_else(not_found);
+        }
+        let _ = self.misses.fetch_add(1, Ordering::SeqCst);
+        match self.nfs.lookup_in(dir_fh, name) {
+            Ok(fh) => {
+                let entry_type = get_entry_type(&self.nfs.getattr_fh(&fh)?);
+                self.store(dir_fh, change, name, Some((fh.clone(), entry_type.clone())));
+                Ok((fh, entry_type))
+            },
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::NotFound && self.negative {
+                    self.store(dir_fh, change, name, None);
+                }
+                Err(e)
+            },
+        }
+    }
+
+    fn get_root_fh(&self) -> Option<Vec<u8>> {
+        let mut root_fh = self.root_fh.lock().unwrap();
+        if root_fh.is_none() {
+            *root_fh = self.nfs.lookup_fh("/").ok();
+        }
+        root_fh.clone()
+    }
+
+    // Resolves path one component at a time through cache, or gives up if it cannot be done that way, e.g. because it
+    // goes through a symlink or server does not support file handles.
+    fn resolve(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let Some(mut fh) = self.get_root_fh() else {
+            return Ok(None);
+        };
+        let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty() && *name != ".").collect();
+        for (index, name) in names.iter().enumerate() {
+            if *name == ".." {
+                return Ok(None);
+            }
+            match self.lookup_child(&fh, name) {
+                Ok((_, NFSEntryType::Symlink)) if index + 1 < names.len() => return Ok(None),
+                Ok((child_fh, _)) => fh = child_fh,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(e),
+                Err(_) => return Ok(None),
+            }
+        }
+        Ok(Some(fh))
+    }
+}
+
+impl NFS for NameCache {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.nfs.access(path, mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.nfs.stat64(path)
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        match self.resolve(path)? {
+            Some(fh) => Ok(fh),
+            None => self.nfs.lookup_fh(path),
+        }
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        match self.lookup_child(dir_fh, name) {
+            Ok((fh, _)) => Ok(fh),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(e),
+            Err(_) => self.nfs.lookup_in(dir_fh, name), // XXX: directory attributes may not be available
+        }
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.nfs.getattr_fh(fh)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.nfs.set_credentials(credentials)?;
+        self.credentials = credentials.clone();
+        Ok(())
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.readdir_fh(fh)
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.nfs.open_fh(fh, flags)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.nfs.readlink(path)
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.nfs.lchmod(path, mode)
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.opendir(path)
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        let res = self.nfs.mkdir(path, mode);
+        self.forget(path);
+        res
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        let res = self.nfs.create(path, flags, mode, how);
+        self.forget(path);
+        res
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        let res = self.nfs.mknod(path, mode, rdev);
+        self.forget(path);
+        res
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        let res = self.nfs.rmdir(path);
+        self.forget(path);
+        res
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        let res = self.nfs.unlink(path);
+        self.forget(path);
+        res
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        let res = self.nfs.open(path, flags);
+        self.forget(path); // XXX: may have created file
+        res
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        self.nfs.truncate(path, len)
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.nfs.getxattr(path, name)
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.nfs.setxattr(path, name, value)
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.nfs.listxattrs(path)
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        self.nfs.removexattr(path, name)
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.nfs.getacl(path)
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        self.nfs.setacl(path, acl)
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.nfs.supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        let dirs = self.dirs.lock().unwrap();
+        CacheStats{
+            name_hits: self.hits.load(Ordering::SeqCst),
+            name_misses: self.misses.load(Ordering::SeqCst),
+            name_entries: dirs.iter().map(|(_, dirs)| dirs.len as u64).sum(),
+            ..self.nfs.cache_stats()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::fcntl::OFlag;
+
+    use super::*;
+    use super::super::mock;
+
+    #[test]
+    fn lookups_are_cached() {
+        let nfs = NameCache::new(mock::NFS3::connect("nfs://localhost/export".into()), true, Duration::ZERO);
+        let fh = nfs.lookup_fh("/first/comment").unwrap();
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.name_hits, stats.name_misses, stats.name_entries), (0, 2, 2));
+        assert_eq!(nfs.lookup_fh("/first/comment").unwrap(), fh);
+        let dir_fh = nfs.lookup_fh("/first").unwrap();
+        assert_eq!(nfs.lookup_in(&dir_fh, "comment").unwrap(), fh);
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.name_hits, stats.name_misses, stats.name_entries), (4, 2, 2));
+    }
+
+    #[test]
+    fn missing_names_are_cached_until_created() {
+        let mut nfs = NameCache::new(mock::NFS3::connect("nfs://localhost/export".into()), true, Duration::ZERO);
+        let dir_fh = nfs.lookup_fh("/first").unwrap();
+        for _ in 0..2 {
+            assert_eq!(nfs.lookup_in(&dir_fh, "new").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        }
+        assert_eq!(nfs.lookup_fh("/first/new").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.name_hits, stats.name_misses), (3, 2));
+        let _ = nfs.create("/first/new", OFlag::O_RDWR.bits() as u32, 0o664, CreateMode::Unchecked).unwrap();
+        assert!(nfs.lookup_in(&dir_fh, "new").is_ok());
+        nfs.unlink("/first/new").unwrap();
+        assert_eq!(nfs.lookup_in(&dir_fh, "new").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(nfs.cache_stats().name_misses, 4);
+    }
+
+    #[test]
+    fn directories_are_trusted_until_timeout() {
+        let server = mock::Server::new();
+        let nfs = NameCache::new(mock::NFS3::connect_to("nfs://localhost/export".into(), &server), true, Duration::from_secs(60));
+        let other = mock::NFS3::connect_to("nfs://localhost/export".into(), &server);
+        let dir_fh = nfs.lookup_fh("/first").unwrap();
+        assert_eq!(nfs.lookup_in(&dir_fh, "new").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        other.mkdir("/first/new", 0o775).unwrap();
+        assert_eq!(nfs.lookup_in(&dir_fh, "new").unwrap_err().kind(), std::io::ErrorKind::NotFound); // XXX: not asked again yet
+        let nfs = NameCache::new(mock::NFS3::connect_to("nfs://localhost/export".into(), &server), true, Duration::ZERO);
+        assert_eq!(nfs.lookup_in(&dir_fh, "gone").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        other.mkdir("/first/gone", 0o775).unwrap();
+        assert!(nfs.lookup_in(&dir_fh, "gone").is_ok());
+    }
+
+    #[test]
+    fn names_are_cached_per_credentials() {
+        let mut nfs = NameCache::new(mock::NFS3::connect("nfs://localhost/export".into()), true, Duration::ZERO);
+        let _ = nfs.lookup_fh("/first/comment").unwrap();
+        nfs.set_credentials(&Credentials{uid: Some(1001), gid: Some(1001), aux_gids: None}).unwrap();
+        let _ = nfs.lookup_fh("/first/comment").unwrap();
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.name_hits, stats.name_misses, stats.name_entries), (0, 4, 4));
+        nfs.set_credentials(&Default::default()).unwrap();
+        let _ = nfs.lookup_fh("/first/comment").unwrap();
+        assert_eq!(nfs.cache_stats().name_hits, 2);
+    }
+
+    #[test]
+    fn missing_names_are_not_cached_unless_asked_to() {
+        let nfs = NameCache::new(mock::NFS3::connect("nfs://localhost/export".into()), false, Duration::ZERO);
+        let dir_fh = nfs.lookup_fh("/first").unwrap();
+        for _ in 0..2 {
+            assert_eq!(nfs.lookup_in(&dir_fh, "new").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        }
+        let stats = nfs.cache_stats();
+        assert_eq!((stats.name_hits, stats.name_misses, stats.name_entries), (0, 3, 1));
+    }
+}
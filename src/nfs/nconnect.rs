@@ -0,0 +1,362 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use super::reconnect::Reopen;
+use super::{read_chunks, write_chunks, Ace, CacheStats, Check, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, Result};
+
+pub(super) const MAX_NCONNECT: u32 = 16; // XXX: same limit as Linux
+
+// Pool spreads reads, writes and directory listings over several transport connections to the same server, so that
+// a mount is not limited to what a single connection can carry. Everything else goes to the first connection.
+//
+// XXX: callers run one operation at a time, so files and listings just take turns between connections, and it is only
+//      the chunks of a single large transfer that actually go over several connections at once
+#[derive(Debug)]
+pub(super) struct Pool {
+    connections: Arc<Vec<Mutex<Box<dyn NFS>>>>,
+    next: AtomicUsize,
+}
+
+impl Pool {
+    pub(super) fn new(connections: Vec<Box<dyn NFS>>) -> Box<dyn NFS> {
+        Box::new(Self::with_connections(connections))
+    }
+
+    fn with_connections(connections: Vec<Box<dyn NFS>>) -> Self {
+        Pool{connections: Arc::new(connections.into_iter().map(Mutex::new).collect()), next: AtomicUsize::new(0)}
+    }
+
+    fn first(&self) -> MutexGuard<'_, Box<dyn NFS>> {
+        self.connections[0].lock().unwrap()
+    }
+
+    fn choose(&self) -> usize {
+        self.next.fetch_add(1, Ordering::SeqCst) % self.connections.len()
+    }
+
+    fn open_on_any(&self, reopen: Reopen, op: impl FnOnce(&mut Box<dyn NFS>) -> Result<Box<dyn NFSFile>>) -> Result<Box<dyn NFSFile>> {
+        let connection = self.choose();
+        let file = op(&mut self.connections[connection].lock().unwrap())?;
+        let others = self.connections.iter().map(|_| OnceLock::new()).collect();
+        Ok(Box::new(PoolFile{connections: self.connections.clone(), connection, file, reopen, others}))
+    }
+
+    fn opendir_on_any(&self, op: impl FnOnce(&mut Box<dyn NFS>) -> Result<Box<dyn NFSDirectory>>) -> Result<Box<dyn NFSDirectory>> {
+        op(&mut self.connections[self.choose()].lock().unwrap())
+    }
+}
+
+impl NFS for Pool {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.first().access(path, mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.first().stat64(path)
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        self.first().lookup_fh(path)
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        self.first().lookup_in(dir_fh, name)
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.first().getattr_fh(fh)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        for connection in self.connections.iter() {
+            connection.lock().unwrap().set_credentials(credentials)?;
+        }
+        Ok(())
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.opendir_on_any(|nfs| nfs.readdir_fh(fh))
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.open_on_any(Reopen::Fh(fh.to_vec(), flags), |nfs| nfs.open_fh(fh, flags))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.first().readlink(path)
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.first().lchmod(path, mode)
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        self.opendir_on_any(|nfs| nfs.opendir(path))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        self.first().mkdir(path, mode)
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        self.open_on_any(Reopen::after_create(path, flags), |nfs| nfs.create(path, flags, mode, how))
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        self.first().mknod(path, mode, rdev)
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        self.first().rmdir(path)
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        self.first().unlink(path)
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.open_on_any(Reopen::after_open(path, flags), |nfs| nfs.open(path, flags))
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        self.first().truncate(path, len)
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.first().getxattr(path, name)
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.first().setxattr(path, name, value)
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.first().listxattrs(path)
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        self.first().removexattr(path, name)
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.first().getacl(path)
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        self.first().setacl(path, acl)
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.first().supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.first().cache_stats()
+    }
+}
+
+// PoolFile is opened on one connection, and opened again on the others the first time a transfer is large enough to be
+// spread over them, after which it stays open on them for later transfers.
+#[derive(Debug)]
+struct PoolFile {
+    connections: Arc<Vec<Mutex<Box<dyn NFS>>>>,
+    connection: usize,
+    file: Box<dyn NFSFile>,
+    reopen: Reopen,
+    others: Vec<OnceLock<Option<OtherFile>>>, // indexed by connection, None if file could not be opened on it
+}
+
+#[derive(Debug)]
+struct OtherFile(Box<dyn NFSFile>);
+
+// XXX: file opened again on another connection is only ever used and dropped while holding lock of that connection,
+//      which is Send and Sync, so that it is never touched from two threads at once
+unsafe impl Send for OtherFile {}
+unsafe impl Sync for OtherFile {}
+
+impl PoolFile {
+    // Runs op with this file on calling thread and, if there is more than one chunk, also with the file opened on up to
+    // one other connection per remaining chunk, each from a thread of its own.
+    fn in_parallel<T: Send>(&self, chunks: usize, op: &(dyn Fn(&dyn NFSFile) -> Result<T> + Sync)) -> Result<Vec<T>> {
+        let reopen = &self.reopen;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = self.connections.iter().zip(&self.others).enumerate()
+                .filter(|(index, _)| *index != self.connection)
+                .take(chunks.saturating_sub(1))
+                .map(|(_, (connection, other))| scope.spawn(move || {
+                    let mut nfs = connection.lock().unwrap();
+                    // XXX: if file cannot be opened on this connection, then just leave its share to the others
+                    let Some(OtherFile(file)) = other.get_or_init(|| reopen.open(&mut nfs).ok().map(OtherFile)) else {
+                        return Ok(None);
+                    };
+                    op(file.as_ref()).map(Some)
+                }))
+                .collect();
+            let mut results = vec![op(self.file.as_ref())?];
+            for worker in workers {
+                results.extend(worker.join().unwrap()?);
+            }
+            Ok(results)
+        })
+    }
+}
+
+impl Drop for PoolFile {
+    fn drop(&mut self) {
+        for (connection, other) in self.connections.iter().zip(&mut self.others) {
+            if let Some(other) = other.take() {
+                let _nfs = connection.lock().unwrap();
+                drop(other);
+            }
+        }
+    }
+}
+
+impl NFSFile for PoolFile {
+    fn fstat64(&self) -> Result<NFSStat64> {
+        self.file.fstat64()
+    }
+
+    fn get_max_read_size(&self) -> u64 {
+        self.file.get_max_read_size()
+    }
+
+    fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
+        self.file.pread_into(count, offset, buffer)
+    }
+
+    fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
+        self.file.pwrite(buffer, offset)
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        self.file.lock(offset, len, exclusive, wait)
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.unlock(offset, len)
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        self.file.seek_data(offset)
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        self.file.seek_hole(offset)
+    }
+
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.allocate(offset, len)
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.punch_hole(offset, len)
+    }
+
+    fn pread_all_into(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        let chunk_size = self.get_max_read_size().max(1) as usize;
+        let len = buffer.len() as u64;
+        let count = buffer.len().div_ceil(chunk_size);
+        let chunks = Mutex::new(buffer.chunks_mut(chunk_size).enumerate());
+        let failed = AtomicBool::new(false);
+        let ends = self.in_parallel(count, &|file| read_chunks(file, offset, chunk_size, &chunks, check, &failed))?;
+        Ok(ends.into_iter().flatten().min().unwrap_or(len))
+    }
+
+    fn pwrite_all(&self, buffer: &[u8], offset: u64, check: &Check) -> Result<()> {
+        let chunk_size = self.get_max_read_size().max(1) as usize; // XXX: servers practically always use same size for writes
+        let count = buffer.len().div_ceil(chunk_size);
+        let chunks = Mutex::new(buffer.chunks(chunk_size).enumerate());
+        let failed = AtomicBool::new(false);
+        let _ = self.in_parallel(count, &|file| write_chunks(file, offset, chunk_size, &chunks, check, &failed))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Error, ErrorKind};
+
+    use nix::fcntl::OFlag;
+
+    use super::*;
+    use super::super::mock;
+
+    #[test]
+    fn pool_takes_turns_between_connections() {
+        let pool = Pool::with_connections(mock::NFS3::connect_many("nfs://localhost/export".into(), 3));
+        assert_eq!((0..4).map(|_| pool.choose()).collect::<Vec<_>>(), vec![0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn pool_transfers_chunks_over_all_connections() {
+        let mut pool = Pool::new(mock::NFS3::connect_many("nfs://localhost/export?rsize=8192".into(), 4));
+        let data: Vec<u8> = (0..100000u32).map(|i| (i % 251) as u8).collect();
+        let file = pool.create("/big", (OFlag::O_WRONLY | OFlag::O_CREAT).bits() as u32, 0o644, CreateMode::Unchecked).unwrap();
+        file.pwrite_all(&data, 0, &|| Ok(())).unwrap();
+        let file = pool.open("/big", OFlag::O_RDONLY.bits() as u32).unwrap();
+        let checks = AtomicUsize::new(0);
+        let mut buffer = vec![0u8; 150000];
+        let len = file.pread_all_into(0, &mut buffer, &|| {
+            let _ = checks.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }).unwrap();
+        assert_eq!(len, 100000);
+        assert_eq!(&buffer[..100000], data.as_slice());
+        assert_eq!(checks.load(Ordering::SeqCst), 150000usize.div_ceil(8192));
+    }
+
+    #[test]
+    fn pool_files_outlive_it() {
+        let mut pool = Pool::new(mock::NFS3::connect_many("nfs://localhost/export?rsize=8192".into(), 2));
+        let file = pool.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        drop(pool);
+        let mut buffer = vec![0u8; 123];
+        assert_eq!(file.pread_all_into(0, &mut buffer, &|| Ok(())).unwrap(), 123);
+    }
+
+    #[test]
+    fn pool_stops_transfer_when_check_fails() {
+        let mut pool = Pool::new(mock::NFS3::connect_many("nfs://localhost/export?rsize=8192".into(), 2));
+        let file = pool.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        let mut buffer = vec![0u8; 100000];
+        let err = file.pread_all_into(0, &mut buffer, &|| Err(Error::new(ErrorKind::Interrupted, "aborted"))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn pool_file_stays_open_on_other_connections_between_transfers() {
+        let pool = Pool::with_connections(mock::NFS3::connect_many("nfs://localhost/export?rsize=8192".into(), 3));
+        let flags = (OFlag::O_RDWR | OFlag::O_CREAT).bits() as u32;
+        let file = pool.connections[0].lock().unwrap().create("/big", flags, 0o644, CreateMode::Unchecked).unwrap();
+        let others = pool.connections.iter().map(|_| OnceLock::new()).collect();
+        let file = PoolFile{connections: pool.connections.clone(), connection: 0, file, reopen: Reopen::after_create("/big", flags), others};
+        let opened = |file: &PoolFile| file.others.iter()
+            .map(|other| other.get().and_then(Option::as_ref).map(|OtherFile(file)| &**file as *const dyn NFSFile as *const u8))
+            .collect::<Vec<_>>();
+        let data: Vec<u8> = (0..50000u32).map(|i| (i % 251) as u8).collect();
+        file.pwrite_all(&data, 0, &|| Ok(())).unwrap();
+        let after_write = opened(&file);
+        assert!(after_write[0].is_none() && after_write[1].is_some() && after_write[2].is_some());
+        let mut buffer = vec![0u8; 50000];
+        assert_eq!(file.pread_all_into(0, &mut buffer, &|| Ok(())).unwrap(), 50000);
+        assert_eq!(buffer, data);
+        assert_eq!(opened(&file), after_write);
+    }
+}
@@ -15,57 +15,142 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use bytes::BufMut;
-use nfs_rs::{Mount, parse_url_and_mount};
+use nfs_rs::{Attr, Mount, parse_url_and_mount};
+use std::collections::VecDeque;
 use std::io::Error;
 use std::sync::{Arc, RwLock};
 
-use super::{NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, Result, Time};
+use super::{makerdev, Ace, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, NFSDirEntry, NFSEntryType, Result, Time};
 
 const NFS_ENTRY_TYPE_DIR: u32 = 2;
+const NFS_ENTRY_TYPE_LNK: u32 = 5;
+const NFS_BLKSIZE: u64 = 4096; // XXX: mimic libnfs, which reports a fixed block size since NFS does not provide one
+const MAX_MOUNTS: usize = 8;
+
+fn spec_data_to_rdev(spec_data: &[u32; 2]) -> u64 {
+    makerdev(spec_data[0], spec_data[1])
+}
+
+fn used_to_blocks(used: u64) -> u64 {
+    used.div_ceil(512) // XXX: mimic libnfs, which reports number of 512-byte blocks allocated
+}
+
+fn type_to_mode(type_: u32) -> u64 {
+    // XXX: mimic libnfs, which includes file type bits in mode
+    NFSEntryType::try_from(type_).map_or(0, |type_| type_.to_mode())
+}
+
+fn fattr_to_stat64(fattr: &Attr) -> NFSStat64 {
+    NFSStat64{
+        dev: fattr.fsid,
+        ino: fattr.fileid,
+        mode: type_to_mode(fattr.type_) | fattr.file_mode as u64,
+        nlink: fattr.nlink.into(),
+        uid: fattr.uid.into(),
+        gid: fattr.gid.into(),
+        rdev: spec_data_to_rdev(&fattr.spec_data),
+        size: fattr.filesize,
+        used: fattr.used,
+        blksize: NFS_BLKSIZE,
+        blocks: used_to_blocks(fattr.used),
+        atime: fattr.atime.seconds.into(),
+        mtime: fattr.mtime.seconds.into(),
+        ctime: fattr.ctime.seconds.into(),
+        atime_nsec: fattr.atime.nseconds.into(),
+        mtime_nsec: fattr.mtime.nseconds.into(),
+        ctime_nsec: fattr.ctime.nseconds.into(),
+    }
+}
 
 #[derive(Debug)]
 pub(super) struct NFS3{
     mount: Arc<RwLock<Box<dyn Mount>>>,
+    url: String,
+    credentials: Credentials,
+    mounts: VecDeque<(Credentials, Arc<RwLock<Box<dyn Mount>>>)>,
 }
 
 impl NFS3 {
-    pub(super) fn connect(url: String) -> Result<Box<dyn NFS>> {
-        let mount = parse_url_and_mount(url.as_str())?;
-        Ok(Box::new(NFS3{mount: Arc::new(RwLock::new(mount))}))
+    pub(super) fn connect(url: String, credentials: &Credentials) -> Result<Box<dyn NFS>> {
+        let mount = Arc::new(RwLock::new(parse_url_and_mount(credentials.apply_to_url(&url).as_str())?));
+        let mounts = VecDeque::from([(credentials.clone(), mount.clone())]);
+        Ok(Box::new(NFS3{mount, url, credentials: credentials.clone(), mounts}))
     }
 }
 
 impl NFS for NFS3 {
-    fn access(&self, path: &str, mode: u32) -> Result<()> {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        let mount = self.mount.read().unwrap();
+        let granted = mount.access_path(path, mode)?;
+        Ok(granted & mode)
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        let mount = self.mount.read().unwrap();
+        mount.getattr_path(path).map(|fattr| fattr_to_stat64(&fattr))
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        let mount = self.mount.read().unwrap();
+        mount.lookup_path(path).map(|obj_res| obj_res.fh)
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        let mount = self.mount.read().unwrap();
+        mount.lookup(&dir_fh.to_vec(), name).map(|obj_res| obj_res.fh)
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
         let mount = self.mount.read().unwrap();
-        let res = mount.access_path(path, mode)?;
-        if res != mode {
-            return Err(Error::new(std::io::ErrorKind::Other, "access denied"));
+        mount.getattr(&fh.to_vec()).map(|fattr| fattr_to_stat64(&fattr))
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        if *credentials == self.credentials {
+            return Ok(());
         }
+        // XXX: nfs-rs binds credentials to a mount, so keep the most recently used mounts, one per set of credentials,
+        //      and have files and directories hold on to the mount they were opened with, so that evicting a mount
+        //      or switching to another one never changes the credentials used for an already open file
+        let mount = match self.mounts.iter().position(|(creds, _)| creds == credentials) {
+            Some(index) => self.mounts.remove(index).unwrap().1,
+            None => Arc::new(RwLock::new(parse_url_and_mount(credentials.apply_to_url(&self.url).as_str())?)),
+        };
+        if self.mounts.len() >= MAX_MOUNTS {
+            let _ = self.mounts.pop_front();
+        }
+        self.mounts.push_back((credentials.clone(), mount.clone()));
+        self.mount = mount;
+        self.credentials = credentials.clone();
         Ok(())
     }
 
-    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
         let mount = self.mount.read().unwrap();
-        mount.getattr_path(path).map(|fattr| NFSStat64{
-            dev: ((fattr.spec_data[0] as u64) << 32) + (fattr.spec_data[1] as u64), // FIXME: verify that this is correct
-            ino: fattr.fileid, // FIXME: verify that this is correct
-            mode: fattr.file_mode.into(),
-            nlink: fattr.nlink.into(),
-            uid: fattr.uid.into(),
-            gid: fattr.gid.into(),
-            rdev: 0, // FIXME: verify that this is correct
-            size: fattr.filesize,
-            used: fattr.used,
-            blksize: 0, // FIXME: verify that this is correct
-            blocks: 0, // FIXME: verify that this is correct
-            atime: fattr.atime.seconds.into(),
-            mtime: fattr.mtime.seconds.into(),
-            ctime: fattr.ctime.seconds.into(),
-            atime_nsec: fattr.atime.nseconds.into(),
-            mtime_nsec: fattr.mtime.nseconds.into(),
-            ctime_nsec: fattr.ctime.nseconds.into(),
-         })
+        let fh = fh.to_vec();
+        let attr = mount.getattr(&fh)?;
+        if attr.type_ != NFS_ENTRY_TYPE_DIR {
+            return Err(Error::new(std::io::ErrorKind::InvalidData, "not a directory"));
+        }
+        Ok(Box::new(NFSDirectory3{mount: self.mount.clone(), dir_fh: fh, entries: None, index: 0}))
+    }
+
+    fn open_fh(&mut self, fh: &[u8], _flags: u32) -> Result<Box<dyn NFSFile>> {
+        let mount = self.mount.write().unwrap();
+        let fh = fh.to_vec();
+        let attr = mount.getattr(&fh)?;
+        if attr.type_ == NFS_ENTRY_TYPE_DIR {
+            return Err(Error::new(std::io::ErrorKind::InvalidData, "is a directory"));
+        }
+        Ok(Box::new(NFSFile3{mount: self.mount.clone(), fh}))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        let mount = self.mount.read().unwrap();
+        mount.readlink_path(path).map_err(|e| match mount.getattr_path(path) {
+            Ok(attr) if attr.type_ != NFS_ENTRY_TYPE_LNK => Error::new(std::io::ErrorKind::InvalidInput, "not a symlink"),
+            _ => e,
+        })
     }
 
     fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
@@ -81,7 +166,7 @@ impl NFS for NFS3 {
         if attr.type_ != NFS_ENTRY_TYPE_DIR {
             return Err(Error::new(std::io::ErrorKind::InvalidData, "not a directory"));
         }
-        Ok(Box::new(NFSDirectory3{nfs: self, dir_fh: fh, entries: None, index: 0}))
+        Ok(Box::new(NFSDirectory3{mount: self.mount.clone(), dir_fh: fh, entries: None, index: 0}))
     }
 
     fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
@@ -89,11 +174,25 @@ impl NFS for NFS3 {
         mount.mkdir_path(path, mode).map(|_| ())
     }
 
-    fn create(&mut self, path: &str, _flags: u32, mode: u32) -> Result<Box<dyn NFSFile>> {
+    fn create(&mut self, path: &str, _flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        // XXX: nfs-rs only ever sends UNCHECKED, and looking before creating would leave a window for another client
+        if how.is_exclusive() {
+            return Err(Error::new(std::io::ErrorKind::Unsupported, "exclusive create not supported by nfs-rs"));
+        }
         let mount = self.mount.write().unwrap();
         let obj_res = mount.create_path(path, mode)?;
         let fh = obj_res.fh;
-        Ok(Box::new(NFSFile3{nfs: self, fh}))
+        Ok(Box::new(NFSFile3{mount: self.mount.clone(), fh}))
+    }
+
+    fn mknod(&self, path: &str, _mode: u32, _rdev: u64) -> Result<()> {
+        // XXX: nfs-rs has no MKNOD to send, so fail the way server would when name is taken and only otherwise report
+        // that special files cannot be made over this connection
+        let mount = self.mount.read().unwrap();
+        if mount.lookup_path(path).is_ok() {
+            return Err(Error::new(std::io::ErrorKind::AlreadyExists, "file exists"));
+        }
+        Err(Error::new(std::io::ErrorKind::Unsupported, "special files not supported by nfs-rs"))
     }
 
     fn rmdir(&self, path: &str) -> Result<()> {
@@ -114,18 +213,44 @@ impl NFS for NFS3 {
         if attr.type_ == NFS_ENTRY_TYPE_DIR {
             return Err(Error::new(std::io::ErrorKind::InvalidData, "is a directory"));
         }
-        Ok(Box::new(NFSFile3{nfs: self, fh}))
+        Ok(Box::new(NFSFile3{mount: self.mount.clone(), fh}))
     }
 
     fn truncate(&self, path: &str, len: u64) -> Result<()> {
         let mount = self.mount.read().unwrap();
         mount.setattr_path(path, true, None, None, None, Some(len), None, None)
     }
+
+    // XXX: GETXATTR, SETXATTR, LISTXATTRS and REMOVEXATTR of RFC 8276 are NFSv4.2 only
+    fn getxattr(&self, _path: &str, _name: &str) -> Result<Vec<u8>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by NFSv3"))
+    }
+
+    fn setxattr(&self, _path: &str, _name: &str, _value: &[u8]) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by NFSv3"))
+    }
+
+    fn listxattrs(&self, _path: &str) -> Result<Vec<String>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by NFSv3"))
+    }
+
+    fn removexattr(&self, _path: &str, _name: &str) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "extended attributes not supported by NFSv3"))
+    }
+
+    // XXX: acl attribute is NFSv4 only, and NFSACL side protocol of NFSv3 carries POSIX draft ACLs rather than NFSv4 ones
+    fn getacl(&self, _path: &str) -> Result<Vec<Ace>> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "ACLs not supported by NFSv3"))
+    }
+
+    fn setacl(&self, _path: &str, _acl: &[Ace]) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "ACLs not supported by NFSv3"))
+    }
 }
 
 #[derive(Debug)]
 pub struct NFSDirectory3 {
-    nfs: *const NFS3,
+    mount: Arc<RwLock<Box<dyn Mount>>>,
     dir_fh: Vec<u8>,
     entries: Option<Vec<NFSDirEntry>>,
     index: usize,
@@ -138,7 +263,7 @@ impl Iterator for NFSDirectory3 {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.entries.is_none() {
-            let mount = unsafe { (*self.nfs).mount.read().unwrap() };
+            let mount = self.mount.read().unwrap();
             let res = mount.readdirplus(&self.dir_fh);
             if res.is_err() {
                 return Some(Err(res.unwrap_err()));
@@ -152,19 +277,20 @@ impl Iterator for NFSDirectory3 {
                     attr.type_ = 2;
                 }
                 entries.push(NFSDirEntry{
-                    path: entry.file_name.clone(), // FIXME: verify that this is correct
-                    inode: entry.fileid, // FIXME: verify that this is correct
-                    d_type: attr.type_.into(),
-                    mode: attr.file_mode,
+                    path: entry.file_name.clone(),
+                    raw_path: None, // XXX: nfs-rs only hands out names as strings
+                    inode: entry.fileid,
+                    d_type: NFSEntryType::try_from(attr.type_).unwrap_or(NFSEntryType::Unknown),
+                    mode: type_to_mode(attr.type_) as u32 | attr.file_mode,
                     size: attr.filesize,
                     used: attr.used,
                     uid: attr.uid,
                     gid: attr.gid,
                     nlink: attr.nlink,
-                    dev: ((attr.spec_data[0] as u64) << 32) + (attr.spec_data[1] as u64), // FIXME: verify that this is correct
-                    rdev: 0, // FIXME: verify that this is correct
-                    blksize: 0, // FIXME: verify that this is correct
-                    blocks: 0, // FIXME: verify that this is correct
+                    dev: attr.fsid,
+                    rdev: spec_data_to_rdev(&attr.spec_data),
+                    blksize: NFS_BLKSIZE,
+                    blocks: used_to_blocks(attr.used),
                     atime: Time{seconds: attr.atime.seconds, nseconds: attr.atime.nseconds},
                     mtime: Time{seconds: attr.mtime.seconds, nseconds: attr.mtime.nseconds},
                     ctime: Time{seconds: attr.ctime.seconds, nseconds: attr.ctime.nseconds},
@@ -189,48 +315,39 @@ impl Iterator for NFSDirectory3 {
 
 #[derive(Debug)]
 pub struct NFSFile3 {
-    nfs: *const NFS3,
+    mount: Arc<RwLock<Box<dyn Mount>>>,
     fh: Vec<u8>,
 }
 
 impl NFSFile for NFSFile3 {
     fn fstat64(&self) -> Result<NFSStat64> {
-        let mount = unsafe { (*self.nfs).mount.read().unwrap() };
-        mount.getattr(&self.fh).map(|fattr| NFSStat64{
-            dev: ((fattr.spec_data[0] as u64) << 32) + (fattr.spec_data[1] as u64), // FIXME: verify that this is correct
-            ino: fattr.fileid, // FIXME: verify that this is correct
-            mode: fattr.file_mode.into(),
-            nlink: fattr.nlink.into(),
-            uid: fattr.uid.into(),
-            gid: fattr.gid.into(),
-            rdev: 0, // FIXME: verify that this is correct
-            size: fattr.filesize,
-            used: fattr.used,
-            blksize: 0, // FIXME: verify that this is correct
-            blocks: 0, // FIXME: verify that this is correct
-            atime: fattr.atime.seconds.into(),
-            mtime: fattr.mtime.seconds.into(),
-            ctime: fattr.ctime.seconds.into(),
-            atime_nsec: fattr.atime.nseconds.into(),
-            mtime_nsec: fattr.mtime.nseconds.into(),
-            ctime_nsec: fattr.ctime.nseconds.into(),
-         })
+        let mount = self.mount.read().unwrap();
+        mount.getattr(&self.fh).map(|fattr| fattr_to_stat64(&fattr))
     }
 
     fn get_max_read_size(&self) -> u64 {
-        let mount = unsafe { (*self.nfs).mount.read().unwrap() };
+        let mount = self.mount.read().unwrap();
         mount.get_max_read_size() as u64
     }
 
     fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
-        let mount = unsafe { (*self.nfs).mount.read().unwrap() };
+        let mount = self.mount.read().unwrap();
         let res = mount.read(&self.fh, offset, count)?;
         buffer.as_mut().put_slice(res.as_slice());
         Ok(res.len() as u32)
     }
 
     fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
-        let mount = unsafe { (*self.nfs).mount.write().unwrap() };
+        let mount = self.mount.write().unwrap();
         mount.write(&self.fh, offset, &buffer.to_vec())
     }
+
+    fn lock(&self, _offset: u64, _len: u64, _exclusive: bool, _wait: bool) -> Result<()> {
+        // XXX: nfs-rs speaks neither NLM nor NFSv4, so there is no LOCK to send
+        Err(Error::new(std::io::ErrorKind::Unsupported, "byte-range locks not supported by nfs-rs"))
+    }
+
+    fn unlock(&self, _offset: u64, _len: u64) -> Result<()> {
+        Err(Error::new(std::io::ErrorKind::Unsupported, "byte-range locks not supported by nfs-rs"))
+    }
 }
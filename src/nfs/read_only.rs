@@ -0,0 +1,238 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::Error;
+
+use nix::fcntl::OFlag;
+
+use super::{ACCESS_DELETE, ACCESS_EXTEND, ACCESS_MODIFY, Ace, CacheStats, Check, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSFile, Result};
+
+pub(crate) const NO_MODIFICATION_ALLOWED: &str = "modification not allowed on read-only connection";
+
+// ReadOnly rejects every operation that could modify the export before it reaches the server.
+#[derive(Debug)]
+pub(super) struct ReadOnly {
+    nfs: Box<dyn NFS>,
+}
+
+impl ReadOnly {
+    pub(super) fn new(nfs: Box<dyn NFS>) -> Box<dyn NFS> {
+        Box::new(ReadOnly{nfs})
+    }
+}
+
+fn no_modification_allowed() -> Error {
+    Error::new(std::io::ErrorKind::ReadOnlyFilesystem, NO_MODIFICATION_ALLOWED)
+}
+
+fn check_flags(flags: u32) -> Result<()> {
+    let flags = OFlag::from_bits_truncate(flags as i32);
+    if flags & OFlag::O_ACCMODE != OFlag::O_RDONLY || flags.intersects(OFlag::O_CREAT | OFlag::O_TRUNC) {
+        return Err(no_modification_allowed());
+    }
+    Ok(())
+}
+
+impl NFS for ReadOnly {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        // XXX: mimic server with read-only export, which never grants anything that modifies
+        self.nfs.access(path, mode & !(ACCESS_MODIFY | ACCESS_EXTEND | ACCESS_DELETE))
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.nfs.stat64(path)
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        self.nfs.lookup_fh(path)
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        self.nfs.lookup_in(dir_fh, name)
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.nfs.getattr_fh(fh)
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.nfs.set_credentials(credentials)
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.readdir_fh(fh)
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        check_flags(flags)?;
+        let file = self.nfs.open_fh(fh, flags)?;
+        Ok(Box::new(ReadOnlyFile{file}))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.nfs.readlink(path)
+    }
+
+    fn lchmod(&self, _path: &str, _mode: u32) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        self.nfs.opendir(path)
+    }
+
+    fn mkdir(&self, _path: &str, _mode: u32) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn create(&mut self, _path: &str, _flags: u32, _mode: u32, _how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        Err(no_modification_allowed())
+    }
+
+    fn mknod(&self, _path: &str, _mode: u32, _rdev: u64) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn rmdir(&self, _path: &str) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn unlink(&self, _path: &str) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        check_flags(flags)?;
+        let file = self.nfs.open(path, flags)?;
+        Ok(Box::new(ReadOnlyFile{file}))
+    }
+
+    fn truncate(&self, _path: &str, _len: u64) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.nfs.getxattr(path, name)
+    }
+
+    fn setxattr(&self, _path: &str, _name: &str, _value: &[u8]) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.nfs.listxattrs(path)
+    }
+
+    fn removexattr(&self, _path: &str, _name: &str) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.nfs.getacl(path)
+    }
+
+    fn setacl(&self, _path: &str, _acl: &[Ace]) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.nfs.supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.nfs.cache_stats()
+    }
+}
+
+#[derive(Debug)]
+struct ReadOnlyFile {
+    file: Box<dyn NFSFile>,
+}
+
+impl NFSFile for ReadOnlyFile {
+    fn fstat64(&self) -> Result<NFSStat64> {
+        self.file.fstat64()
+    }
+
+    fn get_max_read_size(&self) -> u64 {
+        self.file.get_max_read_size()
+    }
+
+    fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
+        self.file.pread_into(count, offset, buffer)
+    }
+
+    fn pwrite(&self, _buffer: &[u8], _offset: u64) -> Result<u32> {
+        Err(no_modification_allowed())
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        if exclusive {
+            return Err(no_modification_allowed());
+        }
+        self.file.lock(offset, len, exclusive, wait)
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.unlock(offset, len)
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        self.file.seek_data(offset)
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        self.file.seek_hole(offset)
+    }
+
+    fn allocate(&self, _offset: u64, _len: u64) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn punch_hole(&self, _offset: u64, _len: u64) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+
+    fn pread_all_into(&self, offset: u64, buffer: &mut [u8], check: &Check) -> Result<u64> {
+        self.file.pread_all_into(offset, buffer, check)
+    }
+
+    fn pwrite_all(&self, _buffer: &[u8], _offset: u64, _check: &Check) -> Result<()> {
+        Err(no_modification_allowed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::mock;
+
+    #[test]
+    fn read_only_rejects_modifications() {
+        let mut nfs = ReadOnly::new(mock::NFS3::connect("nfs://localhost/export".into()));
+        assert_eq!(nfs.mkdir("/new", 0o775).unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert_eq!(nfs.unlink("/annar").unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert_eq!(nfs.rmdir("/first").unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert_eq!(nfs.truncate("/annar", 0).unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert_eq!(nfs.lchmod("/annar", 0o777).unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert!(nfs.create("/new", OFlag::O_WRONLY.bits() as u32, 0o664, CreateMode::Unchecked).is_err());
+        assert!(nfs.open("/annar", OFlag::O_WRONLY.bits() as u32).is_err());
+        let file = nfs.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        assert_eq!(file.pwrite(b"data", 0).unwrap_err().kind(), std::io::ErrorKind::ReadOnlyFilesystem);
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        assert!(nfs.stat64("/annar").is_ok());
+    }
+}
@@ -0,0 +1,508 @@
+// Copyright 2025 NetApp Inc. All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{Debug, Formatter};
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+
+use super::{Ace, CacheStats, ConnectOptions, CreateMode, Credentials, NFS, NFSStat64, NFSDirectory, NFSDirEntry, NFSFile, Result};
+
+const MAX_BACKOFF_MS: u64 = 5000;
+
+type Connect = Box<dyn Fn(&str, &ConnectOptions) -> Result<Box<dyn NFS>> + Send + Sync>;
+
+// XXX: directories and files opened on a connection may still point into it after re-mounting, so they keep it alive
+//      and it gets dropped once the last of them is gone
+type Connection = Arc<RwLock<Box<dyn NFS>>>;
+
+// Reconnect re-mounts when the transport breaks and retries operations with exponential backoff, so that handles keep
+// working across server failover.
+#[derive(Debug)]
+pub(super) struct Reconnect {
+    remount: Arc<Remount>,
+}
+
+// Remount is what Reconnect and files opened through it share, so that files can go on retrying and reopening on a
+// new connection for as long as they are around, even once Reconnect is gone.
+struct Remount {
+    url: String,
+    options: RwLock<ConnectOptions>,
+    connect: Connect,
+    nfs: RwLock<Connection>,
+    generation: RwLock<u64>,
+}
+
+impl Debug for Remount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Remount").field("url", &self.url).field("nfs", &self.nfs).field("generation", &self.generation).finish()
+    }
+}
+
+fn is_transport_error(e: &Error) -> bool {
+    if matches!(e.kind(), ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+        | ErrorKind::ConnectionRefused | ErrorKind::NotConnected | ErrorKind::TimedOut | ErrorKind::UnexpectedEof) {
+        return true;
+    }
+    // XXX: backends do not always map errors to a kind, so fall back to looking at the message
+    let msg = e.to_string().to_lowercase();
+    ["timed out", "timeout", "broken pipe", "connection reset", "not connected", "connection refused"].iter().any(|m| msg.contains(m))
+}
+
+fn is_stale(e: &Error) -> bool {
+    e.kind() == ErrorKind::StaleNetworkFileHandle || e.raw_os_error() == Some(Errno::ESTALE as i32) || e.to_string().contains("STALE")
+}
+
+fn is_exclusive(flags: u32) -> bool {
+    OFlag::from_bits_truncate(flags as i32).contains(OFlag::O_EXCL)
+}
+
+impl Reconnect {
+    pub(super) fn new(url: String, options: &ConnectOptions, connect: impl Fn(&str, &ConnectOptions) -> Result<Box<dyn NFS>> + Send + Sync + 'static) -> Result<Box<dyn NFS>> {
+        Ok(Box::new(Self::with_connect(url, options, Box::new(connect))?))
+    }
+
+    fn with_connect(url: String, options: &ConnectOptions, connect: Connect) -> Result<Self> {
+        let nfs = connect(&url, options)?;
+        let options = RwLock::new(options.clone());
+        let remount = Remount{url, options, connect, nfs: RwLock::new(Arc::new(RwLock::new(nfs))), generation: RwLock::new(0)};
+        Ok(Reconnect{remount: Arc::new(remount)})
+    }
+
+    fn open_file(&self, retry: impl Fn(&Error) -> bool, reopen: Reopen, op: impl Fn(&mut Box<dyn NFS>, bool) -> Result<Box<dyn NFSFile>>) -> Result<Box<dyn NFSFile>> {
+        let (file, generation, connection) = self.remount.with_retry_on(retry, |generation, connection, retried| {
+            op(&mut connection.write().unwrap(), retried).map(|file| (file, generation, connection.clone()))
+        })?;
+        Ok(Box::new(ReconnectFile{remount: self.remount.clone(), generation: RwLock::new(generation), file: RwLock::new(file), connection: RwLock::new(connection), reopen}))
+    }
+
+    fn open_dir(&self, retry: impl Fn(&Error) -> bool, op: impl Fn(&mut Box<dyn NFS>) -> Result<Box<dyn NFSDirectory>>) -> Result<Box<dyn NFSDirectory>> {
+        let (dir, connection) = self.remount.with_retry_on(retry, |_, connection, _| {
+            op(&mut connection.write().unwrap()).map(|dir| (dir, connection.clone()))
+        })?;
+        Ok(Box::new(ReconnectDirectory{dir, _connection: connection}))
+    }
+}
+
+impl Remount {
+    // Returns current generation along with connection belonging to it.
+    fn current(&self) -> (u64, Connection) {
+        let generation = self.generation.read().unwrap();
+        (*generation, self.nfs.read().unwrap().clone())
+    }
+
+    fn reconnect(&self, generation: u64) -> Result<()> {
+        let mut current = self.generation.write().unwrap();
+        if *current != generation {
+            return Ok(()); // XXX: already reconnected since caller last looked
+        }
+        let nfs = (self.connect)(&self.url, &self.options.read().unwrap())?;
+        *self.nfs.write().unwrap() = Arc::new(RwLock::new(nfs));
+        *current += 1;
+        Ok(())
+    }
+
+    // Calls op until it succeeds, fails with an error that retrying cannot fix, or retries or time run out.
+    // Errors for which retry returns true are retried on a fresh connection, others are returned as is.
+    fn with_retry<T>(&self, retry: impl Fn(&Error) -> bool, mut op: impl FnMut(&mut Box<dyn NFS>, bool) -> Result<T>) -> Result<T> {
+        self.with_retry_on(retry, |_, connection, retried| op(&mut connection.write().unwrap(), retried))
+    }
+
+    // Same as with_retry, except that op is handed generation and connection it runs on, for it to hold on to.
+    fn with_retry_on<T>(&self, retry: impl Fn(&Error) -> bool, mut op: impl FnMut(u64, &Connection, bool) -> Result<T>) -> Result<T> {
+        let options = self.options.read().unwrap().retry.clone();
+        let deadline = options.timeout_ms.map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms.into()));
+        let mut backoff_ms = u64::from(options.backoff_ms);
+        let mut attempt = 0;
+        loop {
+            let (generation, connection) = self.current();
+            let res = op(generation, &connection, attempt > 0);
+            match res {
+                Err(e) if retry(&e) && attempt < options.retries => {
+                    if deadline.is_some_and(|deadline| Instant::now() + Duration::from_millis(backoff_ms) >= deadline) {
+                        return Err(Error::new(ErrorKind::TimedOut, format!("gave up retrying after timeout: {}", e)));
+                    }
+                    std::thread::sleep(Duration::from_millis(backoff_ms));
+                    backoff_ms = (backoff_ms * 2).min(MAX_BACKOFF_MS);
+                    attempt += 1;
+                    // XXX: connection may be fine and server just slow, but re-mounting is cheap compared to a failover
+                    if let Err(e) = self.reconnect(generation) {
+                        if !is_transport_error(&e) || attempt >= options.retries {
+                            return Err(e);
+                        }
+                    }
+                },
+                res => return res,
+            }
+        }
+    }
+
+    // Path based operations re-resolve the path on the new connection, so stale file handles are worth retrying.
+    fn retry_path<T>(&self, op: impl Fn(&mut Box<dyn NFS>) -> Result<T>) -> Result<T> {
+        self.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, _| op(nfs))
+    }
+
+    // File handle based operations cannot recover from a stale file handle, callers need to re-resolve by path.
+    fn retry_fh<T>(&self, op: impl Fn(&mut Box<dyn NFS>) -> Result<T>) -> Result<T> {
+        self.with_retry(is_transport_error, |nfs, _| op(nfs))
+    }
+}
+
+impl NFS for Reconnect {
+    fn access(&self, path: &str, mode: u32) -> Result<u32> {
+        self.remount.retry_path(|nfs| nfs.access(path, mode))
+    }
+
+    fn stat64(&self, path: &str) -> Result<NFSStat64> {
+        self.remount.retry_path(|nfs| nfs.stat64(path))
+    }
+
+    fn lookup_fh(&self, path: &str) -> Result<Vec<u8>> {
+        self.remount.retry_path(|nfs| nfs.lookup_fh(path))
+    }
+
+    fn lookup_in(&self, dir_fh: &[u8], name: &str) -> Result<Vec<u8>> {
+        self.remount.retry_fh(|nfs| nfs.lookup_in(dir_fh, name))
+    }
+
+    fn getattr_fh(&self, fh: &[u8]) -> Result<NFSStat64> {
+        self.remount.retry_fh(|nfs| nfs.getattr_fh(fh))
+    }
+
+    fn set_credentials(&mut self, credentials: &Credentials) -> Result<()> {
+        self.remount.nfs.read().unwrap().write().unwrap().set_credentials(credentials)?;
+        // XXX: remember credentials, so that they are sent again after re-mounting
+        self.remount.options.write().unwrap().credentials = credentials.clone();
+        Ok(())
+    }
+
+    fn readdir_fh(&mut self, fh: &[u8]) -> Result<Box<dyn NFSDirectory>> {
+        self.open_dir(is_transport_error, |nfs| nfs.readdir_fh(fh))
+    }
+
+    fn open_fh(&mut self, fh: &[u8], flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.open_file(is_transport_error, Reopen::Fh(fh.to_vec(), flags), |nfs, _| nfs.open_fh(fh, flags))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.remount.retry_path(|nfs| nfs.readlink(path))
+    }
+
+    fn lchmod(&self, path: &str, mode: u32) -> Result<()> {
+        self.remount.retry_path(|nfs| nfs.lchmod(path, mode))
+    }
+
+    fn opendir(&mut self, path: &str) -> Result<Box<dyn NFSDirectory>> {
+        self.open_dir(|e| is_transport_error(e) || is_stale(e), |nfs| nfs.opendir(path))
+    }
+
+    fn mkdir(&self, path: &str, mode: u32) -> Result<()> {
+        // XXX: if a retried mkdir finds directory already there, then it was most likely created by the lost attempt
+        self.remount.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, retried| match nfs.mkdir(path, mode) {
+            Err(e) if retried && e.kind() == ErrorKind::AlreadyExists => Ok(()),
+            res => res,
+        })
+    }
+
+    fn create(&mut self, path: &str, flags: u32, mode: u32, how: CreateMode) -> Result<Box<dyn NFSFile>> {
+        // XXX: an exclusive create that is retried cannot tell whether the lost attempt created the file or someone
+        //      else did, so report it as existing rather than risk handing over somebody else's file (unless server
+        //      recognizes verifier of lost attempt, in which case retry just succeeds)
+        self.open_file(|e| is_transport_error(e) || is_stale(e), Reopen::after_create(path, flags), |nfs, retried| match nfs.create(path, flags, mode, how) {
+            Err(e) if retried && e.kind() == ErrorKind::AlreadyExists && !is_exclusive(flags) && !how.is_exclusive() => nfs.open(path, flags),
+            res => res,
+        })
+    }
+
+    fn mknod(&self, path: &str, mode: u32, rdev: u64) -> Result<()> {
+        // XXX: if a retried mknod finds name already there, then it was most likely created by the lost attempt
+        self.remount.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, retried| match nfs.mknod(path, mode, rdev) {
+            Err(e) if retried && e.kind() == ErrorKind::AlreadyExists => Ok(()),
+            res => res,
+        })
+    }
+
+    fn rmdir(&self, path: &str) -> Result<()> {
+        // XXX: if a retried remove finds nothing there, then it was most likely removed by the lost attempt
+        self.remount.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, retried| match nfs.rmdir(path) {
+            Err(e) if retried && e.kind() == ErrorKind::NotFound => Ok(()),
+            res => res,
+        })
+    }
+
+    fn unlink(&self, path: &str) -> Result<()> {
+        self.remount.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, retried| match nfs.unlink(path) {
+            Err(e) if retried && e.kind() == ErrorKind::NotFound => Ok(()),
+            res => res,
+        })
+    }
+
+    fn open(&mut self, path: &str, flags: u32) -> Result<Box<dyn NFSFile>> {
+        self.open_file(|e| is_transport_error(e) || is_stale(e), Reopen::after_open(path, flags), |nfs, _| nfs.open(path, flags))
+    }
+
+    fn truncate(&self, path: &str, len: u64) -> Result<()> {
+        self.remount.retry_path(|nfs| nfs.truncate(path, len))
+    }
+
+    fn getxattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.remount.retry_path(|nfs| nfs.getxattr(path, name))
+    }
+
+    fn setxattr(&self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.remount.retry_path(|nfs| nfs.setxattr(path, name, value))
+    }
+
+    fn listxattrs(&self, path: &str) -> Result<Vec<String>> {
+        self.remount.retry_path(|nfs| nfs.listxattrs(path))
+    }
+
+    fn removexattr(&self, path: &str, name: &str) -> Result<()> {
+        // XXX: if a retried remove finds no such attribute, then it was most likely removed by the lost attempt
+        self.remount.with_retry(|e| is_transport_error(e) || is_stale(e), |nfs, retried| match nfs.removexattr(path, name) {
+            Err(e) if retried && e.kind() == ErrorKind::NotFound => Ok(()),
+            res => res,
+        })
+    }
+
+    fn getacl(&self, path: &str) -> Result<Vec<Ace>> {
+        self.remount.retry_path(|nfs| nfs.getacl(path))
+    }
+
+    fn setacl(&self, path: &str, acl: &[Ace]) -> Result<()> {
+        self.remount.retry_path(|nfs| nfs.setacl(path, acl))
+    }
+
+    fn supports_exclusive_create(&self) -> bool {
+        self.remount.nfs.read().unwrap().read().unwrap().supports_exclusive_create()
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.remount.nfs.read().unwrap().read().unwrap().cache_stats()
+    }
+}
+
+// Reopen remembers how a file was opened, so that it can be opened again on another connection without creating or
+// truncating it a second time.
+#[derive(Debug)]
+pub(super) enum Reopen {
+    Path(String, u32),
+    Fh(Vec<u8>, u32),
+}
+
+impl Reopen {
+    pub(super) fn after_open(path: &str, flags: u32) -> Self {
+        let flags = (OFlag::from_bits_truncate(flags as i32) & !OFlag::O_TRUNC).bits() as u32;
+        Reopen::Path(path.to_string(), flags)
+    }
+
+    pub(super) fn after_create(path: &str, flags: u32) -> Self {
+        let flags = (OFlag::from_bits_truncate(flags as i32) & !(OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_TRUNC)).bits() as u32;
+        Reopen::Path(path.to_string(), flags)
+    }
+
+    pub(super) fn open(&self, nfs: &mut Box<dyn NFS>) -> Result<Box<dyn NFSFile>> {
+        match self {
+            Reopen::Path(path, flags) => nfs.open(path, *flags),
+            Reopen::Fh(fh, flags) => nfs.open_fh(fh, *flags),
+        }
+    }
+}
+
+// ReconnectDirectory keeps the connection the directory was opened on alive for as long as the directory is around.
+#[derive(Debug)]
+struct ReconnectDirectory {
+    dir: Box<dyn NFSDirectory>,
+    _connection: Connection, // XXX: declared after dir, so that it gets dropped after it
+}
+
+impl NFSDirectory for ReconnectDirectory {}
+
+impl Iterator for ReconnectDirectory {
+    type Item = Result<NFSDirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.dir.next()
+    }
+}
+
+// ReconnectFile reopens the file on the new connection after Remount re-mounted, since it is bound to the old one,
+// and lets go of the old connection once it has.
+#[derive(Debug)]
+struct ReconnectFile {
+    remount: Arc<Remount>,
+    generation: RwLock<u64>,
+    file: RwLock<Box<dyn NFSFile>>,
+    connection: RwLock<Connection>, // XXX: declared after file, so that it gets dropped after it
+    reopen: Reopen,
+}
+
+impl ReconnectFile {
+    fn with_retry<T>(&self, mut op: impl FnMut(&dyn NFSFile) -> Result<T>) -> Result<T> {
+        self.remount.with_retry_on(|e| is_transport_error(e) || is_stale(e), |generation, connection, _| {
+            let mut nfs = connection.write().unwrap();
+            if *self.generation.read().unwrap() != generation {
+                let file = self.reopen.open(&mut nfs)?;
+                *self.file.write().unwrap() = file;
+                *self.connection.write().unwrap() = connection.clone();
+                *self.generation.write().unwrap() = generation;
+            }
+            op(self.file.read().unwrap().as_ref())
+        })
+    }
+}
+
+impl NFSFile for ReconnectFile {
+    fn fstat64(&self) -> Result<NFSStat64> {
+        self.with_retry(|file| file.fstat64())
+    }
+
+    fn get_max_read_size(&self) -> u64 {
+        self.file.read().unwrap().get_max_read_size()
+    }
+
+    fn pread_into(&self, count: u32, offset: u64, buffer: &mut [u8]) -> Result<u32> {
+        self.with_retry(|file| file.pread_into(count, offset, buffer))
+    }
+
+    fn pwrite(&self, buffer: &[u8], offset: u64) -> Result<u32> {
+        // XXX: writing same data at same offset again is harmless, so retrying is fine
+        self.with_retry(|file| file.pwrite(buffer, offset))
+    }
+
+    fn lock(&self, offset: u64, len: u64, exclusive: bool, wait: bool) -> Result<()> {
+        // XXX: locks do not survive reopening on new connection, so do not pretend to hold them after one
+        self.file.read().unwrap().lock(offset, len, exclusive, wait)
+    }
+
+    fn unlock(&self, offset: u64, len: u64) -> Result<()> {
+        self.file.read().unwrap().unlock(offset, len)
+    }
+
+    fn seek_data(&self, offset: u64) -> Result<u64> {
+        self.with_retry(|file| file.seek_data(offset))
+    }
+
+    fn seek_hole(&self, offset: u64) -> Result<u64> {
+        self.with_retry(|file| file.seek_hole(offset))
+    }
+
+    fn allocate(&self, offset: u64, len: u64) -> Result<()> {
+        self.with_retry(|file| file.allocate(offset, len))
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        self.with_retry(|file| file.punch_hole(offset, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use super::super::{RetryOptions, mock};
+
+    fn options(retries: u32) -> ConnectOptions {
+        ConnectOptions{retry: RetryOptions{retries, timeout_ms: None, backoff_ms: 1}, ..Default::default()}
+    }
+
+    #[test]
+    fn reconnect_recovers_from_broken_transport() {
+        static CONNECTS: AtomicUsize = AtomicUsize::new(0);
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            match CONNECTS.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(mock::NFS3::connect_broken(url.to_string())),
+                _ => Ok(mock::NFS3::connect(url.to_string())),
+            }
+        }
+        let mut nfs = Reconnect::new("nfs://localhost/export".into(), &options(3), connect).unwrap();
+        assert_eq!(nfs.stat64("/annar").unwrap().size, 123);
+        assert_eq!(CONNECTS.load(Ordering::SeqCst), 2);
+        let file = nfs.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        assert_eq!(CONNECTS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn reconnect_drops_old_connection_once_files_let_go_of_it() {
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            Ok(mock::NFS3::connect(url.to_string()))
+        }
+        let mut nfs = Reconnect::with_connect("nfs://localhost/export".into(), &options(3), Box::new(connect)).unwrap();
+        let file = nfs.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        let dir = nfs.opendir("/first/").unwrap();
+        let old = Arc::downgrade(&nfs.remount.current().1);
+        nfs.remount.reconnect(nfs.remount.current().0).unwrap();
+        assert!(old.upgrade().is_some());
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        assert!(old.upgrade().is_some());
+        assert_eq!(dir.count(), 1);
+        assert!(old.upgrade().is_none());
+    }
+
+    #[test]
+    fn reconnect_files_outlive_it() {
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            Ok(mock::NFS3::connect(url.to_string()))
+        }
+        let mut nfs = Reconnect::with_connect("nfs://localhost/export".into(), &options(3), Box::new(connect)).unwrap();
+        let file = nfs.open("/annar", OFlag::O_RDONLY.bits() as u32).unwrap();
+        let remount = Arc::downgrade(&nfs.remount);
+        nfs.remount.reconnect(0).unwrap();
+        drop(nfs);
+        assert_eq!(file.fstat64().unwrap().size, 123);
+        drop(file);
+        assert!(remount.upgrade().is_none());
+    }
+
+    #[test]
+    fn reconnect_gives_up_after_retries() {
+        static CONNECTS: AtomicUsize = AtomicUsize::new(0);
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            let _ = CONNECTS.fetch_add(1, Ordering::SeqCst);
+            Ok(mock::NFS3::connect_broken(url.to_string()))
+        }
+        let nfs = Reconnect::new("nfs://localhost/export".into(), &options(2), connect).unwrap();
+        assert_eq!(nfs.stat64("/annar").unwrap_err().kind(), ErrorKind::BrokenPipe);
+        assert_eq!(CONNECTS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn reconnect_gives_up_after_timeout() {
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            Ok(mock::NFS3::connect_broken(url.to_string()))
+        }
+        let options = ConnectOptions{retry: RetryOptions{retries: 100, timeout_ms: Some(50), backoff_ms: 10}, ..Default::default()};
+        let nfs = Reconnect::new("nfs://localhost/export".into(), &options, connect).unwrap();
+        assert_eq!(nfs.stat64("/annar").unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn reconnect_does_not_retry_other_errors() {
+        static CONNECTS: AtomicUsize = AtomicUsize::new(0);
+        fn connect(url: &str, _options: &ConnectOptions) -> Result<Box<dyn NFS>> {
+            let _ = CONNECTS.fetch_add(1, Ordering::SeqCst);
+            Ok(mock::NFS3::connect(url.to_string()))
+        }
+        let nfs = Reconnect::new("nfs://localhost/export".into(), &options(3), connect).unwrap();
+        assert_eq!(nfs.stat64("/missing").unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(CONNECTS.load(Ordering::SeqCst), 1);
+    }
+}